@@ -6,9 +6,9 @@ use core::fmt::{Debug, Formatter, Result};
 /// Magic number for sanity check
 const EFS_MAGIC: u32 = 0x3b800001;
 /// The max number of direct inodes
-const INODE_DIRECT_COUNT: usize = 28;
-/// The max length of inode name
-const NAME_LENGTH_LIMIT: usize = 27;
+const INODE_DIRECT_COUNT: usize = 21;
+/// The max length of a name in a directory entry
+pub const NAME_LENGTH_LIMIT: usize = 27;
 /// The max number of indirect1 inodes
 const INODE_INDIRECT1_COUNT: usize = BLOCK_SZ / 4;
 /// The max number of indirect2 inodes
@@ -18,8 +18,9 @@ const DIRECT_BOUND: usize = INODE_DIRECT_COUNT;
 /// The upper bound of indirect1 inode index
 const INDIRECT1_BOUND: usize = DIRECT_BOUND + INODE_INDIRECT1_COUNT;
 /// The upper bound of indirect2 inode indexs
-#[allow(unused)]
 const INDIRECT2_BOUND: usize = INDIRECT1_BOUND + INODE_INDIRECT2_COUNT;
+/// The max size of a file, every data block it can index is full
+pub const MAX_FILE_SIZE: usize = INDIRECT2_BOUND * BLOCK_SZ;
 /// Super block of a filesystem
 #[repr(C)]
 pub struct SuperBlock {
@@ -86,17 +87,43 @@ pub struct DiskInode {
     pub indirect1: u32,
     pub indirect2: u32,
     type_: DiskInodeType,
+    /// Number of directory entries referring to this inode
+    pub nlink: u16,
+    /// Permission bits
+    pub mode: u16,
+    /// Time of last access in milliseconds
+    pub atime: u64,
+    /// Time of last data modification in milliseconds
+    pub mtime: u64,
+    /// Time of last status change in milliseconds
+    pub ctime: u64,
 }
 
+// 4 disk inodes fill a block
+const _: () = assert!(core::mem::size_of::<DiskInode>() == BLOCK_SZ / 4);
+
 impl DiskInode {
     /// Initialize a disk inode, as well as all direct inodes under it
     /// indirect1 and indirect2 block are allocated only when they are needed
-    pub fn initialize(&mut self, type_: DiskInodeType) {
+    pub fn initialize(&mut self, type_: DiskInodeType, now: u64) {
         self.size = 0;
         self.direct.iter_mut().for_each(|v| *v = 0);
         self.indirect1 = 0;
         self.indirect2 = 0;
+        self.mode = match type_ {
+            DiskInodeType::File => 0o644,
+            DiskInodeType::Directory => 0o755,
+        };
         self.type_ = type_;
+        self.nlink = 0;
+        self.atime = now;
+        self.mtime = now;
+        self.ctime = now;
+    }
+    /// Record a modification of the data at `now`
+    pub fn touch(&mut self, now: u64) {
+        self.mtime = now;
+        self.ctime = now;
     }
     /// Whether this inode is a directory
     pub fn is_dir(&self) -> bool {
@@ -316,7 +343,7 @@ impl DiskInode {
         block_device: &Arc<dyn BlockDevice>,
     ) -> usize {
         let mut start = offset;
-        let end = (offset + buf.len()).min(self.size as usize);
+        let end = offset.saturating_add(buf.len()).min(self.size as usize);
         if start >= end {
             return 0;
         }
@@ -357,8 +384,10 @@ impl DiskInode {
         block_device: &Arc<dyn BlockDevice>,
     ) -> usize {
         let mut start = offset;
-        let end = (offset + buf.len()).min(self.size as usize);
-        assert!(start <= end);
+        let end = offset.saturating_add(buf.len()).min(self.size as usize);
+        if start >= end {
+            return 0;
+        }
         let mut start_block = start / BLOCK_SZ;
         let mut write_size = 0usize;
         loop {
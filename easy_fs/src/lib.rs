@@ -14,5 +14,6 @@ use bitmap::Bitmap;
 use block_cache::{block_cache_sync_all, get_block_cache};
 pub use block_dev::BlockDevice;
 pub use efs::EasyFileSystem;
+pub use layout::MAX_FILE_SIZE;
 use layout::*;
-pub use vfs::Inode;
+pub use vfs::{Inode, Stat};
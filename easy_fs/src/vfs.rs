@@ -1,11 +1,40 @@
 use super::{
     block_cache_sync_all, get_block_cache, BlockDevice, DirEntry, DiskInode, DiskInodeType,
-    EasyFileSystem, DIRENT_SZ,
+    EasyFileSystem, DIRENT_SZ, MAX_FILE_SIZE, NAME_LENGTH_LIMIT,
 };
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use spin::{Mutex, MutexGuard};
+/// Metadata of an inode, laid out as user space expects it
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stat {
+    /// Inode number
+    pub ino: u64,
+    /// File type and permission bits
+    pub mode: u32,
+    /// Number of hard links
+    pub nlink: u32,
+    /// Size in bytes
+    pub size: u64,
+    /// Time of last access in milliseconds
+    pub atime: u64,
+    /// Time of last data modification in milliseconds
+    pub mtime: u64,
+    /// Time of last status change in milliseconds
+    pub ctime: u64,
+}
+
+impl Stat {
+    /// Mask of the file type bits in `mode`
+    pub const S_IFMT: u32 = 0o170000;
+    /// File type of a directory
+    pub const S_IFDIR: u32 = 0o040000;
+    /// File type of a regular file
+    pub const S_IFREG: u32 = 0o100000;
+}
+
 /// Virtual filesystem layer over easy-fs
 pub struct Inode {
     block_id: usize,
@@ -15,8 +44,8 @@ pub struct Inode {
 }
 
 impl Inode {
-    /// Create a vfs inode
-    pub fn new(
+    /// Create a vfs inode, counted by [`EasyFileSystem::open_inode`]
+    pub(crate) fn new(
         block_id: u32,
         block_offset: usize,
         fs: Arc<Mutex<EasyFileSystem>>,
@@ -60,46 +89,224 @@ impl Inode {
     }
     /// Find inode under current inode by name
     pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
+        let mut fs = self.fs.lock();
+        self.lookup(name)
+            .map(|inode_id| self.inode_by_id(inode_id, &mut fs))
+    }
+    /// Find the inode id of `name` under current inode, None if current
+    /// inode is not a directory
+    fn lookup(&self, name: &str) -> Option<u32> {
+        self.read_disk_inode(|disk_inode| {
+            if !disk_inode.is_dir() {
+                return None;
+            }
+            self.find_inode_id(name, disk_inode)
+        })
+    }
+    /// Get a vfs inode of the same filesystem by inode id
+    fn inode_by_id(&self, inode_id: u32, fs: &mut MutexGuard<EasyFileSystem>) -> Arc<Inode> {
+        let (block_id, block_offset) = fs.open_inode(inode_id);
+        Arc::new(Self::new(
+            block_id,
+            block_offset,
+            self.fs.clone(),
+            self.block_device.clone(),
+        ))
+    }
+    /// Call a function over the disk inode of inode id to modify it, no vfs
+    /// inode is made for it
+    fn modify_disk_inode_by_id<V>(
+        &self,
+        inode_id: u32,
+        fs: &MutexGuard<EasyFileSystem>,
+        f: impl FnOnce(&mut DiskInode) -> V,
+    ) -> V {
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        get_block_cache(block_id as usize, Arc::clone(&self.block_device))
+            .lock()
+            .modify(block_offset, f)
+    }
+    /// Find inode by a path relative to current inode, components are
+    /// separated by `/`
+    pub fn find_path(self: &Arc<Self>, path: &str) -> Option<Arc<Inode>> {
+        path.split('/')
+            .filter(|name| !name.is_empty())
+            .try_fold(self.clone(), |inode, name| inode.find(name))
+    }
+    /// Id of current inode
+    pub fn inode_id(&self) -> u32 {
+        self.inode_id_locked(&self.fs.lock())
+    }
+    /// Whether current inode is a directory
+    pub fn is_dir(&self) -> bool {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.is_dir())
+    }
+    /// Number of directory entries referring to current inode
+    pub fn nlink(&self) -> u32 {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.nlink as u32)
+    }
+    /// Metadata of current inode
+    pub fn stat(&self) -> Stat {
         let fs = self.fs.lock();
+        let ino = self.inode_id_locked(&fs) as u64;
         self.read_disk_inode(|disk_inode| {
-            self.find_inode_id(name, disk_inode).map(|inode_id| {
-                let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
-                Arc::new(Self::new(
-                    block_id,
-                    block_offset,
-                    self.fs.clone(),
-                    self.block_device.clone(),
-                ))
-            })
+            let type_ = if disk_inode.is_dir() {
+                Stat::S_IFDIR
+            } else {
+                Stat::S_IFREG
+            };
+            Stat {
+                ino,
+                mode: type_ | disk_inode.mode as u32,
+                nlink: disk_inode.nlink as u32,
+                size: disk_inode.size as u64,
+                atime: disk_inode.atime,
+                mtime: disk_inode.mtime,
+                ctime: disk_inode.ctime,
+            }
         })
     }
-    /// Increase the size of a disk inode
+    /// Add `delta` to the link count of inode `inode_id`. The inode and its
+    /// data are released once no link is left, an inode that is still open
+    /// lives on until its last vfs inode is dropped.
+    fn adjust_nlink(&self, inode_id: u32, delta: i32, fs: &mut MutexGuard<EasyFileSystem>) {
+        let now = fs.now();
+        let unlinked = self.modify_disk_inode_by_id(inode_id, fs, |disk_inode| {
+            disk_inode.nlink = (disk_inode.nlink as i32 + delta) as u16;
+            disk_inode.ctime = now;
+            disk_inode.nlink == 0
+        });
+        if unlinked && !fs.is_open(inode_id) {
+            self.release(inode_id, fs);
+        }
+    }
+    /// Release inode `inode_id` and its data
+    fn release(&self, inode_id: u32, fs: &mut MutexGuard<EasyFileSystem>) {
+        let data_blocks = self.modify_disk_inode_by_id(inode_id, fs, |disk_inode| {
+            disk_inode.clear_size(&self.block_device)
+        });
+        for data_block in data_blocks {
+            fs.dealloc_data(data_block);
+        }
+        fs.dealloc_inode(inode_id);
+    }
+    /// Increase the size of a disk inode, false if there are not enough free
+    /// blocks and the size is left unchanged
     fn increase_size(
         &self,
         new_size: u32,
         disk_inode: &mut DiskInode,
         fs: &mut MutexGuard<EasyFileSystem>,
-    ) {
+    ) -> bool {
         if new_size < disk_inode.size {
-            return;
+            return true;
         }
         let blocks_needed = disk_inode.blocks_num_needed(new_size);
         let mut v: Vec<u32> = Vec::new();
         for _ in 0..blocks_needed {
-            v.push(fs.alloc_data());
+            let Some(data_block) = fs.alloc_data() else {
+                for data_block in v {
+                    fs.dealloc_data(data_block);
+                }
+                return false;
+            };
+            v.push(data_block);
         }
         disk_inode.increase_size(new_size, v, &self.block_device);
+        true
     }
-    /// Create inode under current inode by name
-    pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+    /// Write `buf` to a file disk inode at `offset`, the part past
+    /// [`MAX_FILE_SIZE`] is left out. Return the size written, 0 if there
+    /// are not enough free blocks
+    fn write_file(
+        &self,
+        offset: usize,
+        buf: &[u8],
+        disk_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) -> usize {
+        let end = offset.saturating_add(buf.len()).min(MAX_FILE_SIZE);
+        if offset >= end || !self.increase_size(end as u32, disk_inode, fs) {
+            return 0;
+        }
+        disk_inode.touch(fs.now());
+        disk_inode.write_at(offset, &buf[..end - offset], &self.block_device)
+    }
+    /// Append a directory entry to a directory disk inode
+    fn append_dirent(
+        &self,
+        name: &str,
+        inode_id: u32,
+        dir_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) {
+        let file_count = (dir_inode.size as usize) / DIRENT_SZ;
+        let new_size = (file_count + 1) * DIRENT_SZ;
+        // increase size
+        assert!(self.increase_size(new_size as u32, dir_inode, fs));
+        // write dirent
+        let dirent = DirEntry::new(name, inode_id);
+        dir_inode.write_at(
+            file_count * DIRENT_SZ,
+            dirent.as_bytes(),
+            &self.block_device,
+        );
+        dir_inode.touch(fs.now());
+    }
+    /// Remove the directory entry `name` from a directory disk inode, the
+    /// last entry takes its slot. Return the inode id of the entry.
+    fn remove_dirent(
+        &self,
+        name: &str,
+        dir_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) -> Option<u32> {
+        let file_count = (dir_inode.size as usize) / DIRENT_SZ;
+        let mut dirents: Vec<DirEntry> = (0..file_count)
+            .map(|i| {
+                let mut dirent = DirEntry::empty();
+                dir_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device);
+                dirent
+            })
+            .collect();
+        let idx = dirents.iter().position(|dirent| dirent.name() == name)?;
+        let removed = dirents.swap_remove(idx);
+        // rewrite the directory, a disk inode can only shrink to nothing
+        for data_block in dir_inode.clear_size(&self.block_device) {
+            fs.dealloc_data(data_block);
+        }
+        assert!(self.increase_size((dirents.len() * DIRENT_SZ) as u32, dir_inode, fs));
+        for (i, dirent) in dirents.iter().enumerate() {
+            dir_inode.write_at(i * DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+        }
+        dir_inode.touch(fs.now());
+        Some(removed.inode_number())
+    }
+    /// Add the `.` and `..` entries to an empty directory
+    pub(crate) fn init_dir(&self, parent_id: u32) {
+        let mut fs = self.fs.lock();
+        let inode_id = self.inode_id_locked(&fs);
+        self.modify_disk_inode(|dir_inode| {
+            self.append_dirent(".", inode_id, dir_inode, &mut fs);
+            self.append_dirent("..", parent_id, dir_inode, &mut fs);
+        });
+        self.adjust_nlink(inode_id, 1, &mut fs);
+        self.adjust_nlink(parent_id, 1, &mut fs);
+        block_cache_sync_all();
+    }
+    /// Create inode of `type_` under current inode by name
+    fn create_inode(&self, name: &str, type_: DiskInodeType) -> Option<Arc<Inode>> {
+        if !Self::valid_name(name) {
+            return None;
+        }
         let mut fs = self.fs.lock();
         let op = |root_inode: &DiskInode| {
-            // assert it is a directory
-            assert!(root_inode.is_dir());
             // has the file been created?
-            self.find_inode_id(name, root_inode)
+            !root_inode.is_dir() || self.find_inode_id(name, root_inode).is_some()
         };
-        if self.read_disk_inode(op).is_some() {
+        if self.read_disk_inode(op) {
             return None;
         }
         // create a new file
@@ -107,37 +314,168 @@ impl Inode {
         let new_inode_id = fs.alloc_inode();
         // initialize inode
         let (new_inode_block_id, new_inode_block_offset) = fs.get_disk_inode_pos(new_inode_id);
+        let now = fs.now();
         get_block_cache(new_inode_block_id as usize, Arc::clone(&self.block_device))
             .lock()
             .modify(new_inode_block_offset, |new_inode: &mut DiskInode| {
-                new_inode.initialize(DiskInodeType::File);
+                new_inode.initialize(type_, now);
             });
         self.modify_disk_inode(|root_inode| {
             // append file in the dirent
-            let file_count = (root_inode.size as usize) / DIRENT_SZ;
-            let new_size = (file_count + 1) * DIRENT_SZ;
-            // increase size
-            self.increase_size(new_size as u32, root_inode, &mut fs);
-            // write dirent
-            let dirent = DirEntry::new(name, new_inode_id);
-            root_inode.write_at(
-                file_count * DIRENT_SZ,
-                dirent.as_bytes(),
-                &self.block_device,
-            );
+            self.append_dirent(name, new_inode_id, root_inode, &mut fs);
         });
+        self.adjust_nlink(new_inode_id, 1, &mut fs);
 
-        let (block_id, block_offset) = fs.get_disk_inode_pos(new_inode_id);
         block_cache_sync_all();
         // return inode
-        Some(Arc::new(Self::new(
-            block_id,
-            block_offset,
-            self.fs.clone(),
-            self.block_device.clone(),
-        )))
+        Some(self.inode_by_id(new_inode_id, &mut fs))
         // release efs lock automatically by compiler
     }
+    /// Whether `name` can be the name of a new directory entry
+    fn valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name.len() <= NAME_LENGTH_LIMIT
+            && !name.contains('/')
+            && name != "."
+            && name != ".."
+    }
+    /// Create a file under current inode by name
+    pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::File)
+    }
+    /// Create a directory under current inode by name
+    pub fn create_dir(&self, name: &str) -> Option<Arc<Inode>> {
+        let dir = self.create_inode(name, DiskInodeType::Directory)?;
+        dir.init_dir(self.inode_id());
+        Some(dir)
+    }
+    /// Remove the empty directory `name` under current inode
+    pub fn remove_dir(&self, name: &str) -> bool {
+        if name == "." || name == ".." {
+            return false;
+        }
+        let mut fs = self.fs.lock();
+        let Some(inode_id) = self.lookup(name) else {
+            return false;
+        };
+        // only `.` and `..` may be left
+        let empty_dir = self.modify_disk_inode_by_id(inode_id, &fs, |disk_inode| {
+            disk_inode.is_dir() && disk_inode.size as usize == 2 * DIRENT_SZ
+        });
+        if !empty_dir {
+            return false;
+        }
+        self.modify_disk_inode(|dir_inode| self.remove_dirent(name, dir_inode, &mut fs));
+        // the entry in current inode and its own `.`
+        self.adjust_nlink(inode_id, -2, &mut fs);
+        // its `..`
+        self.adjust_nlink(self.inode_id_locked(&fs), -1, &mut fs);
+        block_cache_sync_all();
+        true
+    }
+    /// Remove the entry `name` of a file under current inode, the file is
+    /// released with its last link
+    pub fn unlink(&self, name: &str) -> bool {
+        let mut fs = self.fs.lock();
+        let Some(inode_id) = self.lookup(name) else {
+            return false;
+        };
+        if self.modify_disk_inode_by_id(inode_id, &fs, |disk_inode| disk_inode.is_dir()) {
+            return false;
+        }
+        self.modify_disk_inode(|dir_inode| self.remove_dirent(name, dir_inode, &mut fs));
+        self.adjust_nlink(inode_id, -1, &mut fs);
+        block_cache_sync_all();
+        true
+    }
+    /// Create the entry `name` under current inode referring to the file
+    /// `inode`, directories can't be linked
+    pub fn link(&self, name: &str, inode: &Inode) -> bool {
+        if !Self::valid_name(name) {
+            return false;
+        }
+        let mut fs = self.fs.lock();
+        if !self.read_disk_inode(|dir_inode| dir_inode.is_dir())
+            || self.lookup(name).is_some()
+            || inode.read_disk_inode(|disk_inode| disk_inode.is_dir())
+        {
+            return false;
+        }
+        let inode_id = inode.inode_id_locked(&fs);
+        self.modify_disk_inode(|dir_inode| {
+            self.append_dirent(name, inode_id, dir_inode, &mut fs);
+        });
+        self.adjust_nlink(inode_id, 1, &mut fs);
+        block_cache_sync_all();
+        true
+    }
+    /// Move the entry `old_name` under current inode to `new_name` under the
+    /// directory `new_dir`. An existing file at `new_name` is replaced when
+    /// a file is moved, a directory can't be moved into itself.
+    pub fn rename(&self, old_name: &str, new_dir: &Inode, new_name: &str) -> bool {
+        if old_name == "." || old_name == ".." || !Self::valid_name(new_name) {
+            return false;
+        }
+        let mut fs = self.fs.lock();
+        let Some(inode_id) = self.lookup(old_name) else {
+            return false;
+        };
+        if !new_dir.read_disk_inode(|dir_inode| dir_inode.is_dir()) {
+            return false;
+        }
+        let is_dir = self.modify_disk_inode_by_id(inode_id, &fs, |disk_inode| disk_inode.is_dir());
+        let old_dir_id = self.inode_id_locked(&fs);
+        let new_dir_id = new_dir.inode_id_locked(&fs);
+        if is_dir {
+            // walk up from the new parent to the root
+            let mut ancestor_id = new_dir_id;
+            loop {
+                if ancestor_id == inode_id {
+                    return false;
+                }
+                if ancestor_id == 0 {
+                    break;
+                }
+                ancestor_id = self
+                    .modify_disk_inode_by_id(ancestor_id, &fs, |dir_inode| {
+                        self.find_inode_id("..", dir_inode)
+                    })
+                    .unwrap();
+            }
+        }
+        if let Some(target_id) = new_dir.lookup(new_name) {
+            if target_id == inode_id {
+                return true;
+            }
+            if is_dir
+                || self.modify_disk_inode_by_id(target_id, &fs, |disk_inode| disk_inode.is_dir())
+            {
+                return false;
+            }
+            new_dir
+                .modify_disk_inode(|dir_inode| new_dir.remove_dirent(new_name, dir_inode, &mut fs));
+            self.adjust_nlink(target_id, -1, &mut fs);
+        }
+        self.modify_disk_inode(|dir_inode| self.remove_dirent(old_name, dir_inode, &mut fs));
+        new_dir.modify_disk_inode(|dir_inode| {
+            new_dir.append_dirent(new_name, inode_id, dir_inode, &mut fs);
+        });
+        if is_dir && old_dir_id != new_dir_id {
+            // `..` always stays the second entry
+            self.modify_disk_inode_by_id(inode_id, &fs, |dir_inode| {
+                let dirent = DirEntry::new("..", new_dir_id);
+                dir_inode.write_at(DIRENT_SZ, dirent.as_bytes(), &self.block_device);
+            });
+            self.adjust_nlink(old_dir_id, -1, &mut fs);
+            self.adjust_nlink(new_dir_id, 1, &mut fs);
+        }
+        block_cache_sync_all();
+        true
+    }
+    /// Id of current inode while the fs lock is held
+    fn inode_id_locked(&self, fs: &MutexGuard<EasyFileSystem>) -> u32 {
+        fs.get_inode_id(self.block_id as u32, self.block_offset)
+    }
     /// List inodes under current inode
     pub fn ls(&self) -> Vec<String> {
         let _fs = self.fs.lock();
@@ -155,21 +493,39 @@ impl Inode {
             v
         })
     }
+    /// Size of the data in current inode
+    pub fn size(&self) -> usize {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.size as usize)
+    }
     /// Read data from current inode
     pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
-        let _fs = self.fs.lock();
-        self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
+        let fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            disk_inode.atime = fs.now();
+            disk_inode.read_at(offset, buf, &self.block_device)
+        })
     }
-    /// Write data to current inode
+    /// Write data to current inode, return the size written. It falls short
+    /// at [`MAX_FILE_SIZE`] and is 0 if the disk is full
     pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
         let mut fs = self.fs.lock();
-        let size = self.modify_disk_inode(|disk_inode| {
-            self.increase_size((offset + buf.len()) as u32, disk_inode, &mut fs);
-            disk_inode.write_at(offset, buf, &self.block_device)
-        });
+        let size =
+            self.modify_disk_inode(|disk_inode| self.write_file(offset, buf, disk_inode, &mut fs));
         block_cache_sync_all();
         size
     }
+    /// Write data at the end of current inode in one step, return the offset
+    /// it was written at and the size written like [`Inode::write_at`]
+    pub fn append(&self, buf: &[u8]) -> (usize, usize) {
+        let mut fs = self.fs.lock();
+        let written = self.modify_disk_inode(|disk_inode| {
+            let offset = disk_inode.size as usize;
+            (offset, self.write_file(offset, buf, disk_inode, &mut fs))
+        });
+        block_cache_sync_all();
+        written
+    }
     /// Clear the data in current inode
     pub fn clear(&self) {
         let mut fs = self.fs.lock();
@@ -180,7 +536,19 @@ impl Inode {
             for data_block in data_blocks_dealloc.into_iter() {
                 fs.dealloc_data(data_block);
             }
+            disk_inode.touch(fs.now());
         });
         block_cache_sync_all();
     }
 }
+
+impl Drop for Inode {
+    /// The last vfs inode of an inode without links releases it
+    fn drop(&mut self) {
+        let mut fs = self.fs.lock();
+        let inode_id = self.inode_id_locked(&fs);
+        if fs.close_inode(inode_id) && self.read_disk_inode(|disk_inode| disk_inode.nlink == 0) {
+            self.release(inode_id, &mut fs);
+        }
+    }
+}
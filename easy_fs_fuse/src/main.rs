@@ -99,6 +99,11 @@ fn easy_fs_pack() -> std::io::Result<()> {
     Ok(())
 }
 
+#[cfg(test)]
+use easy_fs::{Stat, MAX_FILE_SIZE};
+#[cfg(test)]
+use std::sync::atomic::{AtomicU64, Ordering};
+
 #[test]
 fn efs_test() -> std::io::Result<()> {
     let block_file = Arc::new(BlockFile(Mutex::new({
@@ -112,6 +117,10 @@ fn efs_test() -> std::io::Result<()> {
     })));
     EasyFileSystem::create(block_file.clone(), 4096, 1);
     let efs = EasyFileSystem::open(block_file.clone());
+    // every reading of the clock is a new millisecond
+    static TICKS: AtomicU64 = AtomicU64::new(1);
+    efs.lock()
+        .set_clock(|| TICKS.fetch_add(1, Ordering::Relaxed));
     let root_inode = EasyFileSystem::root_inode(&efs);
     root_inode.create("filea");
     root_inode.create("fileb");
@@ -159,5 +168,125 @@ fn efs_test() -> std::io::Result<()> {
     random_str_test(1000 * BLOCK_SZ);
     random_str_test(2000 * BLOCK_SZ);
 
+    // directories
+    let root_inode = Arc::new(root_inode);
+    let dir = root_inode.create_dir("dir").unwrap();
+    assert!(dir.is_dir());
+    assert!(root_inode.create_dir("dir").is_none());
+    assert_eq!(dir.ls(), [".", ".."]);
+    let sub = dir.create_dir("sub").unwrap();
+    let file = sub.create("file").unwrap();
+    file.write_at(0, greet_str.as_bytes());
+    let found = root_inode.find_path("/dir/./sub/../sub/file").unwrap();
+    assert_eq!(found.inode_id(), file.inode_id());
+    assert_eq!(
+        root_inode.find_path("dir/sub/..").unwrap().inode_id(),
+        dir.inode_id()
+    );
+    assert_eq!(root_inode.find_path("..").unwrap().inode_id(), 0);
+    assert!(root_inode.find_path("dir/sub/file/x").is_none());
+    assert!(file.create("x").is_none());
+    // only empty directories can be removed
+    assert!(!dir.remove_dir("sub"));
+    assert!(!root_inode.remove_dir("."));
+    let empty = dir.create_dir("empty").unwrap();
+    let empty_id = empty.inode_id();
+    assert!(dir.remove_dir("empty"));
+    assert!(dir.find("empty").is_none());
+    assert_eq!(dir.ls(), [".", "..", "sub"]);
+    // the inode is reused once it is no longer open
+    drop(empty);
+    assert_eq!(dir.create_dir("again").unwrap().inode_id(), empty_id);
+
+    // links
+    assert_eq!(root_inode.nlink(), 3);
+    assert_eq!(dir.nlink(), 4);
+    assert_eq!(file.nlink(), 1);
+    assert!(root_inode.link("hard", &file));
+    assert!(!root_inode.link("hard", &file));
+    assert!(!root_inode.link("dirlink", &dir));
+    assert_eq!(file.nlink(), 2);
+    assert!(sub.unlink("file"));
+    assert!(sub.find("file").is_none());
+    assert!(!dir.unlink("sub"));
+    let hard = root_inode.find("hard").unwrap();
+    assert_eq!(hard.nlink(), 1);
+    let len = hard.read_at(0, &mut buffer);
+    assert_eq!(greet_str, core::str::from_utf8(&buffer[..len]).unwrap());
+    // the last link releases the inode once it is no longer open
+    let hard_id = hard.inode_id();
+    drop((file, found));
+    assert!(root_inode.unlink("hard"));
+    assert_eq!(hard.nlink(), 0);
+    let len = hard.read_at(0, &mut buffer);
+    assert_eq!(greet_str, core::str::from_utf8(&buffer[..len]).unwrap());
+    assert_ne!(root_inode.create("other").unwrap().inode_id(), hard_id);
+    assert!(root_inode.unlink("other"));
+    drop(hard);
+    assert_eq!(root_inode.create("new").unwrap().inode_id(), hard_id);
+
+    // rename
+    let ls_before = root_inode.ls();
+    assert!(root_inode.rename("new", &root_inode, "new"));
+    assert_eq!(root_inode.ls(), ls_before);
+    assert!(root_inode.rename("new", &sub, "moved"));
+    assert_eq!(sub.find("moved").unwrap().inode_id(), hard_id);
+    assert!(root_inode.find("new").is_none());
+    // a file replaces a file but not a directory
+    assert!(!sub.rename("moved", &dir, "again"));
+    assert!(sub.rename("moved", &root_inode, "filea"));
+    assert_eq!(root_inode.find("filea").unwrap().inode_id(), hard_id);
+    // a directory can't move into itself
+    assert!(!root_inode.rename("dir", &sub, "dir"));
+    assert!(dir.rename("sub", &root_inode, "sub"));
+    assert_eq!(dir.nlink(), 3);
+    assert_eq!(root_inode.nlink(), 4);
+    assert_eq!(
+        root_inode.find_path("sub/..").unwrap().inode_id(),
+        root_inode.inode_id()
+    );
+    assert!(root_inode.remove_dir("sub"));
+    assert_eq!(root_inode.nlink(), 3);
+
+    // stat
+    let file = root_inode.create("stat").unwrap();
+    let created = file.stat();
+    assert_eq!(created.mode, Stat::S_IFREG | 0o644);
+    assert_eq!((created.nlink, created.size), (1, 0));
+    assert_eq!(created.ino, file.inode_id() as u64);
+    assert!(created.atime > 0 && created.atime == created.mtime);
+    file.write_at(0, greet_str.as_bytes());
+    let written = file.stat();
+    assert_eq!(written.size, greet_str.len() as u64);
+    assert!(written.mtime > created.mtime && written.ctime == written.mtime);
+    assert_eq!(written.atime, created.atime);
+    file.read_at(0, &mut buffer);
+    let read = file.stat();
+    assert!(read.atime > written.mtime && read.mtime == written.mtime);
+    assert!(root_inode.link("stat2", &file));
+    let linked = file.stat();
+    assert_eq!(linked.nlink, 2);
+    assert!(linked.ctime > linked.mtime);
+    let dir_stat = dir.stat();
+    assert_eq!(dir_stat.mode & Stat::S_IFMT, Stat::S_IFDIR);
+    assert_eq!(dir_stat.mode & 0o777, 0o755);
+    assert!(root_inode.stat().mtime > read.atime);
+
+    // append
+    assert_eq!(file.append(b"!!"), (greet_str.len(), 2));
+    assert_eq!(file.append(b"?"), (greet_str.len() + 2, 1));
+    let len = file.read_at(0, &mut buffer);
+    assert_eq!(&buffer[..len], b"Hello, world!!!?");
+
+    // the max file size does not fit on this disk, a failed write leaves no
+    // block behind
+    assert_eq!(file.write_at(MAX_FILE_SIZE, b"x"), 0);
+    assert_eq!(file.write_at(usize::MAX, b"x"), 0);
+    assert_eq!(file.write_at(MAX_FILE_SIZE - 1, b"xy"), 0);
+    assert_eq!(file.size(), len);
+    assert_eq!(file.read_at(usize::MAX, &mut buffer), 0);
+    let data = vec![1u8; 1000 * BLOCK_SZ];
+    assert_eq!(file.write_at(len, &data), data.len());
+
     Ok(())
 }
@@ -11,5 +11,11 @@ pub const PAGE_SIZE: usize = CONS_4K;
 pub const PAGE_SIZE_BITS: usize = 12;
 
 pub const KERNEL_SPACE_OFFSET: usize = 0xffff_ffc0_0000_0000;
+pub const USER_SPACE_MAX: usize = usize::MAX - KERNEL_SPACE_OFFSET;
+/// User space starts above the low 2 GiB, every address space shares the
+/// kernel's identity maps of the devices there.
+pub const USER_SPACE_MIN: usize = 0x8000_0000;
+/// Where `mmap` starts looking for room when not given an address.
+pub const MMAP_BASE: usize = 0x20_0000_0000;
 
 pub use crate::config::board::qemu::*;
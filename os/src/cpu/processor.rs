@@ -1,8 +1,5 @@
-use crate::{
-    cpu_local,
-    lang_items::print_backtrace,
-    task::{self, schedule::add_task},
-};
+use crate::mm::UserPtr;
+use crate::{cpu_local, println, task};
 use alloc::sync::Arc;
 use log::{debug, info, trace};
 use spin::Once;
@@ -18,6 +15,9 @@ pub struct Processor {
     current: Option<Arc<Task>>,
     ///The basic control flow of each core, helping to select and switch process
     idle_task_cx: TaskContext,
+    /// A detached thread that exited on this processor, it is reaped by the
+    /// task switched to once its kernel stack is no longer in use
+    exited: Option<Arc<Task>>,
 }
 
 impl Processor {
@@ -26,6 +26,7 @@ impl Processor {
         Self {
             current: None,
             idle_task_cx: TaskContext::default(),
+            exited: None,
         }
     }
 
@@ -39,38 +40,54 @@ impl Processor {
         current.get_mutable_inner().status = task::TaskStatus::Ready;
     }
 
+    /// End the process of the current task with all its threads
     pub fn exit_current(&self, exit_code: i32) {
         trace!("exit_current: {}", exit_code);
         let current = self.current().unwrap();
-        current.get_mutable_inner().exit_code = exit_code;
-        current.get_mutable_inner().status = task::TaskStatus::Zombie;
-        for task in current.get_inner().waiting_tasks.iter() {
-            add_task(task.clone());
+        let process = current.get_inner().process.upgrade().unwrap();
+        process.exit_process(exit_code);
+    }
+
+    /// End the current thread only, the main thread carries the process
+    /// and ends it
+    pub fn exit_current_thread(&self, exit_code: i32) {
+        let current = self.current().unwrap();
+        if current.tid == 0 {
+            self.exit_current(exit_code);
+        } else {
+            current.exit_thread(exit_code);
         }
-        current.get_mutable_inner().waiting_tasks.clear();
     }
 
     pub fn abort_current(&self) {
         let current = self.current().unwrap();
         let fp = current.get_inner().user_ctx.general.s0;
         let ra: usize = current.get_inner().user_ctx.sepc;
-        if fp != 0 {
-            unsafe {
-                info!(
-                    "Aborting task {} fp: {:x} ra: {:x}",
-                    current.taskid.value,
-                    *(fp as *mut usize),
-                    ra
-                );
-                print_backtrace(fp, ra);
-            }
-        }
+        info!("Aborting task {} ra: {:x}", current.taskid.value, ra);
+        print_user_backtrace(fp, ra);
         self.exit_current(i32::MIN);
     }
 
+    /// Reap the detached thread that exited before the switch to the
+    /// current task
+    pub fn reap_exited(&mut self) {
+        let Some(thread) = self.exited.take() else {
+            return;
+        };
+        if let Some(process) = thread.get_inner().process.upgrade() {
+            process.get_mutable_inner().reap_thread(thread.tid);
+        }
+    }
+
     pub fn switch_to_task(&mut self, next_task: Arc<Task>) {
-        let mut old_ctx = match self.current.take().map(|task| task.get_inner().task_ctx) {
-            Some(ctx) => ctx,
+        let mut old_ctx = match self.current.take() {
+            Some(task) => {
+                let inner = task.get_inner();
+                if inner.detached && inner.status == task::TaskStatus::Zombie {
+                    self.exited = Some(task.clone());
+                }
+                inner.task_ctx
+            }
             None => self.idle_task_cx,
         };
         self.current = Some(next_task.clone());
@@ -87,6 +104,25 @@ impl Processor {
     }
 }
 
+/// Frames of a user backtrace at most, the frame pointers may form a loop
+const USER_BACKTRACE_DEPTH: usize = 32;
+
+/// Walk the frame pointers of the current task from `fp` like
+/// [`print_backtrace`](crate::lang_items::print_backtrace), the frames are
+/// user memory and are read like the arguments of a syscall
+fn print_user_backtrace(mut fp: usize, pc: usize) {
+    println!("\nuser backtrace:");
+    println!("\t0:\t{:#x}", pc);
+    for idx in 1..USER_BACKTRACE_DEPTH {
+        // the caller's frame pointer and the return address
+        let Ok([prev_fp, ra]) = UserPtr::<[usize; 2]>::new(fp.wrapping_sub(16)).read() else {
+            break;
+        };
+        println!("\t{}:\t{:#x}", idx, ra.saturating_sub(4));
+        fp = prev_fp;
+    }
+}
+
 pub fn switch_to_task(task: Arc<Task>) {
     PROCESSOR.as_mut().switch_to_task(task);
 }
@@ -4,14 +4,18 @@
 //!
 //! `Mutex<OSInodeInner>` -> `OSInode`: for static `ROOT_INODE`,we
 //! need to wrap `OSInodeInner` into `Mutex`
-use super::File;
+use super::{File, SeekFrom};
+use crate::config::PAGE_SIZE;
 use crate::drivers::BLOCK_DEVICE;
 use crate::mm::UserBuffer;
 use crate::println;
+use crate::syscall::{SysError, SysResult};
+use crate::timer::get_time_ms;
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use bitflags::*;
-use easy_fs::{EasyFileSystem, Inode};
+use easy_fs::{EasyFileSystem, Inode, MAX_FILE_SIZE, Stat};
 use lazy_static::*;
 use spin::Mutex;
 /// A wrapper around a filesystem inode
@@ -19,6 +23,8 @@ use spin::Mutex;
 pub struct OSInode {
     readable: bool,
     writable: bool,
+    /// Every write goes to the end of the file
+    append: bool,
     inner: Mutex<OSInodeInner>,
 }
 /// The OS inode inner in 'Mutex'
@@ -29,10 +35,11 @@ pub struct OSInodeInner {
 
 impl OSInode {
     /// Construct an OS inode from a inode
-    pub fn new(readable: bool, writable: bool, inode: Arc<Inode>) -> Self {
+    pub fn new(readable: bool, writable: bool, append: bool, inode: Arc<Inode>) -> Self {
         Self {
             readable,
             writable,
+            append,
             inner: Mutex::new(OSInodeInner { offset: 0, inode }),
         }
     }
@@ -53,9 +60,57 @@ impl OSInode {
     }
 }
 
+impl OSInodeInner {
+    /// Read the inode at `offset` to `buf`, a page at a time
+    fn read_at(&self, mut offset: usize, buf: UserBuffer) -> usize {
+        let mut chunk = [0u8; PAGE_SIZE];
+        let mut total_read_size = 0usize;
+        while total_read_size < buf.len() {
+            let len = (buf.len() - total_read_size).min(PAGE_SIZE);
+            let read_size = self.inode.read_at(offset, &mut chunk[..len]);
+            if read_size == 0 {
+                break;
+            }
+            buf.write(total_read_size, &chunk[..read_size]);
+            offset += read_size;
+            total_read_size += read_size;
+        }
+        total_read_size
+    }
+    /// Write `buf` to the inode at `offset`, a page at a time. The write
+    /// falls short at the max file size or once the disk is full
+    fn write_at(&self, mut offset: usize, buf: UserBuffer) -> SysResult {
+        let mut chunk = [0u8; PAGE_SIZE];
+        let mut total_write_size = 0usize;
+        while total_write_size < buf.len() {
+            let len = buf.read(total_write_size, &mut chunk);
+            let write_size = self.inode.write_at(offset, &chunk[..len]);
+            offset += write_size;
+            total_write_size += write_size;
+            if write_size < len {
+                break;
+            }
+        }
+        match total_write_size {
+            0 if buf.len() > 0 => Err(write_error(offset)),
+            _ => Ok(total_write_size),
+        }
+    }
+}
+
+/// Why nothing could be written at `offset`
+fn write_error(offset: usize) -> SysError {
+    if offset >= MAX_FILE_SIZE {
+        SysError::EFBIG
+    } else {
+        SysError::ENOSPC
+    }
+}
+
 lazy_static! {
     pub static ref ROOT_INODE: Arc<Inode> = {
         let efs = EasyFileSystem::open(BLOCK_DEVICE.clone());
+        efs.lock().set_clock(|| get_time_ms() as u64);
         Arc::new(EasyFileSystem::root_inode(&efs))
     };
 }
@@ -81,6 +136,10 @@ bitflags! {
         const CREATE = 1 << 9;
         ///Clear file and return an empty one
         const TRUNC = 1 << 10;
+        ///Move to the end of file before each write
+        const APPEND = 1 << 11;
+        ///Close the file descriptor on exec
+        const CLOEXEC = 1 << 19;
     }
 }
 
@@ -97,30 +156,122 @@ impl OpenFlags {
         }
     }
 }
-///Open file with flags
-pub fn open_file(name: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
+/// Join `path` to the absolute directory `cwd` and resolve `.` and `..`,
+/// the result is absolute without a trailing `/`.
+pub fn absolute_path(cwd: &str, path: &str) -> String {
+    let base = if path.starts_with('/') { "" } else { cwd };
+    let mut components: Vec<&str> = Vec::new();
+    for name in base.split('/').chain(path.split('/')) {
+        match name {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            name => components.push(name),
+        }
+    }
+    let mut path = String::new();
+    for name in components {
+        path.push('/');
+        path.push_str(name);
+    }
+    if path.is_empty() {
+        path.push('/');
+    }
+    path
+}
+
+/// Split a path into its parent directory and last component
+fn split_path(path: &str) -> (&str, &str) {
+    path.rsplit_once('/').unwrap_or(("", path))
+}
+
+///Open file with flags, directories can only be opened for reading
+pub fn open_file(path: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
     let (readable, writable) = flags.read_write();
-    if flags.contains(OpenFlags::CREATE) {
-        if let Some(inode) = ROOT_INODE.find(name) {
+    let append = flags.contains(OpenFlags::APPEND);
+    if let Some(inode) = ROOT_INODE.find_path(path) {
+        if writable && inode.is_dir() {
+            return None;
+        }
+        if flags.contains(OpenFlags::TRUNC) {
             // clear size
             inode.clear();
-            Some(Arc::new(OSInode::new(readable, writable, inode)))
-        } else {
-            // create file
-            ROOT_INODE
-                .create(name)
-                .map(|inode| Arc::new(OSInode::new(readable, writable, inode)))
         }
+        Some(Arc::new(OSInode::new(readable, writable, append, inode)))
+    } else if flags.contains(OpenFlags::CREATE) {
+        // create file
+        let (parent, name) = split_path(path);
+        ROOT_INODE
+            .find_path(parent)?
+            .create(name)
+            .map(|inode| Arc::new(OSInode::new(readable, writable, append, inode)))
     } else {
-        ROOT_INODE.find(name).map(|inode| {
-            if flags.contains(OpenFlags::TRUNC) {
-                inode.clear();
-            }
-            Arc::new(OSInode::new(readable, writable, inode))
-        })
+        None
+    }
+}
+
+/// Create a directory at an absolute path
+pub fn create_dir(path: &str) -> bool {
+    let (parent, name) = split_path(path);
+    ROOT_INODE
+        .find_path(parent)
+        .and_then(|parent| parent.create_dir(name))
+        .is_some()
+}
+
+/// Remove the empty directory at an absolute path
+pub fn remove_dir(path: &str) -> bool {
+    let (parent, name) = split_path(path);
+    ROOT_INODE
+        .find_path(parent)
+        .is_some_and(|parent| parent.remove_dir(name))
+}
+
+/// Remove the link to a file at an absolute path
+pub fn unlink_file(path: &str) -> bool {
+    let (parent, name) = split_path(path);
+    ROOT_INODE
+        .find_path(parent)
+        .is_some_and(|parent| parent.unlink(name))
+}
+
+/// Create a link at `new_path` to the file at `old_path`, both absolute
+pub fn link_file(old_path: &str, new_path: &str) -> bool {
+    let Some(inode) = ROOT_INODE.find_path(old_path) else {
+        return false;
+    };
+    let (parent, name) = split_path(new_path);
+    ROOT_INODE
+        .find_path(parent)
+        .is_some_and(|parent| parent.link(name, &inode))
+}
+
+/// Move a file or directory from `old_path` to `new_path`, both absolute
+pub fn rename_file(old_path: &str, new_path: &str) -> bool {
+    let (old_parent, old_name) = split_path(old_path);
+    let (new_parent, new_name) = split_path(new_path);
+    match (
+        ROOT_INODE.find_path(old_parent),
+        ROOT_INODE.find_path(new_parent),
+    ) {
+        (Some(old_parent), Some(new_parent)) => old_parent.rename(old_name, &new_parent, new_name),
+        _ => false,
     }
 }
 
+/// Metadata of the file or directory at an absolute path
+pub fn stat_file(path: &str) -> Option<Stat> {
+    ROOT_INODE.find_path(path).map(|inode| inode.stat())
+}
+
+/// Whether there is a directory at an absolute path
+pub fn is_dir(path: &str) -> bool {
+    ROOT_INODE
+        .find_path(path)
+        .is_some_and(|inode| inode.is_dir())
+}
+
 impl File for OSInode {
     fn readable(&self) -> bool {
         self.readable
@@ -128,28 +279,45 @@ impl File for OSInode {
     fn writable(&self) -> bool {
         self.writable
     }
-    fn read(&self, mut buf: UserBuffer) -> usize {
+    fn read(&self, buf: UserBuffer) -> usize {
         let mut inner = self.inner.lock();
-        let mut total_read_size = 0usize;
-        for slice in buf.buffers.iter_mut() {
-            let read_size = inner.inode.read_at(inner.offset, *slice);
-            if read_size == 0 {
-                break;
-            }
-            inner.offset += read_size;
-            total_read_size += read_size;
-        }
-        total_read_size
+        let read_size = inner.read_at(inner.offset, buf);
+        inner.offset += read_size;
+        read_size
     }
-    fn write(&self, buf: UserBuffer) -> usize {
+    fn write(&self, buf: UserBuffer) -> SysResult {
         let mut inner = self.inner.lock();
-        let mut total_write_size = 0usize;
-        for slice in buf.buffers.iter() {
-            let write_size = inner.inode.write_at(inner.offset, *slice);
-            assert_eq!(write_size, slice.len());
-            inner.offset += write_size;
-            total_write_size += write_size;
+        if self.append {
+            // find the end and write in one step
+            let data = buf.to_vec();
+            let (offset, write_size) = inner.inode.append(&data);
+            inner.offset = offset + write_size;
+            return match write_size {
+                0 if !data.is_empty() => Err(write_error(offset)),
+                _ => Ok(write_size),
+            };
         }
-        total_write_size
+        let write_size = inner.write_at(inner.offset, buf)?;
+        inner.offset += write_size;
+        Ok(write_size)
+    }
+    fn seek(&self, pos: SeekFrom) -> Option<usize> {
+        let mut inner = self.inner.lock();
+        let offset = match pos {
+            SeekFrom::Start(offset) => offset,
+            SeekFrom::Current(delta) => inner.offset.checked_add_signed(delta)?,
+            SeekFrom::End(delta) => inner.inode.size().checked_add_signed(delta)?,
+        };
+        inner.offset = Some(offset).filter(|&offset| offset <= MAX_FILE_SIZE)?;
+        Some(inner.offset)
+    }
+    fn read_at(&self, offset: usize, buf: UserBuffer) -> Option<usize> {
+        Some(self.inner.lock().read_at(offset, buf))
+    }
+    fn write_at(&self, offset: usize, buf: UserBuffer) -> SysResult {
+        self.inner.lock().write_at(offset, buf)
+    }
+    fn inode(&self) -> Option<Arc<Inode>> {
+        Some(self.inner.lock().inode.clone())
     }
 }
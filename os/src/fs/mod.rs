@@ -1,8 +1,29 @@
 //! File system in os
 mod inode;
+mod pipe;
 mod stdio;
+mod tty;
+
+use crate::mm::{Pod, UserBuffer};
+use crate::syscall::{SysError, SysResult};
+use alloc::sync::Arc;
+use bitflags::*;
+use core::ops::Deref;
+use easy_fs::Inode;
+pub use easy_fs::Stat;
+
+unsafe impl Pod for Stat {}
+
+/// Where `File::seek` moves the offset to
+pub enum SeekFrom {
+    /// From the start of the file
+    Start(usize),
+    /// From the current offset
+    Current(isize),
+    /// From the end of the file
+    End(isize),
+}
 
-use crate::mm::UserBuffer;
 /// File trait
 pub trait File: Send + Sync {
     /// If readable
@@ -11,9 +32,72 @@ pub trait File: Send + Sync {
     fn writable(&self) -> bool;
     /// Read file to `UserBuffer`
     fn read(&self, buf: UserBuffer) -> usize;
-    /// Write `UserBuffer` to file
-    fn write(&self, buf: UserBuffer) -> usize;
+    /// Write `UserBuffer` to file, `EPIPE` if nothing can read it any more
+    fn write(&self, buf: UserBuffer) -> SysResult;
+    /// Move the offset of the file and return the new one, None if the file
+    /// is not seekable or the offset would be negative or past the max file
+    /// size
+    fn seek(&self, _pos: SeekFrom) -> Option<usize> {
+        None
+    }
+    /// Read file at `offset` to `UserBuffer` without moving the offset of
+    /// the file, None if the file is not seekable
+    fn read_at(&self, _offset: usize, _buf: UserBuffer) -> Option<usize> {
+        None
+    }
+    /// Write `UserBuffer` to file at `offset` without moving the offset of
+    /// the file, `ESPIPE` if the file is not seekable
+    fn write_at(&self, _offset: usize, _buf: UserBuffer) -> SysResult {
+        Err(SysError::ESPIPE)
+    }
+    /// The inode behind the file, if it can be mapped into memory
+    fn inode(&self) -> Option<Arc<Inode>> {
+        None
+    }
+    /// Run the device request `cmd` with `arg`, `ENOTTY` if the file does
+    /// not support it
+    fn ioctl(&self, _cmd: usize, _arg: usize) -> SysResult {
+        Err(SysError::ENOTTY)
+    }
+}
+
+bitflags! {
+    /// Flags of a file descriptor, `FD_*`
+    #[derive(Clone, Copy)]
+    pub struct FdFlags: u32 {
+        /// Close the descriptor on exec
+        const CLOEXEC = 1;
+    }
+}
+
+/// An entry of the fd table, the flags belong to this descriptor only and
+/// are not shared with its duplicates
+#[derive(Clone)]
+pub struct FileDescriptor {
+    /// The open file
+    pub file: Arc<dyn File + Send + Sync>,
+    /// Flags of the descriptor
+    pub flags: FdFlags,
+}
+
+impl FileDescriptor {
+    /// Create a descriptor of `file`
+    pub fn new(file: Arc<dyn File + Send + Sync>, flags: FdFlags) -> Self {
+        Self { file, flags }
+    }
+}
+
+impl Deref for FileDescriptor {
+    type Target = Arc<dyn File + Send + Sync>;
+    fn deref(&self) -> &Self::Target {
+        &self.file
+    }
 }
 
-pub use inode::{OpenFlags, list_apps, open_file};
+pub use inode::{
+    OpenFlags, absolute_path, create_dir, is_dir, link_file, list_apps, open_file, remove_dir,
+    rename_file, stat_file, unlink_file,
+};
+pub use pipe::make_pipe;
 pub use stdio::{Stdin, Stdout};
+pub use tty::TTY;
@@ -0,0 +1,164 @@
+//! Anonymous pipes
+//!
+//! The two ends of a pipe share a [`PipeRingBuffer`]. A reader parks on an
+//! empty buffer and a writer on a full one, the syscall restarts once the
+//! other end wakes it up.
+use super::File;
+use crate::mm::UserBuffer;
+use crate::syscall::{SysError, SysResult};
+use crate::task::signal::SignalFlags;
+use crate::task::{Task, current_task, schedule};
+use alloc::collections::vec_deque::VecDeque;
+use alloc::sync::{Arc, Weak};
+use alloc::vec;
+use alloc::vec::Vec;
+use spin::Mutex;
+
+/// Capacity of the buffer of a pipe
+const RING_BUFFER_SIZE: usize = 512;
+
+/// One end of a pipe
+pub struct Pipe {
+    readable: bool,
+    writable: bool,
+    buffer: Arc<Mutex<PipeRingBuffer>>,
+}
+
+/// The bounded buffer shared by both ends of a pipe
+struct PipeRingBuffer {
+    arr: [u8; RING_BUFFER_SIZE],
+    head: usize,
+    len: usize,
+    read_end: Weak<Pipe>,
+    write_end: Weak<Pipe>,
+    /// Readers parked on an empty buffer
+    readers: VecDeque<Arc<Task>>,
+    /// Writers parked on a full buffer
+    writers: VecDeque<Arc<Task>>,
+}
+
+impl PipeRingBuffer {
+    fn new() -> Self {
+        Self {
+            arr: [0; RING_BUFFER_SIZE],
+            head: 0,
+            len: 0,
+            read_end: Weak::new(),
+            write_end: Weak::new(),
+            readers: VecDeque::new(),
+            writers: VecDeque::new(),
+        }
+    }
+    fn read_byte(&mut self) -> Option<u8> {
+        if self.len == 0 {
+            return None;
+        }
+        let byte = self.arr[self.head];
+        self.head = (self.head + 1) % RING_BUFFER_SIZE;
+        self.len -= 1;
+        Some(byte)
+    }
+    fn write_byte(&mut self, byte: u8) -> bool {
+        if self.len == RING_BUFFER_SIZE {
+            return false;
+        }
+        self.arr[(self.head + self.len) % RING_BUFFER_SIZE] = byte;
+        self.len += 1;
+        true
+    }
+}
+
+/// Make the tasks parked on `queue` runnable again
+fn wake_all(queue: &mut VecDeque<Arc<Task>>) {
+    while let Some(task) = queue.pop_front() {
+        schedule::wake(task);
+    }
+}
+
+/// Create a pipe and return its read end and write end
+pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
+    let buffer = Arc::new(Mutex::new(PipeRingBuffer::new()));
+    let read_end = Arc::new(Pipe {
+        readable: true,
+        writable: false,
+        buffer: buffer.clone(),
+    });
+    let write_end = Arc::new(Pipe {
+        readable: false,
+        writable: true,
+        buffer: buffer.clone(),
+    });
+    let mut ring = buffer.lock();
+    ring.read_end = Arc::downgrade(&read_end);
+    ring.write_end = Arc::downgrade(&write_end);
+    drop(ring);
+    (read_end, write_end)
+}
+
+impl File for Pipe {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    /// Read what is in the buffer, 0 means every write end is gone
+    fn read(&self, buf: UserBuffer) -> usize {
+        let mut ring = self.buffer.lock();
+        if buf.len() == 0 {
+            return 0;
+        }
+        if ring.len == 0 {
+            if ring.write_end.strong_count() == 0 {
+                return 0;
+            }
+            schedule::park_current(&mut ring.readers);
+            return 0;
+        }
+        let mut data = Vec::new();
+        while data.len() < buf.len() {
+            let Some(byte) = ring.read_byte() else {
+                break;
+            };
+            data.push(byte);
+        }
+        buf.write(0, &data);
+        wake_all(&mut ring.writers);
+        data.len()
+    }
+    /// Write as much as fits into the buffer. The writer gets SIGPIPE and
+    /// `EPIPE` once every read end is gone.
+    fn write(&self, buf: UserBuffer) -> SysResult {
+        let mut ring = self.buffer.lock();
+        if ring.read_end.strong_count() == 0 {
+            current_task().unwrap().send_signal(SignalFlags::SIGPIPE);
+            return Err(SysError::EPIPE);
+        }
+        if buf.len() == 0 {
+            return Ok(0);
+        }
+        if ring.len == RING_BUFFER_SIZE {
+            schedule::park_current(&mut ring.writers);
+            return Ok(0);
+        }
+        let mut data = vec![0; buf.len().min(RING_BUFFER_SIZE - ring.len)];
+        buf.read(0, &mut data);
+        for &byte in data.iter() {
+            assert!(ring.write_byte(byte));
+        }
+        wake_all(&mut ring.readers);
+        Ok(data.len())
+    }
+}
+
+impl Drop for Pipe {
+    fn drop(&mut self) {
+        // tasks parked on the other end see that this end is gone
+        let mut ring = self.buffer.lock();
+        if self.writable {
+            wake_all(&mut ring.readers);
+        } else {
+            wake_all(&mut ring.writers);
+        }
+    }
+}
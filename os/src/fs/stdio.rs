@@ -1,14 +1,55 @@
 //!Stdin & Stdout
 use super::File;
-use crate::mm::UserBuffer;
-use crate::print;
-use crate::sbi::console_getchar;
-use crate::task::schedule;
+use super::tty::{TTY, Termios};
+use crate::mm::{UserBuffer, UserPtr};
+use crate::syscall::{SysError, SysResult};
+use crate::task::find_group;
+
+/// Get the settings of the terminal into `*arg`
+const TCGETS: usize = 0x5401;
+/// Set the settings of the terminal to `*arg`
+const TCSETS: usize = 0x5402;
+/// Get the foreground process group of the terminal into `*arg`
+const TIOCGPGRP: usize = 0x540f;
+/// Set the foreground process group of the terminal to `*arg`
+const TIOCSPGRP: usize = 0x5410;
+
 ///Standard input
 pub struct Stdin;
 ///Standard output
 pub struct Stdout;
 
+/// The terminal requests of stdin and stdout. Only a group that has a
+/// process can be brought to the foreground.
+fn tty_ioctl(cmd: usize, arg: usize) -> SysResult {
+    match cmd {
+        TCGETS => {
+            UserPtr::<Termios>::new(arg).write(TTY.termios())?;
+            Ok(0)
+        }
+        TCSETS => {
+            TTY.set_termios(UserPtr::<Termios>::new(arg).read()?);
+            Ok(0)
+        }
+        TIOCGPGRP => {
+            UserPtr::<i32>::new(arg).write(TTY.foreground() as i32)?;
+            Ok(0)
+        }
+        TIOCSPGRP => {
+            let pgid = UserPtr::<i32>::new(arg).read()?;
+            if pgid <= 0 {
+                return Err(SysError::EINVAL);
+            }
+            if find_group(pgid as usize).is_empty() {
+                return Err(SysError::EPERM);
+            }
+            TTY.set_foreground(pgid as usize);
+            Ok(0)
+        }
+        _ => Err(SysError::ENOTTY),
+    }
+}
+
 impl File for Stdin {
     fn readable(&self) -> bool {
         true
@@ -16,28 +57,15 @@ impl File for Stdin {
     fn writable(&self) -> bool {
         false
     }
-    fn read(&self, mut user_buf: UserBuffer) -> usize {
-        assert_eq!(user_buf.len(), 1);
-        // busy loop
-        let mut c: usize;
-        loop {
-            c = console_getchar();
-            if c == 0 {
-                schedule::yield_now();
-                continue;
-            } else {
-                break;
-            }
-        }
-        let ch = c as u8;
-        unsafe {
-            user_buf.buffers[0].as_mut_ptr().write_volatile(ch);
-        }
-        1
+    fn read(&self, user_buf: UserBuffer) -> usize {
+        TTY.read(user_buf)
     }
-    fn write(&self, _user_buf: UserBuffer) -> usize {
+    fn write(&self, _user_buf: UserBuffer) -> SysResult {
         panic!("Cannot write to stdin!");
     }
+    fn ioctl(&self, cmd: usize, arg: usize) -> SysResult {
+        tty_ioctl(cmd, arg)
+    }
 }
 
 impl File for Stdout {
@@ -50,10 +78,10 @@ impl File for Stdout {
     fn read(&self, _user_buf: UserBuffer) -> usize {
         panic!("Cannot read from stdout!");
     }
-    fn write(&self, user_buf: UserBuffer) -> usize {
-        for buffer in user_buf.buffers.iter() {
-            print!("{}", core::str::from_utf8(*buffer).unwrap());
-        }
-        user_buf.len()
+    fn write(&self, user_buf: UserBuffer) -> SysResult {
+        Ok(TTY.write(user_buf))
+    }
+    fn ioctl(&self, cmd: usize, arg: usize) -> SysResult {
+        tty_ioctl(cmd, arg)
     }
 }
@@ -0,0 +1,396 @@
+//! The console as a terminal
+//!
+//! Nothing interrupts on console input, so [`Tty::poll`] takes what has
+//! been typed on every timer tick and whenever nothing is runnable. Typed
+//! bytes go through the line discipline as the [`Termios`] of the terminal
+//! says. In canonical mode they are echoed and edited in a line that can
+//! only be read once it is ended by a newline or `VEOF`, in raw mode they
+//! can be read right away. The signal characters become signals to the
+//! foreground process group. Readers outside the foreground group are
+//! stopped by SIGTTIN.
+use crate::mm::{Pod, UserBuffer};
+use crate::print;
+use crate::sbi::{console_getchar, console_putchar};
+use crate::task::signal::{SIG_IGN, SignalFlags, signal_group};
+use crate::task::{Task, current_process, schedule};
+use alloc::collections::vec_deque::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use bitflags::bitflags;
+use lazy_static::lazy_static;
+use spin::Mutex;
+
+/// Index of the interrupt character in [`Termios::cc`]
+const VINTR: usize = 0;
+/// Index of the quit character
+const VQUIT: usize = 1;
+/// Index of the erase character
+const VERASE: usize = 2;
+/// Index of the kill-line character
+const VKILL: usize = 3;
+/// Index of the end-of-file character
+const VEOF: usize = 4;
+/// Index of the least number of bytes a raw read waits for
+const VMIN: usize = 6;
+/// Index of the suspend character
+const VSUSP: usize = 10;
+/// Index of the word-erase character
+const VWERASE: usize = 14;
+/// Number of control characters
+const NCCS: usize = 19;
+
+/// Longest line canonical mode keeps, the rest of it is dropped
+const MAX_CANON: usize = 4096;
+
+bitflags! {
+    /// Input modes, `I*`
+    #[derive(Clone, Copy)]
+    pub struct InputFlags: u32 {
+        /// Drop carriage returns
+        const IGNCR = 0o200;
+        /// Turn carriage returns into newlines
+        const ICRNL = 0o400;
+    }
+
+    /// Local modes, `ECHO*` and friends
+    #[derive(Clone, Copy)]
+    pub struct LocalFlags: u32 {
+        /// Turn the signal characters into signals
+        const ISIG = 0o1;
+        /// Edit the input line by line
+        const ICANON = 0o2;
+        /// Echo what is typed
+        const ECHO = 0o10;
+        /// Echo the erase characters by erasing on the screen
+        const ECHOE = 0o20;
+        /// Erase the line on the screen for the kill-line character
+        const ECHOK = 0o40;
+        /// Echo newlines even without `ECHO`
+        const ECHONL = 0o100;
+        /// Echo control characters as `^X`
+        const ECHOCTL = 0o1000;
+        /// Enable the word-erase character
+        const IEXTEN = 0o100000;
+    }
+}
+
+/// Settings of a terminal, laid out as the `struct termios` of `TCGETS`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Termios {
+    /// Input modes
+    pub iflag: InputFlags,
+    /// Output modes, kept but not acted on
+    pub oflag: u32,
+    /// Control modes, kept but not acted on
+    pub cflag: u32,
+    /// Local modes
+    pub lflag: LocalFlags,
+    /// Line discipline
+    pub line: u8,
+    /// Control characters, 0 disables one
+    pub cc: [u8; NCCS],
+}
+
+unsafe impl Pod for Termios {}
+
+impl Default for Termios {
+    /// Canonical mode with echo, like a freshly opened terminal
+    fn default() -> Self {
+        let mut cc = [0; NCCS];
+        cc[VINTR] = 0x03; // Ctrl-C
+        cc[VQUIT] = 0x1c; // Ctrl-\
+        cc[VERASE] = 0x7f;
+        cc[VKILL] = 0x15; // Ctrl-U
+        cc[VEOF] = 0x04; // Ctrl-D
+        cc[VMIN] = 1;
+        cc[VSUSP] = 0x1a; // Ctrl-Z
+        cc[VWERASE] = 0x17; // Ctrl-W
+        Self {
+            iflag: InputFlags::ICRNL,
+            // OPOST | ONLCR
+            oflag: 0o5,
+            // B38400 | CS8 | CREAD
+            cflag: 0o277,
+            lflag: LocalFlags::ISIG
+                | LocalFlags::ICANON
+                | LocalFlags::ECHO
+                | LocalFlags::ECHOE
+                | LocalFlags::ECHOK
+                | LocalFlags::ECHOCTL
+                | LocalFlags::IEXTEN,
+            line: 0,
+            cc,
+        }
+    }
+}
+
+impl Termios {
+    /// Whether `c` is the control character `index`
+    fn is(&self, c: u8, index: usize) -> bool {
+        self.cc[index] != 0 && self.cc[index] == c
+    }
+}
+
+/// A terminal on the console
+pub struct Tty {
+    inner: Mutex<TtyInner>,
+}
+
+struct TtyInner {
+    termios: Termios,
+    /// Input that can be read. In canonical mode each entry is a line and
+    /// a read does not go past it, an empty one is an end of input.
+    input: VecDeque<Vec<u8>>,
+    /// The line being edited in canonical mode
+    line: Vec<u8>,
+    /// Readers parked on empty input
+    readers: VecDeque<Arc<Task>>,
+    /// Process group that reads the input and gets the signals of the
+    /// control characters, 0 before anyone has set it
+    foreground: usize,
+}
+
+lazy_static! {
+    /// The terminal of the console
+    pub static ref TTY: Tty = Tty {
+        inner: Mutex::new(TtyInner {
+            termios: Termios::default(),
+            input: VecDeque::new(),
+            line: Vec::new(),
+            readers: VecDeque::new(),
+            foreground: 0,
+        }),
+    };
+}
+
+/// Columns `c` takes when it is echoed, tabs count as one
+fn echo_width(termios: &Termios, c: u8) -> usize {
+    if c < 0x20 && c != b'\t' && c != b'\n' && termios.lflag.contains(LocalFlags::ECHOCTL) {
+        2
+    } else {
+        1
+    }
+}
+
+impl TtyInner {
+    /// Echo `c` if the terminal echoes, control characters as `^X`. Bytes
+    /// go to the console as they are, the input need not be UTF-8 either.
+    fn echo(&self, c: u8) {
+        let lflag = self.termios.lflag;
+        if c == b'\n' {
+            if lflag.intersects(LocalFlags::ECHO | LocalFlags::ECHONL) {
+                console_putchar(b'\n' as usize);
+            }
+        } else if lflag.contains(LocalFlags::ECHO) {
+            if echo_width(&self.termios, c) == 2 {
+                console_putchar(b'^' as usize);
+                console_putchar((c + b'@') as usize);
+            } else {
+                console_putchar(c as usize);
+            }
+        }
+    }
+
+    /// Drop the last character of the line, and from the screen as well
+    /// if `visual`
+    fn erase(&mut self, visual: bool) -> Option<u8> {
+        let c = self.line.pop()?;
+        if visual && self.termios.lflag.contains(LocalFlags::ECHO) {
+            for _ in 0..echo_width(&self.termios, c) {
+                print!("\x08 \x08");
+            }
+        }
+        Some(c)
+    }
+
+    /// Run `c` through the line discipline, returns the signal it stands
+    /// for if any
+    fn receive(&mut self, mut c: u8) -> Option<SignalFlags> {
+        let termios = self.termios;
+        if c == b'\r' {
+            if termios.iflag.contains(InputFlags::IGNCR) {
+                return None;
+            }
+            if termios.iflag.contains(InputFlags::ICRNL) {
+                c = b'\n';
+            }
+        }
+        if termios.lflag.contains(LocalFlags::ISIG) {
+            let signal = if termios.is(c, VINTR) {
+                Some(SignalFlags::SIGINT)
+            } else if termios.is(c, VQUIT) {
+                Some(SignalFlags::SIGQUIT)
+            } else if termios.is(c, VSUSP) {
+                Some(SignalFlags::SIGTSTP)
+            } else {
+                None
+            };
+            if signal.is_some() {
+                self.echo(c);
+                // what was typed before belongs to the interrupted program
+                self.input.clear();
+                self.line.clear();
+                return signal;
+            }
+        }
+        if !termios.lflag.contains(LocalFlags::ICANON) {
+            self.echo(c);
+            match self.input.back_mut() {
+                Some(last) if !last.is_empty() => last.push(c),
+                _ => self.input.push_back(vec![c]),
+            }
+            return None;
+        }
+        let visual = termios.lflag.contains(LocalFlags::ECHOE);
+        if termios.is(c, VERASE) {
+            self.erase(visual);
+        } else if termios.is(c, VKILL) {
+            let visual = termios.lflag.contains(LocalFlags::ECHOK);
+            while self.erase(visual).is_some() {}
+        } else if termios.is(c, VWERASE) && termios.lflag.contains(LocalFlags::IEXTEN) {
+            while self.line.last().is_some_and(|c| c.is_ascii_whitespace()) {
+                self.erase(visual);
+            }
+            while self.line.last().is_some_and(|c| !c.is_ascii_whitespace()) {
+                self.erase(visual);
+            }
+        } else if termios.is(c, VEOF) {
+            // the line so far can be read, or the end of input if it is empty
+            let line = core::mem::take(&mut self.line);
+            self.input.push_back(line);
+        } else if c == b'\n' {
+            self.echo(c);
+            let mut line = core::mem::take(&mut self.line);
+            line.push(c);
+            self.input.push_back(line);
+        } else if self.line.len() < MAX_CANON - 1 {
+            self.echo(c);
+            self.line.push(c);
+        }
+        None
+    }
+}
+
+impl Tty {
+    /// Take what has been typed on the console and wake the readers
+    pub fn poll(&self) {
+        let mut signals = SignalFlags::empty();
+        let mut inner = self.inner.lock();
+        loop {
+            let c = console_getchar();
+            // the console returns 0 or -1 when there is nothing
+            let Ok(c @ 1..=255) = u8::try_from(c) else {
+                break;
+            };
+            if let Some(signal) = inner.receive(c) {
+                signals |= signal;
+            }
+        }
+        if !inner.input.is_empty() {
+            while let Some(task) = inner.readers.pop_front() {
+                schedule::wake(task);
+            }
+        }
+        let foreground = inner.foreground;
+        drop(inner);
+        if foreground != 0 {
+            for signal in signals.iter() {
+                signal_group(foreground, signal);
+            }
+        }
+    }
+
+    /// Read a line in canonical mode, or what has been typed in raw mode.
+    /// 0 means the end of input. A reader that is not in the foreground
+    /// group is stopped by SIGTTIN and reads again once it is continued, or
+    /// reads nothing if it ignores SIGTTIN. In raw mode a `VMIN` of 0 does
+    /// not wait for input, `VTIME` is not supported.
+    pub fn read(&self, buf: UserBuffer) -> usize {
+        if buf.len() == 0 {
+            return 0;
+        }
+        let process = current_process().unwrap();
+        let pgid = process.get_inner().pgid;
+        let foreground = self.foreground();
+        if foreground != 0 && pgid != foreground {
+            let inner = process.get_inner();
+            if inner.signal_actions[SignalFlags::SIGTTIN.signum()].handler == SIG_IGN
+                || inner.blocked_signals.contains(SignalFlags::SIGTTIN)
+            {
+                return 0;
+            }
+            signal_group(pgid, SignalFlags::SIGTTIN);
+            schedule::restart_current();
+            return 0;
+        }
+        self.poll();
+        let mut inner = self.inner.lock();
+        let canonical = inner.termios.lflag.contains(LocalFlags::ICANON);
+        if inner.input.is_empty() {
+            if canonical || inner.termios.cc[VMIN] != 0 {
+                schedule::park_current(&mut inner.readers);
+            }
+            return 0;
+        }
+        let mut data = Vec::new();
+        while let Some(mut line) = inner.input.pop_front() {
+            if line.is_empty() {
+                // an end of input is read on its own
+                if !data.is_empty() {
+                    inner.input.push_front(line);
+                }
+                break;
+            }
+            let taken = line.len().min(buf.len() - data.len());
+            data.extend(line.iter().take(taken));
+            if taken < line.len() {
+                line.drain(..taken);
+                inner.input.push_front(line);
+                break;
+            }
+            if canonical {
+                break;
+            }
+        }
+        buf.write(0, &data);
+        data.len()
+    }
+
+    /// Write everything to the console, byte by byte as it need not be
+    /// UTF-8
+    pub fn write(&self, buf: UserBuffer) -> usize {
+        let data = buf.to_vec();
+        for &byte in data.iter() {
+            console_putchar(byte as usize);
+        }
+        data.len()
+    }
+
+    /// The foreground process group
+    pub fn foreground(&self) -> usize {
+        self.inner.lock().foreground
+    }
+
+    /// Make `pgid` the foreground process group
+    pub fn set_foreground(&self, pgid: usize) {
+        self.inner.lock().foreground = pgid;
+    }
+
+    /// The settings of the terminal
+    pub fn termios(&self) -> Termios {
+        self.inner.lock().termios
+    }
+
+    /// Change the settings of the terminal. A line being edited can be read
+    /// right away once canonical mode is off.
+    pub fn set_termios(&self, termios: Termios) {
+        let mut inner = self.inner.lock();
+        inner.termios = termios;
+        if !termios.lflag.contains(LocalFlags::ICANON) && !inner.line.is_empty() {
+            let line = core::mem::take(&mut inner.line);
+            inner.input.push_back(line);
+        }
+    }
+}
@@ -14,6 +14,7 @@ mod lang_items;
 mod logger;
 mod mm;
 mod sbi;
+mod sleep;
 mod sync;
 mod syscall;
 mod task;
@@ -40,6 +41,7 @@ pub fn ros_main(_hartid: usize, dtb_addr: usize) -> ! {
 
     trap::init();
     trap::enable_timer_interrupt();
+    timer::next_slice();
     timer::set_next_trigger();
 
     fs::list_apps();
@@ -1,4 +1,5 @@
 use super::address::PhysPageNum;
+use alloc::collections::btree_map::BTreeMap;
 use alloc::vec::Vec;
 use lazy_static::*;
 use spin::mutex::Mutex;
@@ -22,10 +23,22 @@ pub fn frame_alloc() -> Option<PhysPageNum> {
     FRAME_ALLOCATOR.lock().alloc()
 }
 
+/// Drop one reference to the frame, it is recycled when the last one is gone.
 pub fn frame_dealloc(ppn: PhysPageNum) {
     FRAME_ALLOCATOR.lock().dealloc(ppn);
 }
 
+/// Take one more reference to an allocated frame, e.g. when it is mapped
+/// copy-on-write into another address space.
+pub fn frame_share(ppn: PhysPageNum) {
+    FRAME_ALLOCATOR.lock().share(ppn);
+}
+
+/// Number of references held on an allocated frame.
+pub fn frame_ref_count(ppn: PhysPageNum) -> usize {
+    FRAME_ALLOCATOR.lock().ref_count(ppn)
+}
+
 // === test ===
 
 // pub fn frame_allocator_test() {
@@ -58,6 +71,9 @@ pub struct StackFrameAllocator {
     current: usize,
     end: usize,
     recycled: Vec<usize>,
+    /// Reference counts of shared frames, a frame missing here has exactly one owner.
+    /// Kept sparse because the heap is not ready yet when the allocator is initialized.
+    shared: BTreeMap<usize, usize>,
 }
 
 impl StackFrameAllocator {
@@ -65,6 +81,18 @@ impl StackFrameAllocator {
         self.current = l.0;
         self.end = r.0;
     }
+
+    pub fn share(&mut self, ppn: PhysPageNum) {
+        let ppn = ppn.0;
+        if ppn >= self.current || self.recycled.contains(&ppn) {
+            panic!("Frame ppn={:#x} has not been allocated!", ppn);
+        }
+        *self.shared.entry(ppn).or_insert(1) += 1;
+    }
+
+    pub fn ref_count(&self, ppn: PhysPageNum) -> usize {
+        self.shared.get(&ppn.0).copied().unwrap_or(1)
+    }
 }
 
 impl FrameAllocator for StackFrameAllocator {
@@ -73,6 +101,7 @@ impl FrameAllocator for StackFrameAllocator {
             current: 0,
             end: 0,
             recycled: Vec::new(),
+            shared: BTreeMap::new(),
         }
     }
 
@@ -91,6 +120,14 @@ impl FrameAllocator for StackFrameAllocator {
     }
     fn dealloc(&mut self, ppn: PhysPageNum) {
         let ppn = ppn.0;
+        // still referenced by someone else
+        if let Some(count) = self.shared.get_mut(&ppn) {
+            *count -= 1;
+            if *count == 1 {
+                self.shared.remove(&ppn);
+            }
+            return;
+        }
         // validity check
         if ppn >= self.current || self.recycled.iter().find(|&v| *v == ppn).is_some() {
             panic!("Frame ppn={:#x} has not been allocated!", ppn);
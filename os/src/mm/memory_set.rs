@@ -1,17 +1,25 @@
 //! Implementation of [`MapArea`] and [`MemorySet`].
 use super::address::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum};
 use super::address::{StepByOne, VPNRange};
-use super::frame_allocator::frame_alloc;
+use super::frame_allocator::{frame_alloc, frame_dealloc, frame_ref_count, frame_share};
 use super::linker_args::*;
-use super::page_table::{PTEFlags, PageTable};
+use super::page_table::{
+    PTEFlags, PageTable, PageTableEntry, frame_clear, frame_read, frame_write,
+};
 use crate::config::MMIO;
-use crate::config::{KERNEL_SPACE_OFFSET, PAGE_SIZE};
+use crate::config::{KERNEL_SPACE_OFFSET, PAGE_SIZE, USER_SPACE_MAX, USER_SPACE_MIN};
 use crate::println;
+use alloc::sync::Arc;
+use alloc::vec;
 use alloc::vec::Vec;
 use bitflags::bitflags;
+use easy_fs::Inode;
 use lazy_static::*;
 use log::trace;
 use spin::Mutex;
+use xmas_elf::ElfFile;
+use xmas_elf::header::{Class, Machine};
+use xmas_elf::program::{ProgramHeader64, Type as ProgramType};
 
 struct KernelSpaceInitParam {
     pub dtb_addr: usize,
@@ -40,6 +48,17 @@ pub fn init_kernel_space(dtb_addr: usize, mem_end: usize) {
 pub struct MemorySet {
     page_table: PageTable,
     areas: Vec<MapArea>,
+    /// Thread-local data of the program, each thread gets a copy
+    pub tls: Option<TlsImage>,
+}
+
+/// The initial thread-local data of a program, from its `PT_TLS` segment
+#[derive(Clone)]
+pub struct TlsImage {
+    /// The initialized part, `.tdata`, the rest up to `mem_size` is zeroed
+    pub data: Vec<u8>,
+    pub mem_size: usize,
+    pub align: usize,
 }
 
 impl Drop for MemorySet {
@@ -52,11 +71,52 @@ impl Drop for MemorySet {
     }
 }
 
+/// Parse `elf_data` as a program for this machine whose headers and
+/// segments lie within the file, and whose segments load into user space.
+/// None if it is anything else.
+pub fn parse_elf(elf_data: &[u8]) -> Option<ElfFile<'_>> {
+    let elf = ElfFile::new(elf_data).ok()?;
+    let pt2 = &elf.header.pt2;
+    if elf.header.pt1.class() != Class::SixtyFour
+        || pt2.machine().as_machine() != Machine::RISC_V
+        || pt2.ph_entry_size() as usize != size_of::<ProgramHeader64>()
+    {
+        return None;
+    }
+    // xmas_elf reads the program headers in place
+    let ph_end = (pt2.ph_count() as usize)
+        .checked_mul(size_of::<ProgramHeader64>())?
+        .checked_add(pt2.ph_offset() as usize)?;
+    if ph_end > elf_data.len()
+        || (elf_data.as_ptr() as usize + pt2.ph_offset() as usize) % align_of::<ProgramHeader64>()
+            != 0
+    {
+        return None;
+    }
+    for ph in elf.program_iter() {
+        let ty = ph.get_type().ok()?;
+        if ty != ProgramType::Load && ty != ProgramType::Tls {
+            continue;
+        }
+        let file_end = ph.offset().checked_add(ph.file_size())?;
+        let mem_end = ph.virtual_addr().checked_add(ph.mem_size())?;
+        if file_end > elf_data.len() as u64
+            || ph.file_size() > ph.mem_size()
+            || ph.virtual_addr() < USER_SPACE_MIN as u64
+            || mem_end > USER_SPACE_MAX as u64
+        {
+            return None;
+        }
+    }
+    Some(elf)
+}
+
 impl MemorySet {
     pub fn new(pt: PageTable) -> Self {
         Self {
             page_table: pt,
             areas: Vec::new(),
+            tls: None,
         }
     }
 
@@ -64,18 +124,21 @@ impl MemorySet {
         &self.page_table
     }
 
-    pub fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
+    pub fn push(&mut self, map_area: MapArea, data: Option<&[u8]>) {
         if let Some(data) = data {
-            let mut write_area = MapArea {
-                vpn_range: map_area.vpn_range,
-                map_type: map_area.map_type,
-                map_perm: ((map_area.map_perm | MapPermission::W)
-                    & (!MapPermission::X)
-                    & (!MapPermission::U)),
-            };
+            // only the pages holding data are mapped now, the rest on demand
+            let data_end =
+                VirtPageNum(map_area.vpn_range.get_start().0 + data.len().div_ceil(PAGE_SIZE));
+            let mut write_area = MapArea::new(
+                map_area.vpn_range.get_start().into(),
+                data_end.into(),
+                map_area.map_type,
+                (map_area.map_perm | MapPermission::W) & (!MapPermission::X) & (!MapPermission::U),
+            );
             write_area.map(&self.page_table);
             write_area.copy_data(data);
-            map_area.update_perm(&self.page_table);
+            write_area.map_perm = map_area.map_perm;
+            write_area.update_perm(&self.page_table);
         } else {
             map_area.map(&mut self.page_table);
         }
@@ -173,21 +236,22 @@ impl MemorySet {
         }
     }
 
-    /// Include sections in elf and trampoline and TrapContext and user stack,
-    /// also returns user_sp and entry point.
-    pub fn from_elf(elf_data: &[u8], new_pt: PageTable, old_pt: &PageTable) -> (Self, usize) {
+    /// Include sections in elf and an empty heap right after them, also
+    /// returns the heap bottom and entry point. None if `elf_data` is not
+    /// a program [`parse_elf`] accepts.
+    pub fn from_elf(
+        elf_data: &[u8],
+        new_pt: PageTable,
+        old_pt: &PageTable,
+    ) -> Option<(Self, usize, usize)> {
+        let elf = parse_elf(elf_data)?;
         let mut memory_set = Self::new(new_pt);
         // map program headers of elf, with U flag
-        let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
-        let elf_header = elf.header;
-        let magic = elf_header.pt1.magic;
-        assert_eq!(magic, [0x7f, 0x45, 0x4c, 0x46], "invalid elf!");
-        let ph_count = elf_header.pt2.ph_count();
         memory_set.activate();
         trace!("token {:#x}", memory_set.page_table.token());
-        for i in 0..ph_count {
-            let ph = elf.program_header(i).unwrap();
-            if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
+        let mut max_end_vpn = VirtPageNum(0);
+        for ph in elf.program_iter() {
+            if ph.get_type() == Ok(ProgramType::Load) {
                 // info!("segment: {:?}", ph);
                 let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
                 let end_va: VirtAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
@@ -204,15 +268,331 @@ impl MemorySet {
                 }
 
                 let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
+                max_end_vpn = max_end_vpn.max(map_area.vpn_range.get_end());
 
                 memory_set.push(
                     map_area,
                     Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
                 );
+            } else if ph.get_type() == Ok(ProgramType::Tls) {
+                // `.tdata` is in a load segment as well, this is the image
+                // every thread copies
+                memory_set.tls = Some(TlsImage {
+                    data: elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]
+                        .to_vec(),
+                    mem_size: ph.mem_size() as usize,
+                    align: (ph.align() as usize).max(1),
+                });
             }
         }
         old_pt.activate();
-        (memory_set, elf.header.pt2.entry_point() as usize)
+
+        // grown by sbrk
+        let heap_bottom: usize = VirtAddr::from(max_end_vpn).into();
+        memory_set.push(
+            MapArea::new(
+                heap_bottom.into(),
+                heap_bottom.into(),
+                MapType::Framed,
+                MapPermission::R | MapPermission::W | MapPermission::U,
+            ),
+            None,
+        );
+        Some((
+            memory_set,
+            heap_bottom,
+            elf.header.pt2.entry_point() as usize,
+        ))
+    }
+
+    /// Move the areas outside user space, i.e. kernel stacks, over from
+    /// `other` without touching their mappings.
+    pub fn take_kernel_areas(&mut self, other: &mut MemorySet) {
+        let (kernel, user): (Vec<_>, Vec<_>) = other
+            .areas
+            .drain(..)
+            .partition(|area| !area.map_perm.contains(MapPermission::U));
+        other.areas = user;
+        self.areas.extend(kernel);
+    }
+
+    /// Unmap the area that starts at `start` and free its frames, if there
+    /// is one
+    pub fn remove_area(&mut self, start: VirtPageNum) {
+        if let Some(index) = self
+            .areas
+            .iter()
+            .position(|area| area.vpn_range.get_start() == start)
+        {
+            let mut area = self.areas.remove(index);
+            area.unmap(&mut self.page_table);
+        }
+    }
+
+    /// Unmap every user area and free its frames, like on the exit of the
+    /// process. The kernel stacks stay.
+    pub fn release_user_areas(&mut self) {
+        let (kernel, user): (Vec<_>, Vec<_>) = self
+            .areas
+            .drain(..)
+            .partition(|area| !area.map_perm.contains(MapPermission::U));
+        self.areas = kernel;
+        for mut area in user {
+            area.unmap(&mut self.page_table);
+        }
+    }
+
+    /// Copy the user areas into a new address space built on `new_pt`.
+    /// `Framed` pages are shared read-only by both spaces and copied by
+    /// [`MemorySet::handle_page_fault`] on the first write.
+    pub fn fork(&mut self, new_pt: PageTable) -> Self {
+        let mut memory_set = Self::new(new_pt);
+        for area in self
+            .areas
+            .iter()
+            .filter(|area| area.map_perm.contains(MapPermission::U))
+        {
+            let new_area = MapArea::from_another(area);
+            for vpn in area.vpn_range {
+                let pte = match self.page_table.translate(vpn) {
+                    Some(pte) => pte,
+                    // both spaces must end up with the same frames
+                    None if area.shared && area.accessible() => {
+                        area.map_one(&self.page_table, vpn).unwrap();
+                        self.page_table.translate(vpn).unwrap()
+                    }
+                    None => continue,
+                };
+                let ppn = pte.ppn();
+                let mut flags = pte.flags();
+                if area.map_type == MapType::Framed {
+                    if !area.shared && flags.contains(PTEFlags::W) {
+                        flags.remove(PTEFlags::W);
+                        self.page_table.update_perm(vpn, flags);
+                    }
+                    frame_share(ppn);
+                }
+                memory_set.page_table.map(vpn, ppn, flags);
+            }
+            memory_set.areas.push(new_area);
+        }
+        memory_set.tls = self.tls.clone();
+        memory_set
+    }
+
+    /// Resolve a page fault at `va` that needs `access`, one of R, W or X,
+    /// from user mode if `user`. `Framed` pages are allocated on the first
+    /// touch and pages shared by [`MemorySet::fork`] are copied on the
+    /// first write. Returns false if no area allows the access, user mode
+    /// only reaches user areas, or there is no frame left. The fault is
+    /// then a real one.
+    pub fn handle_page_fault(&mut self, va: VirtAddr, access: MapPermission, user: bool) -> bool {
+        let vpn = va.floor();
+        let Some(area) = self.areas.iter().find(|area| area.contains(vpn)) else {
+            return false;
+        };
+        if area.map_type != MapType::Framed
+            || !area.map_perm.contains(access)
+            || (user && !area.map_perm.contains(MapPermission::U))
+        {
+            return false;
+        }
+        let Some(pte) = self.page_table.translate(vpn) else {
+            return area.map_one(&self.page_table, vpn).is_some();
+        };
+        if !area.owns(&pte) {
+            return false;
+        }
+        if !access.contains(MapPermission::W) || pte.writable() {
+            // another thread has already resolved it, or the hardware leaves
+            // the accessed and dirty bits to us
+            let mut flags = pte.flags() | PTEFlags::A;
+            if access.contains(MapPermission::W) {
+                flags |= PTEFlags::D;
+            }
+            if flags != pte.flags() {
+                self.page_table.update_perm(vpn, flags);
+            }
+            return true;
+        }
+        let flags = pte.flags() | PTEFlags::W;
+        if area.shared || frame_ref_count(pte.ppn()) == 1 {
+            // the other owners are gone or meant to see the write
+            self.page_table.update_perm(vpn, flags);
+        } else {
+            let Some(frame) = frame_alloc() else {
+                return false;
+            };
+            let old = self.page_table.copy_remap(vpn, frame, flags);
+            frame_dealloc(old);
+        }
+        true
+    }
+
+    /// Move the end of the user area starting at `start` to `new_end`, the
+    /// pages past it are unmapped. Returns false if there is no such area or
+    /// it would run into another one.
+    pub fn resize_area(&mut self, start: VirtPageNum, new_end: VirtPageNum) -> bool {
+        if new_end < start {
+            return false;
+        }
+        let Some(idx) = self.areas.iter().position(|area| {
+            area.map_perm.contains(MapPermission::U) && area.vpn_range.get_start() == start
+        }) else {
+            return false;
+        };
+        let end = self.areas[idx].vpn_range.get_end();
+        if new_end > end && self.areas.iter().any(|area| area.overlaps(end, new_end)) {
+            return false;
+        }
+        let area = &mut self.areas[idx];
+        for vpn in VPNRange::new(new_end, end) {
+            area.unmap_one(&mut self.page_table, vpn);
+        }
+        area.vpn_range = VPNRange::new(start, new_end);
+        true
+    }
+
+    /// Whether every page of `[start, start + len)` is in a user area that
+    /// allows `access`. The pages need not be present yet.
+    pub fn user_range_allows(&self, start: VirtAddr, len: usize, access: MapPermission) -> bool {
+        let Some(end) = start.0.checked_add(len) else {
+            return false;
+        };
+        let end = VirtPageNum(end.div_ceil(PAGE_SIZE));
+        let mut vpn = start.floor();
+        while vpn < end {
+            let Some(area) = self.areas.iter().find(|area| area.contains(vpn)) else {
+                return false;
+            };
+            if !area.map_perm.contains(access | MapPermission::U) {
+                return false;
+            }
+            vpn = area.vpn_range.get_end();
+        }
+        true
+    }
+
+    /// Make every page of `[start, start + len)` present for `access`, one
+    /// of R or W, as faults on them would. The page table tells which pages
+    /// need it. Returns false if some page is not in a user area that allows
+    /// `access`.
+    pub fn prepare_user_range(
+        &mut self,
+        start: VirtAddr,
+        len: usize,
+        access: MapPermission,
+    ) -> bool {
+        if !self.user_range_allows(start, len, access) {
+            return false;
+        }
+        let mut needed = PTEFlags::V | PTEFlags::A;
+        if access.contains(MapPermission::W) {
+            needed |= PTEFlags::W | PTEFlags::D;
+        }
+        let end = VirtAddr::from(start.0 + len).ceil();
+        VPNRange::new(start.floor(), end).into_iter().all(|vpn| {
+            let present = self
+                .page_table
+                .translate(vpn)
+                .is_some_and(|pte| pte.flags().contains(needed));
+            present || self.handle_page_fault(vpn.into(), access, false)
+        })
+    }
+
+    /// Copy `data` to the pages from `start` on, which must be in a
+    /// writable user area. The address space need not be active.
+    pub fn write_pages(&mut self, start: VirtPageNum, data: &[u8]) {
+        for (i, chunk) in data.chunks(PAGE_SIZE).enumerate() {
+            let vpn = VirtPageNum(start.0 + i);
+            assert!(self.handle_page_fault(vpn.into(), MapPermission::W, false));
+            frame_write(self.page_table.translate(vpn).unwrap().ppn(), chunk);
+        }
+    }
+
+    /// Find `pages` free pages in user space, starting the search at `hint`.
+    pub fn find_free_area(&self, hint: VirtPageNum, pages: usize) -> VirtPageNum {
+        let mut start = hint;
+        while let Some(area) = self
+            .areas
+            .iter()
+            .find(|area| area.overlaps(start, VirtPageNum(start.0 + pages)))
+        {
+            start = area.vpn_range.get_end();
+        }
+        start
+    }
+
+    /// Take the user pages in `[start, end)` out of every area, splitting the
+    /// areas that are only partly covered.
+    pub fn remove_range(&mut self, start: VirtPageNum, end: VirtPageNum) {
+        let mut areas = Vec::new();
+        for area in core::mem::take(&mut self.areas) {
+            if !area.map_perm.contains(MapPermission::U) || !area.overlaps(start, end) {
+                areas.push(area);
+                continue;
+            }
+            let (left, mut middle, right) = area.split(start, end);
+            middle.unmap(&mut self.page_table);
+            areas.extend(left);
+            areas.extend(right);
+        }
+        self.areas = areas;
+    }
+
+    /// Change the permission of the user pages in `[start, end)`. Returns
+    /// false, changing nothing, if some page in the range is not mapped.
+    pub fn protect_range(
+        &mut self,
+        start: VirtPageNum,
+        end: VirtPageNum,
+        perm: MapPermission,
+    ) -> bool {
+        let covered = VPNRange::new(start, end).into_iter().all(|vpn| {
+            self.areas
+                .iter()
+                .any(|area| area.map_perm.contains(MapPermission::U) && area.contains(vpn))
+        });
+        if !covered {
+            return false;
+        }
+        let mut areas = Vec::new();
+        for area in core::mem::take(&mut self.areas) {
+            if !area.map_perm.contains(MapPermission::U) || !area.overlaps(start, end) {
+                areas.push(area);
+                continue;
+            }
+            let (left, mut middle, right) = area.split(start, end);
+            middle.map_perm = perm;
+            for vpn in middle.vpn_range {
+                let Some(pte) = self.page_table.translate(vpn) else {
+                    continue;
+                };
+                let mut flags = PTEFlags::from_bits(perm.bits()).unwrap()
+                    | (pte.flags() & (PTEFlags::A | PTEFlags::D));
+                if !middle.shared && frame_ref_count(pte.ppn()) > 1 {
+                    // still shared with a fork, keep it copy-on-write
+                    flags.remove(PTEFlags::W);
+                }
+                self.page_table.update_perm(vpn, flags);
+            }
+            areas.extend(left);
+            areas.push(middle);
+            areas.extend(right);
+        }
+        self.areas = areas;
+        true
+    }
+
+    /// Write the dirty pages of shared file mappings in `[start, end)` back.
+    pub fn sync_range(&self, start: VirtPageNum, end: VirtPageNum) {
+        for area in self.areas.iter().filter(|area| area.overlaps(start, end)) {
+            for vpn in area.vpn_range {
+                if start <= vpn && vpn < end {
+                    area.write_back(&self.page_table, vpn);
+                }
+            }
+        }
     }
 
     pub fn activate(&self) {
@@ -220,11 +600,24 @@ impl MemorySet {
     }
 }
 
+/// The file behind a file-backed [`MapArea`].
+#[derive(Clone)]
+pub struct MapFile {
+    pub inode: Arc<Inode>,
+    /// Offset in the file of the first page of the area
+    pub offset: usize,
+}
+
 /// map area structure, controls a contiguous piece of virtual memory
 pub struct MapArea {
     pub vpn_range: VPNRange,
     pub map_type: MapType,
     pub map_perm: MapPermission,
+    /// Pages are read from this file on the first touch
+    pub file: Option<MapFile>,
+    /// Writes are seen by every space mapping the pages and, for a file,
+    /// written back to it
+    pub shared: bool,
 }
 
 impl MapArea {
@@ -241,10 +634,89 @@ impl MapArea {
             // data_frames: BTreeMap::new(),
             map_type,
             map_perm,
+            file: None,
+            shared: false,
+        }
+    }
+
+    pub fn from_another(another: &MapArea) -> Self {
+        another.slice(another.vpn_range.get_start(), another.vpn_range.get_end())
+    }
+
+    /// A copy of the description of `[start, end)`, which must lie within
+    /// this area.
+    fn slice(&self, start: VirtPageNum, end: VirtPageNum) -> Self {
+        Self {
+            vpn_range: VPNRange::new(start, end),
+            map_type: self.map_type,
+            map_perm: self.map_perm,
+            file: self.file.as_ref().map(|file| MapFile {
+                inode: file.inode.clone(),
+                offset: file.offset + (start.0 - self.vpn_range.get_start().0) * PAGE_SIZE,
+            }),
+            shared: self.shared,
+        }
+    }
+
+    /// Cut the area into the parts before, inside and after `[start, end)`,
+    /// the range must overlap the area. Mappings are left untouched.
+    fn split(self, start: VirtPageNum, end: VirtPageNum) -> (Option<Self>, Self, Option<Self>) {
+        let (area_start, area_end) = (self.vpn_range.get_start(), self.vpn_range.get_end());
+        let start = start.max(area_start);
+        let end = end.min(area_end);
+        let left = (area_start < start).then(|| self.slice(area_start, start));
+        let right = (end < area_end).then(|| self.slice(end, area_end));
+        (left, self.slice(start, end), right)
+    }
+
+    pub fn contains(&self, vpn: VirtPageNum) -> bool {
+        self.vpn_range.get_start() <= vpn && vpn < self.vpn_range.get_end()
+    }
+
+    pub fn overlaps(&self, start: VirtPageNum, end: VirtPageNum) -> bool {
+        self.vpn_range.get_start() < end && start < self.vpn_range.get_end()
+    }
+
+    /// A page table entry without any of R, W and X is not a leaf.
+    fn accessible(&self) -> bool {
+        self.map_perm
+            .intersects(MapPermission::R | MapPermission::W | MapPermission::X)
+    }
+
+    /// Whether `pte` maps a page of this area. A `Framed` area only owns the
+    /// frames it allocated itself, those of user areas are the only ones
+    /// user mode reaches.
+    fn owns(&self, pte: &PageTableEntry) -> bool {
+        self.map_type != MapType::Framed
+            || pte.flags().contains(PTEFlags::U) == self.map_perm.contains(MapPermission::U)
+    }
+
+    fn file_offset(&self, file: &MapFile, vpn: VirtPageNum) -> usize {
+        file.offset + (vpn.0 - self.vpn_range.get_start().0) * PAGE_SIZE
+    }
+
+    /// Write the page at `vpn` back to the file if the area is a shared
+    /// file mapping and the page is dirty.
+    fn write_back(&self, page_table: &PageTable, vpn: VirtPageNum) {
+        let Some(file) = self.file.as_ref().filter(|_| self.shared) else {
+            return;
+        };
+        let Some(pte) = page_table.translate(vpn) else {
+            return;
+        };
+        let offset = self.file_offset(file, vpn);
+        let size = file.inode.size();
+        if !pte.flags().contains(PTEFlags::D) || offset >= size {
+            return;
         }
+        let mut buf = vec![0u8; PAGE_SIZE.min(size - offset)];
+        frame_read(pte.ppn(), &mut buf);
+        file.inode.write_at(offset, &buf);
+        page_table.update_perm(vpn, pte.flags() - PTEFlags::D);
     }
 
-    pub fn map_one(&self, page_table: &PageTable, vpn: VirtPageNum) -> PhysPageNum {
+    /// Map `vpn`, None if there is no frame left for it
+    pub fn map_one(&self, page_table: &PageTable, vpn: VirtPageNum) -> Option<PhysPageNum> {
         let ppn: PhysPageNum;
         match self.map_type {
             MapType::KernelOffset => {
@@ -255,22 +727,41 @@ impl MapArea {
                 ppn = PhysPageNum(vpn.0);
             }
             MapType::Framed => {
-                let frame = frame_alloc().unwrap();
+                let frame = frame_alloc()?;
+                frame_clear(frame);
+                if let Some(file) = &self.file {
+                    let mut buf = vec![0u8; PAGE_SIZE];
+                    let len = file.inode.read_at(self.file_offset(file, vpn), &mut buf);
+                    frame_write(frame, &buf[..len]);
+                }
                 ppn = frame;
                 // println!("\t map one page: {:#x} -> {:#x}", ppn.0, vpn.0);
             }
         }
         let pte_flags = PTEFlags::from_bits(self.map_perm.bits()).unwrap();
         page_table.map(vpn, ppn, pte_flags);
-        ppn
+        Some(ppn)
     }
     #[allow(unused)]
     pub fn unmap_one(&mut self, page_table: &mut PageTable, vpn: VirtPageNum) {
-        page_table.unmap(vpn);
+        match page_table.translate(vpn) {
+            Some(pte) if self.owns(&pte) => {}
+            // never touched
+            _ => return,
+        }
+        self.write_back(page_table, vpn);
+        let ppn = page_table.unmap(vpn);
+        if self.map_type == MapType::Framed {
+            frame_dealloc(ppn);
+        }
     }
+    /// User `Framed` pages are left to [`MemorySet::handle_page_fault`].
     pub fn map(&self, page_table: &PageTable) {
+        if self.map_type == MapType::Framed && self.map_perm.contains(MapPermission::U) {
+            return;
+        }
         for vpn in self.vpn_range {
-            self.map_one(page_table, vpn);
+            self.map_one(page_table, vpn).unwrap();
         }
     }
     pub fn update_perm(&mut self, page_table: &PageTable) {
@@ -4,9 +4,11 @@ pub(crate) mod heap_allocator;
 pub(crate) mod linker_args;
 pub(crate) mod memory_set;
 pub(crate) mod page_table;
+pub(crate) mod user_ptr;
 
 use fdt::Fdt;
 pub use page_table::UserBuffer;
+pub use user_ptr::{Pod, UserPtr, UserSlice};
 
 use crate::println;
 use address::PhysAddr;
@@ -1,8 +1,10 @@
 use core::arch::{asm, global_asm};
 use core::mem;
+use core::ops::Range;
 
 use crate::mm::address::PhysPageNum;
 use crate::mm::{address::VirtPageNum, frame_allocator::frame_alloc};
+use alloc::vec;
 use alloc::vec::Vec;
 use bitflags::bitflags;
 use log::trace;
@@ -10,7 +12,7 @@ use macros::ptenv_call;
 use riscv::register::sstatus::{Sstatus, set_sum};
 use riscv::register::{satp, sstatus};
 
-use super::frame_allocator::frame_dealloc;
+use super::frame_allocator::{frame_dealloc, frame_share};
 
 global_asm!(include_str!("switch_env.S"));
 
@@ -221,6 +223,48 @@ impl PageTable {
         // info!("self: {:p} vpn = {:?}, flags = {:?}\n", self, vpn, flags);
         let pte = self.find_pte(vpn).unwrap();
         *pte = PageTableEntry::new(pte.ppn(), flags | PTEFlags::V);
+        unsafe {
+            asm!(
+                "sfence.vma {va}",
+                va = in(reg) vpn.0 << 12,
+            );
+        };
+    }
+
+    /// Copy the frame mapped at `vpn` into `ppn` and map `ppn` there instead,
+    /// returns the frame that was mapped before.
+    pub fn copy_remap(&self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) -> PhysPageNum {
+        let old_ppn: usize;
+        ptenv_call!(
+            Self::copy_remap_internal,
+            out = old_ppn,
+            self,
+            vpn.0,
+            ppn.0,
+            flags.bits()
+        );
+        old_ppn.into()
+    }
+
+    fn copy_remap_internal(
+        &self,
+        vpn: VirtPageNum,
+        ppn: PhysPageNum,
+        flags: PTEFlags,
+    ) -> PhysPageNum {
+        let pte = self.find_pte(vpn).unwrap();
+        assert!(pte.is_valid(), "vpn {:?} is invalid before remapping", vpn);
+        let old_ppn = pte.ppn();
+        ppn.get_bytes_array()
+            .copy_from_slice(old_ppn.get_bytes_array());
+        *pte = PageTableEntry::new(ppn, flags | PTEFlags::V);
+        unsafe {
+            asm!(
+                "sfence.vma {va}",
+                va = in(reg) vpn.0 << 12,
+            );
+        };
+        old_ppn
     }
 
     pub fn map(&self, vpn: VirtPageNum, ppn: PhysPageNum, flags: PTEFlags) {
@@ -247,15 +291,26 @@ impl PageTable {
         };
     }
 
-    pub fn unmap(&mut self, vpn: VirtPageNum) {
+    /// Returns the frame that was mapped at `vpn`.
+    pub fn unmap(&mut self, vpn: VirtPageNum) -> PhysPageNum {
         // info!("self {:p} {:x} asid: {}", self, vpn.0, self.asid);
-        ptenv_call!(Self::unmap_internal, self, vpn.0);
+        let ppn: usize;
+        ptenv_call!(Self::unmap_internal, out = ppn, self, vpn.0);
+        ppn.into()
     }
 
-    fn unmap_internal(&self, vpn: VirtPageNum) {
+    fn unmap_internal(&self, vpn: VirtPageNum) -> PhysPageNum {
         let pte = self.find_pte(vpn).unwrap();
         assert!(pte.is_valid(), "vpn {:?} is invalid before unmapping", vpn);
+        let ppn = pte.ppn();
         *pte = PageTableEntry::empty();
+        unsafe {
+            asm!(
+                "sfence.vma {va}",
+                va = in(reg) vpn.0 << 12,
+            );
+        };
+        ppn
     }
 
     pub fn translate(&self, vpn: VirtPageNum) -> Option<PageTableEntry> {
@@ -266,15 +321,15 @@ impl PageTable {
             let pte: PageTableEntry = mem::transmute(pte);
             // info!("pte: {:x}", pte.bits);
             // panic!("translate");
-            Some(pte)
+            pte.is_valid().then_some(pte)
         }
     }
 
     fn translate_internal(&self, vpn: VirtPageNum) -> PageTableEntry {
         // info!("self: {:p} vpn: {:x} asid: {:x}", self, vpn.0, self.asid);
-        let pte = self.find_pte(vpn).unwrap();
         // info!("pte: {:p} {:x}", pte, pte.bits);
-        *pte
+        self.find_pte(vpn)
+            .map_or(PageTableEntry::empty(), |pte| *pte)
     }
 
     pub fn token(&self) -> usize {
@@ -291,59 +346,129 @@ impl PageTable {
     }
 }
 
-///Array of u8 slice that user communicate with os
+/// Fill a physical frame with zeros.
+pub fn frame_clear(ppn: PhysPageNum) {
+    ptenv_call!(frame_clear_internal, ppn.0);
+}
+
+fn frame_clear_internal(ppn: PhysPageNum) {
+    ppn.get_bytes_array().fill(0);
+}
+
+/// Copy `data` to the start of a physical frame.
+pub fn frame_write(ppn: PhysPageNum, data: &[u8]) {
+    frame_write_at(ppn, 0, data);
+}
+
+/// Copy `data` to a physical frame from `offset` on.
+pub fn frame_write_at(ppn: PhysPageNum, offset: usize, data: &[u8]) {
+    ptenv_call!(
+        frame_write_internal,
+        ppn.0,
+        offset,
+        data.as_ptr(),
+        data.len()
+    );
+}
+
+fn frame_write_internal(ppn: PhysPageNum, offset: usize, data: *const u8, len: usize) {
+    let data = unsafe { core::slice::from_raw_parts(data, len) };
+    ppn.get_bytes_array()[offset..offset + len].copy_from_slice(data);
+}
+
+/// Copy the start of a physical frame into `buf`.
+pub fn frame_read(ppn: PhysPageNum, buf: &mut [u8]) {
+    frame_read_at(ppn, 0, buf);
+}
+
+/// Copy the bytes of a physical frame from `offset` on into `buf`.
+pub fn frame_read_at(ppn: PhysPageNum, offset: usize, buf: &mut [u8]) {
+    ptenv_call!(
+        frame_read_internal,
+        ppn.0,
+        offset,
+        buf.as_mut_ptr(),
+        buf.len()
+    );
+}
+
+fn frame_read_internal(ppn: PhysPageNum, offset: usize, buf: *mut u8, len: usize) {
+    let buf = unsafe { core::slice::from_raw_parts_mut(buf, len) };
+    buf.copy_from_slice(&ppn.get_bytes_array()[offset..offset + len]);
+}
+
+/// User memory that a file reads from or writes to, as the frames behind
+/// it. The buffer holds a reference to each frame, so that they stay
+/// allocated even if the pages are unmapped while a file uses it.
 pub struct UserBuffer {
-    ///U8 vec
-    pub buffers: Vec<&'static mut [u8]>,
+    /// The frames in order, with the range of bytes in each that belongs to
+    /// the buffer
+    pieces: Vec<(PhysPageNum, Range<usize>)>,
 }
 
 impl UserBuffer {
-    ///Create a `UserBuffer` by parameter
-    pub fn new(buffers: Vec<&'static mut [u8]>) -> Self {
-        Self { buffers }
+    /// A buffer over `pieces`, which takes a reference to their frames
+    pub fn new(pieces: Vec<(PhysPageNum, Range<usize>)>) -> Self {
+        for (ppn, _) in pieces.iter() {
+            frame_share(*ppn);
+        }
+        Self { pieces }
     }
     ///Length of `UserBuffer`
     pub fn len(&self) -> usize {
-        let mut total: usize = 0;
-        for b in self.buffers.iter() {
-            total += b.len();
+        self.pieces.iter().map(|(_, range)| range.len()).sum()
+    }
+    /// Copy the buffer from `pos` on into `buf`, as much as there is.
+    /// Returns how many bytes were copied.
+    pub fn read(&self, pos: usize, buf: &mut [u8]) -> usize {
+        let mut done = 0;
+        for (ppn, offset, len) in self.pieces_from(pos, buf.len()) {
+            frame_read_at(ppn, offset, &mut buf[done..done + len]);
+            done += len;
         }
-        total
-    }
-}
-
-impl IntoIterator for UserBuffer {
-    type Item = *mut u8;
-    type IntoIter = UserBufferIterator;
-    fn into_iter(self) -> Self::IntoIter {
-        UserBufferIterator {
-            buffers: self.buffers,
-            current_buffer: 0,
-            current_idx: 0,
+        done
+    }
+    /// Copy `data` to the buffer from `pos` on, as much as fits. Returns
+    /// how many bytes were copied.
+    pub fn write(&self, pos: usize, data: &[u8]) -> usize {
+        let mut done = 0;
+        for (ppn, offset, len) in self.pieces_from(pos, data.len()) {
+            frame_write_at(ppn, offset, &data[done..done + len]);
+            done += len;
         }
+        done
+    }
+    /// A copy of the whole buffer
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut data = vec![0; self.len()];
+        self.read(0, &mut data);
+        data
+    }
+    /// The frames, offsets in them and lengths of at most `len` bytes from
+    /// `pos` on
+    fn pieces_from(
+        &self,
+        mut pos: usize,
+        mut len: usize,
+    ) -> impl Iterator<Item = (PhysPageNum, usize, usize)> + '_ {
+        self.pieces.iter().filter_map(move |(ppn, range)| {
+            if pos >= range.len() {
+                pos -= range.len();
+                return None;
+            }
+            let piece_len = (range.len() - pos).min(len);
+            let piece = (*ppn, range.start + pos, piece_len);
+            pos = 0;
+            len -= piece_len;
+            (piece_len > 0).then_some(piece)
+        })
     }
-}
-/// Iterator of `UserBuffer`
-pub struct UserBufferIterator {
-    buffers: Vec<&'static mut [u8]>,
-    current_buffer: usize,
-    current_idx: usize,
 }
 
-impl Iterator for UserBufferIterator {
-    type Item = *mut u8;
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.current_buffer >= self.buffers.len() {
-            None
-        } else {
-            let r = &mut self.buffers[self.current_buffer][self.current_idx] as *mut _;
-            if self.current_idx + 1 == self.buffers[self.current_buffer].len() {
-                self.current_idx = 0;
-                self.current_buffer += 1;
-            } else {
-                self.current_idx += 1;
-            }
-            Some(r)
+impl Drop for UserBuffer {
+    fn drop(&mut self) {
+        for (ppn, _) in self.pieces.iter() {
+            frame_dealloc(*ppn);
         }
     }
 }
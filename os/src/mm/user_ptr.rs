@@ -0,0 +1,212 @@
+//! Access to the memory of the current task from syscalls
+//!
+//! A pointer from user space is checked against the user areas of the
+//! task's [`MemorySet`](super::memory_set::MemorySet) before the kernel
+//! touches it, so it can reach neither kernel memory nor pages the task may
+//! not access. Copies run in `copy_user`, where a page fault that is not a
+//! lazy or copy-on-write page makes the copy fail with `EFAULT` instead of
+//! bringing the kernel down.
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::arch::global_asm;
+use core::marker::PhantomData;
+use core::mem::{MaybeUninit, size_of};
+use core::slice;
+
+use super::UserBuffer;
+use super::address::VirtAddr;
+use super::memory_set::MapPermission;
+use crate::config::PAGE_SIZE;
+use crate::syscall::SysError;
+use crate::task::current_task;
+
+global_asm!(include_str!("copy_user.S"));
+
+unsafe extern "C" {
+    fn copy_user(dst: *mut u8, src: *const u8, len: usize) -> usize;
+    fn copy_user_end();
+    fn copy_user_fixup();
+}
+
+/// Where to resume after a page fault at `pc` that can't be resolved, if
+/// it is in a copy from or to user space
+pub fn fixup(pc: usize) -> Option<usize> {
+    (copy_user as usize..copy_user_end as usize)
+        .contains(&pc)
+        .then_some(copy_user_fixup as usize)
+}
+
+/// Types that any bytes are a valid value of, they can be copied from and
+/// to user space as they are.
+///
+/// # Safety
+///
+/// Every bit pattern of the size of the type has to be a valid value.
+pub unsafe trait Pod: Sized {}
+
+unsafe impl Pod for u8 {}
+unsafe impl Pod for i32 {}
+unsafe impl Pod for u32 {}
+unsafe impl Pod for usize {}
+unsafe impl<T: Pod, const N: usize> Pod for [T; N] {}
+
+/// Fail with `EFAULT` unless the current task may access
+/// `[addr, addr + len)` with `access`
+fn check(addr: usize, len: usize, access: MapPermission) -> Result<(), SysError> {
+    if len == 0 {
+        return Ok(());
+    }
+    let task = current_task().unwrap();
+    let memory_set = task.get_inner().memory_set.get_mut();
+    if memory_set.user_range_allows(addr.into(), len, access) {
+        Ok(())
+    } else {
+        Err(SysError::EFAULT)
+    }
+}
+
+/// Copy `dst.len()` bytes from the user address `src` into `dst`
+pub fn copy_from_user(dst: &mut [u8], src: usize) -> Result<(), SysError> {
+    check(src, dst.len(), MapPermission::R)?;
+    match unsafe { copy_user(dst.as_mut_ptr(), src as *const u8, dst.len()) } {
+        0 => Ok(()),
+        _ => Err(SysError::EFAULT),
+    }
+}
+
+/// Copy `src` to the user address `dst`
+pub fn copy_to_user(dst: usize, src: &[u8]) -> Result<(), SysError> {
+    check(dst, src.len(), MapPermission::W)?;
+    match unsafe { copy_user(dst as *mut u8, src.as_ptr(), src.len()) } {
+        0 => Ok(()),
+        _ => Err(SysError::EFAULT),
+    }
+}
+
+/// A pointer to a `T` in the memory of the current task
+#[repr(transparent)]
+pub struct UserPtr<T> {
+    addr: usize,
+    _marker: PhantomData<*mut T>,
+}
+
+impl<T> Clone for UserPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for UserPtr<T> {}
+
+unsafe impl<T> Pod for UserPtr<T> {}
+
+// only an address, which is checked whenever it is accessed
+unsafe impl<T> Send for UserPtr<T> {}
+unsafe impl<T> Sync for UserPtr<T> {}
+
+impl<T> UserPtr<T> {
+    pub fn new(addr: usize) -> Self {
+        Self {
+            addr,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Syscalls take a null pointer for an argument that is left out
+    pub fn is_null(&self) -> bool {
+        self.addr == 0
+    }
+
+    /// The pointer `count` `T`s further on
+    pub fn add(&self, count: usize) -> Self {
+        Self::new(self.addr.wrapping_add(count * size_of::<T>()))
+    }
+}
+
+impl<T: Pod> UserPtr<T> {
+    /// Copy the `T` out of user memory
+    pub fn read(&self) -> Result<T, SysError> {
+        let mut value = MaybeUninit::<T>::uninit();
+        let bytes =
+            unsafe { slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size_of::<T>()) };
+        copy_from_user(bytes, self.addr)?;
+        Ok(unsafe { value.assume_init() })
+    }
+
+    /// Copy `value` into user memory
+    pub fn write(&self, value: T) -> Result<(), SysError> {
+        let bytes =
+            unsafe { slice::from_raw_parts(&value as *const T as *const u8, size_of::<T>()) };
+        copy_to_user(self.addr, bytes)
+    }
+}
+
+impl UserPtr<u8> {
+    /// Read a 0-terminated UTF-8 string of at most `max_len` bytes, it fails
+    /// with `EINVAL` if it is longer or not UTF-8
+    pub fn read_cstr(&self, max_len: usize) -> Result<String, SysError> {
+        let mut bytes = Vec::new();
+        loop {
+            let byte = self.add(bytes.len()).read()?;
+            if byte == 0 {
+                break;
+            }
+            if bytes.len() == max_len {
+                return Err(SysError::EINVAL);
+            }
+            bytes.push(byte);
+        }
+        String::from_utf8(bytes).map_err(|_| SysError::EINVAL)
+    }
+}
+
+/// `len` bytes at `addr` in the memory of the current task
+#[derive(Clone, Copy)]
+pub struct UserSlice {
+    addr: usize,
+    len: usize,
+}
+
+impl UserSlice {
+    pub fn new(addr: usize, len: usize) -> Self {
+        Self { addr, len }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Copy `data`, which has to fit, to the start of the slice
+    pub fn write(&self, data: &[u8]) -> Result<(), SysError> {
+        if data.len() > self.len {
+            return Err(SysError::EFAULT);
+        }
+        copy_to_user(self.addr, data)
+    }
+
+    /// The slice as a [`UserBuffer`] over the frames behind it, for a file
+    /// to write to with `W` or to read from with `R`. Its pages are made
+    /// present first.
+    pub fn buffer(&self, access: MapPermission) -> Result<UserBuffer, SysError> {
+        let task = current_task().unwrap();
+        let memory_set = task.get_inner().memory_set.get_mut();
+        if self.len > 0 && !memory_set.prepare_user_range(self.addr.into(), self.len, access) {
+            return Err(SysError::EFAULT);
+        }
+        let mut pieces = Vec::new();
+        let end = self.addr + self.len;
+        let mut start = self.addr;
+        while start < end {
+            let va = VirtAddr::from(start);
+            let len = (PAGE_SIZE - va.page_offset()).min(end - start);
+            let pte = memory_set
+                .get_page_table()
+                .translate(va.floor())
+                .ok_or(SysError::EFAULT)?;
+            let offset = va.page_offset();
+            pieces.push((pte.ppn(), offset..offset + len));
+            start += len;
+        }
+        Ok(UserBuffer::new(pieces))
+    }
+}
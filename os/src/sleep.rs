@@ -0,0 +1,96 @@
+//! Tasks sleeping until a deadline
+//!
+//! Sleepers wait in a queue sorted by their deadline, in ticks of the
+//! `time` CSR. The timer is programmed for the nearest one, see
+//! [`crate::timer::set_next_trigger`], and [`wake_expired`] wakes every
+//! sleeper that is due when it fires.
+use crate::mm::UserPtr;
+use crate::syscall::SysError;
+use crate::task::{Task, schedule};
+use crate::timer::{TimeSpec, get_time, set_next_trigger};
+use crate::trap::context::UserContext;
+use alloc::collections::binary_heap::BinaryHeap;
+use alloc::sync::Arc;
+use core::cmp::Ordering;
+use lazy_static::lazy_static;
+use spin::Mutex;
+
+/// The sleep syscall a task is in
+#[derive(Clone, Copy)]
+pub struct Sleep {
+    /// When it ends, in ticks of `time`
+    pub deadline: usize,
+    /// Where the time left goes if it is interrupted, may be null
+    pub rem: UserPtr<TimeSpec>,
+}
+
+impl Sleep {
+    /// End the sleep with `EINTR` instead of restarting it, a signal
+    /// handler is about to run. `user_ctx` is still at the syscall.
+    pub fn interrupt(self, user_ctx: &mut UserContext) {
+        user_ctx.sepc += 4;
+        user_ctx.set_syscall_ret(SysError::EINTR.to_ret() as usize);
+        if !self.rem.is_null() {
+            // the handler runs even if `rem` is not writable
+            let left = self.deadline.saturating_sub(get_time());
+            let _ = self.rem.write(TimeSpec::from_ticks(left));
+        }
+    }
+}
+
+/// A task parked until `deadline`
+struct Sleeper {
+    deadline: usize,
+    task: Arc<Task>,
+}
+
+impl PartialEq for Sleeper {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for Sleeper {}
+
+impl PartialOrd for Sleeper {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Sleeper {
+    /// The nearest deadline is the greatest, it is on top of the heap
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.deadline.cmp(&self.deadline)
+    }
+}
+
+lazy_static! {
+    static ref SLEEPERS: Mutex<BinaryHeap<Sleeper>> = Mutex::new(BinaryHeap::new());
+}
+
+/// Park the current task until `deadline`, the syscall it is in restarts
+/// then. A signal may wake it earlier.
+pub fn sleep_current(deadline: usize) {
+    schedule::park_current_with(|task| SLEEPERS.lock().push(Sleeper { deadline, task }));
+    // the timer may have to fire earlier than it is programmed to
+    set_next_trigger();
+}
+
+/// Wake the sleepers whose deadline has passed
+pub fn wake_expired() {
+    let now = get_time();
+    let mut sleepers = SLEEPERS.lock();
+    while sleepers
+        .peek()
+        .is_some_and(|sleeper| sleeper.deadline <= now)
+    {
+        // it may have been woken by a signal or have exited already
+        schedule::wake(sleepers.pop().unwrap().task);
+    }
+}
+
+/// The nearest deadline of a sleeper
+pub fn next_deadline() -> Option<usize> {
+    SLEEPERS.lock().peek().map(|sleeper| sleeper.deadline)
+}
@@ -0,0 +1,80 @@
+use alloc::{collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+
+use crate::{
+    cpu::local::CpuLocalCell,
+    sync::mutex::Lock,
+    task::{Task, current_task, schedule},
+};
+
+/// A condition variable
+///
+/// [`Condvar::wait`] runs twice as its syscall restarts. The first run
+/// releases the mutex and parks the task, the run after a wakeup takes the
+/// mutex again, which may park the task once more.
+pub struct Condvar {
+    inner: CpuLocalCell<CondvarInner>,
+}
+
+pub struct CondvarInner {
+    /// Tasks parked in [`Condvar::wait`]
+    pub waiting_tasks: VecDeque<Arc<Task>>,
+    /// Tasks woken up that have not taken their mutex again yet
+    pub woken_tasks: Vec<Arc<Task>>,
+}
+
+impl Condvar {
+    pub fn new() -> Self {
+        Self {
+            inner: CpuLocalCell::new(CondvarInner {
+                waiting_tasks: VecDeque::new(),
+                woken_tasks: Vec::new(),
+            }),
+        }
+    }
+
+    /// Wake the task that has waited longest
+    pub fn signal(&self) {
+        let inner = self.inner.get_mut();
+        if let Some(task) = inner.waiting_tasks.pop_front() {
+            inner.woken_tasks.push(task.clone());
+            schedule::wake(task);
+        }
+    }
+
+    /// Wake every waiting task
+    pub fn broadcast(&self) {
+        let inner = self.inner.get_mut();
+        while let Some(task) = inner.waiting_tasks.pop_front() {
+            inner.woken_tasks.push(task.clone());
+            schedule::wake(task);
+        }
+    }
+
+    /// Release `mutex` and wait for a signal, then take `mutex` again. A
+    /// POSIX signal may wake the task up early.
+    pub fn wait(&self, mutex: &Arc<dyn Lock>) {
+        let current = current_task().unwrap();
+        let inner = self.inner.get_mut();
+        if let Some(index) = inner
+            .waiting_tasks
+            .iter()
+            .position(|task| Arc::ptr_eq(task, &current))
+        {
+            // woken up by a POSIX signal
+            let task = inner.waiting_tasks.remove(index).unwrap();
+            inner.woken_tasks.push(task);
+        }
+        let Some(index) = inner
+            .woken_tasks
+            .iter()
+            .position(|task| Arc::ptr_eq(task, &current))
+        else {
+            mutex.unlock();
+            schedule::park_current(&mut inner.waiting_tasks);
+            return;
+        };
+        if mutex.lock() {
+            inner.woken_tasks.swap_remove(index);
+        }
+    }
+}
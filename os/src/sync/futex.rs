@@ -0,0 +1,135 @@
+//! Futexes, wait queues keyed by a user address
+//!
+//! A waiter parks on the queue of its address space and a user address if
+//! the `u32` there still holds the value it expects, so that a waker that
+//! changes the value first never misses it. The queues are spread over a
+//! fixed number of hash buckets.
+use alloc::{collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+use core::sync::atomic::{AtomicU32, Ordering};
+use lazy_static::lazy_static;
+use spin::Mutex;
+
+use crate::syscall::{SysError, SysResult};
+use crate::task::{Task, TaskStatus, current_task, schedule};
+
+const BUCKETS: usize = 64;
+
+/// A futex is a user address in an address space. Memory shared between
+/// processes is in two address spaces and so are its futexes.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct FutexKey {
+    space: usize,
+    addr: usize,
+}
+
+impl FutexKey {
+    fn of(task: &Task, addr: usize) -> Self {
+        Self {
+            space: Arc::as_ptr(&task.get_inner().memory_set) as usize,
+            addr,
+        }
+    }
+
+    fn bucket(&self) -> &'static Mutex<Bucket> {
+        let hash = (self.addr / 4) ^ (self.space / 8);
+        &FUTEX_BUCKETS[hash % BUCKETS]
+    }
+}
+
+struct FutexQueue {
+    key: FutexKey,
+    waiting_tasks: VecDeque<Arc<Task>>,
+}
+
+#[derive(Default)]
+struct Bucket {
+    queues: Vec<FutexQueue>,
+    /// Tasks woken by [`futex_wake`] whose wait has not returned yet
+    woken_tasks: Vec<Arc<Task>>,
+}
+
+lazy_static! {
+    static ref FUTEX_BUCKETS: Vec<Mutex<Bucket>> = (0..BUCKETS)
+        .map(|_| Mutex::new(Bucket::default()))
+        .collect();
+}
+
+/// Wait on the futex at `addr` if it holds `expected`, until [`futex_wake`].
+/// Returns 0 once woken up, fails with `EAGAIN` right away if the value
+/// differs and with `EINTR` if a signal wakes the task. The syscall
+/// restarts after the wakeup, which takes the task off
+/// [`Bucket::woken_tasks`].
+pub fn futex_wait(addr: usize, expected: u32) -> SysResult {
+    let current = current_task().unwrap();
+    let key = FutexKey::of(&current, addr);
+    let mut bucket = key.bucket().lock();
+    if let Some(index) = bucket
+        .woken_tasks
+        .iter()
+        .position(|task| Arc::ptr_eq(task, &current))
+    {
+        bucket.woken_tasks.swap_remove(index);
+        return Ok(0);
+    }
+    let position = bucket.queues.iter().position(|queue| queue.key == key);
+    if let Some(queue_index) = position {
+        let queue = &mut bucket.queues[queue_index];
+        if let Some(index) = queue
+            .waiting_tasks
+            .iter()
+            .position(|task| Arc::ptr_eq(task, &current))
+        {
+            // woken up by a signal
+            queue.waiting_tasks.remove(index);
+            if queue.waiting_tasks.is_empty() {
+                bucket.queues.swap_remove(queue_index);
+            }
+            return Err(SysError::EINTR);
+        }
+    }
+    let value = unsafe { (*(addr as *const AtomicU32)).load(Ordering::SeqCst) };
+    if value != expected {
+        return Err(SysError::EAGAIN);
+    }
+    let queue_index = position.unwrap_or_else(|| {
+        bucket.queues.push(FutexQueue {
+            key,
+            waiting_tasks: VecDeque::new(),
+        });
+        bucket.queues.len() - 1
+    });
+    schedule::park_current(&mut bucket.queues[queue_index].waiting_tasks);
+    Ok(0)
+}
+
+/// Wake at most `count` tasks waiting on the futex at `addr` and return
+/// how many were woken
+pub fn futex_wake(addr: usize, count: usize) -> usize {
+    let current = current_task().unwrap();
+    let key = FutexKey::of(&current, addr);
+    let mut bucket = key.bucket().lock();
+    // forget the tasks that exited before their wait returned
+    bucket
+        .woken_tasks
+        .retain(|task| task.get_inner().status != TaskStatus::Zombie);
+    let Some(queue_index) = bucket.queues.iter().position(|queue| queue.key == key) else {
+        return 0;
+    };
+    let mut woken = Vec::new();
+    let queue = &mut bucket.queues[queue_index];
+    while woken.len() < count {
+        let Some(task) = queue.waiting_tasks.pop_front() else {
+            break;
+        };
+        // skip the tasks a signal has already woken up
+        if schedule::wake(task.clone()) {
+            woken.push(task);
+        }
+    }
+    if queue.waiting_tasks.is_empty() {
+        bucket.queues.swap_remove(queue_index);
+    }
+    let count = woken.len();
+    bucket.woken_tasks.extend(woken);
+    count
+}
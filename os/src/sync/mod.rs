@@ -1 +1,4 @@
+pub(crate) mod condvar;
+pub(crate) mod futex;
 pub(crate) mod mutex;
+pub(crate) mod semaphore;
@@ -6,7 +6,9 @@ use crate::{
 };
 
 pub trait Lock: Sync + Send {
-    fn lock(&self);
+    /// Take the lock, or park the current task until it is unlocked and
+    /// return false, the syscall restarts then
+    fn lock(&self) -> bool;
     fn unlock(&self);
 }
 
@@ -31,20 +33,25 @@ impl Mutex {
 }
 
 impl Lock for Mutex {
-    fn lock(&self) {
+    fn lock(&self) -> bool {
         let inner = self.inner.get_mut();
         if inner.is_locked {
             schedule::park_current(&mut inner.waiting_tasks);
+            false
         } else {
             inner.is_locked = true;
+            true
         }
     }
 
     fn unlock(&self) {
         let inner = self.inner.get_mut();
         inner.is_locked = false;
-        if let Some(task) = inner.waiting_tasks.pop_front() {
-            schedule::add_task(task);
+        // skip the tasks a signal has already woken up
+        while let Some(task) = inner.waiting_tasks.pop_front() {
+            if schedule::wake(task) {
+                break;
+            }
         }
     }
 }
@@ -0,0 +1,55 @@
+use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+
+use crate::{
+    cpu::local::CpuLocalCell,
+    task::{Task, current_task, schedule},
+};
+
+/// A counting semaphore
+pub struct Semaphore {
+    inner: CpuLocalCell<SemaphoreInner>,
+}
+
+pub struct SemaphoreInner {
+    pub count: usize,
+    pub waiting_tasks: VecDeque<Arc<Task>>,
+}
+
+impl Semaphore {
+    pub fn new(count: usize) -> Self {
+        Self {
+            inner: CpuLocalCell::new(SemaphoreInner {
+                count,
+                waiting_tasks: VecDeque::new(),
+            }),
+        }
+    }
+
+    /// Give a resource back and wake a task waiting for one
+    pub fn up(&self) {
+        let inner = self.inner.get_mut();
+        inner.count += 1;
+        // skip the tasks a signal has already woken up
+        while let Some(task) = inner.waiting_tasks.pop_front() {
+            if schedule::wake(task) {
+                break;
+            }
+        }
+    }
+
+    /// Take a resource, or park the current task until there is one, the
+    /// syscall restarts then
+    pub fn down(&self) {
+        let current = current_task().unwrap();
+        let inner = self.inner.get_mut();
+        // a signal woke the task up, it is still queued
+        inner
+            .waiting_tasks
+            .retain(|task| !Arc::ptr_eq(task, &current));
+        if inner.count == 0 {
+            schedule::park_current(&mut inner.waiting_tasks);
+        } else {
+            inner.count -= 1;
+        }
+    }
+}
@@ -1,84 +1,373 @@
 //! File and filesystem-related syscalls
-use core::ffi::CStr;
+use alloc::string::String;
 
-use alloc::slice;
-use alloc::vec::Vec;
+use super::{SysError, SysResult};
+use crate::fs::{
+    FdFlags, FileDescriptor, OpenFlags, SeekFrom, Stat, absolute_path, create_dir, is_dir,
+    link_file, make_pipe, open_file, remove_dir, rename_file, stat_file, unlink_file,
+};
+use crate::mm::memory_set::MapPermission;
+use crate::mm::{UserPtr, UserSlice};
+use crate::task::{current_process, current_task};
 
-use crate::fs::{OpenFlags, open_file};
-use crate::mm::UserBuffer;
-use crate::task::current_task;
-pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
+/// `dirfd` of the `*at` syscalls for paths relative to the current directory,
+/// the only kind of relative path they support
+const AT_FDCWD: isize = -100;
+/// `unlinkat` removes an empty directory instead of a file
+const AT_REMOVEDIR: u32 = 0x200;
+/// Longest path a syscall takes, without the terminating 0
+const PATH_MAX: usize = 4096;
+/// Largest file descriptor `dup3` may grow the fd table to, plus one
+const FD_MAX: usize = 1024;
+
+/// Read a path from user space and make it absolute against the current
+/// directory of the process.
+pub(super) fn user_path(path: UserPtr<u8>) -> Result<String, SysError> {
+    user_path_at(AT_FDCWD, path)
+}
+
+/// Like [`user_path`] for the `*at` syscalls, a relative path requires
+/// `dirfd` to be `AT_FDCWD`.
+fn user_path_at(dirfd: isize, path: UserPtr<u8>) -> Result<String, SysError> {
+    let path = path.read_cstr(PATH_MAX)?;
+    if dirfd != AT_FDCWD && !path.starts_with('/') {
+        return Err(SysError::EBADF);
+    }
+    let process = current_process().unwrap();
+    Ok(absolute_path(&process.get_inner().cwd, &path))
+}
+
+/// `err` if there is a file or directory at `path`, which an operation
+/// failed on, else `ENOENT`
+fn error_at(path: &str, err: SysError) -> SysError {
+    if stat_file(path).is_some() {
+        err
+    } else {
+        SysError::ENOENT
+    }
+}
+
+pub fn sys_write(fd: usize, buf: UserSlice) -> SysResult {
     let task = current_task().unwrap();
     let inner = task.get_mutable_inner();
 
     if fd >= inner.fd_table.len() {
-        return -1;
+        return Err(SysError::EBADF);
     }
     if let Some(file) = &inner.fd_table[fd] {
         if !file.writable() {
-            return -1;
+            return Err(SysError::EBADF);
         }
         let file = file.clone();
         // release current task TCB manually to avoid multi-borrow
 
-        let slice = unsafe { slice::from_raw_parts_mut::<'static, u8>(buf as *mut u8, len) };
-        let mut vec = Vec::new();
-        vec.push(slice);
-        file.write(UserBuffer::new(vec)) as isize
+        file.write(buf.buffer(MapPermission::R)?)
     } else {
-        -1
+        Err(SysError::EBADF)
     }
 }
 
-pub fn sys_read(fd: usize, buf: *const u8, len: usize) -> isize {
+pub fn sys_read(fd: usize, buf: UserSlice) -> SysResult {
     let task = current_task().unwrap();
     let inner = task.get_mutable_inner();
     if fd >= inner.fd_table.len() {
-        return -1;
+        return Err(SysError::EBADF);
     }
     if let Some(file) = &inner.fd_table[fd] {
         let file = file.clone();
         if !file.readable() {
-            return -1;
+            return Err(SysError::EBADF);
         }
 
-        let slice = unsafe { slice::from_raw_parts_mut::<'static, u8>(buf as *mut u8, len) };
-        let mut vec = Vec::new();
-        vec.push(slice);
-        file.read(UserBuffer::new(vec)) as isize
+        Ok(file.read(buf.buffer(MapPermission::W)?))
     } else {
-        -1
+        Err(SysError::EBADF)
     }
 }
 
-pub fn sys_open(path: *const u8, flags: u32) -> isize {
+/// A directory can only be opened read-only.
+pub fn sys_open(path: UserPtr<u8>, flags: u32) -> SysResult {
     let task = current_task().unwrap();
 
-    let path = unsafe {
-        match CStr::from_ptr(path).to_str() {
-            Ok(path) => path,
-            Err(_) => return -1,
-        }
+    let path = user_path(path)?;
+    let flags = OpenFlags::from_bits(flags).ok_or(SysError::EINVAL)?;
+    let fd_flags = if flags.contains(OpenFlags::CLOEXEC) {
+        FdFlags::CLOEXEC
+    } else {
+        FdFlags::empty()
     };
-    if let Some(inode) = open_file(path, OpenFlags::from_bits(flags).unwrap()) {
+    if let Some(inode) = open_file(&path, flags) {
         let inner = task.get_mutable_inner();
         let fd = inner.alloc_fd();
-        inner.fd_table[fd] = Some(inode);
-        fd as isize
+        inner.fd_table[fd] = Some(FileDescriptor::new(inode, fd_flags));
+        Ok(fd)
     } else {
-        -1
+        Err(error_at(&path, SysError::EISDIR))
+    }
+}
+
+const SEEK_SET: usize = 0;
+const SEEK_CUR: usize = 1;
+const SEEK_END: usize = 2;
+
+/// Pipes and devices can't seek, a file can't seek to a negative offset or
+/// past the max file size.
+pub fn sys_lseek(fd: usize, offset: isize, whence: usize) -> SysResult {
+    let task = current_task().unwrap();
+    let Some(Some(file)) = task.get_inner().fd_table.get(fd) else {
+        return Err(SysError::EBADF);
+    };
+    let pos = match whence {
+        SEEK_SET if offset >= 0 => SeekFrom::Start(offset as usize),
+        SEEK_CUR => SeekFrom::Current(offset),
+        SEEK_END => SeekFrom::End(offset),
+        _ => return Err(SysError::EINVAL),
+    };
+    if file.inode().is_none() {
+        return Err(SysError::ESPIPE);
+    }
+    file.seek(pos).ok_or(SysError::EINVAL)
+}
+
+/// Like [`sys_read`] at `offset`, the offset of the file stays unchanged.
+pub fn sys_pread64(fd: usize, buf: UserSlice, offset: usize) -> SysResult {
+    let task = current_task().unwrap();
+    let Some(Some(file)) = task.get_inner().fd_table.get(fd) else {
+        return Err(SysError::EBADF);
+    };
+    if !file.readable() {
+        return Err(SysError::EBADF);
     }
+    file.read_at(offset, buf.buffer(MapPermission::W)?)
+        .ok_or(SysError::ESPIPE)
 }
 
-pub fn sys_close(fd: usize) -> isize {
+/// Like [`sys_write`] at `offset`, the offset of the file stays unchanged.
+pub fn sys_pwrite64(fd: usize, buf: UserSlice, offset: usize) -> SysResult {
+    let task = current_task().unwrap();
+    let Some(Some(file)) = task.get_inner().fd_table.get(fd) else {
+        return Err(SysError::EBADF);
+    };
+    if !file.writable() {
+        return Err(SysError::EBADF);
+    }
+    file.write_at(offset, buf.buffer(MapPermission::R)?)
+}
+
+pub fn sys_close(fd: usize) -> SysResult {
     let task = current_task().unwrap();
     let inner = task.get_mutable_inner();
     if fd >= inner.fd_table.len() {
-        return -1;
+        return Err(SysError::EBADF);
     }
     if inner.fd_table[fd].is_none() {
-        return -1;
+        return Err(SysError::EBADF);
     }
     inner.fd_table[fd].take();
-    0
+    Ok(0)
+}
+
+/// Only files in the filesystem have metadata.
+pub fn sys_fstat(fd: usize, st: UserPtr<Stat>) -> SysResult {
+    let task = current_task().unwrap();
+    let Some(Some(file)) = task.get_inner().fd_table.get(fd) else {
+        return Err(SysError::EBADF);
+    };
+    let inode = file.inode().ok_or(SysError::EINVAL)?;
+    st.write(inode.stat())?;
+    Ok(0)
+}
+
+/// `flags` is ignored, there are no symbolic links to follow.
+pub fn sys_fstatat(dirfd: isize, path: UserPtr<u8>, st: UserPtr<Stat>) -> SysResult {
+    let path = user_path_at(dirfd, path)?;
+    let stat = stat_file(&path).ok_or(SysError::ENOENT)?;
+    st.write(stat)?;
+    Ok(0)
+}
+
+/// Duplicate `oldfd` to the lowest free descriptor, which is not
+/// close-on-exec.
+pub fn sys_dup(oldfd: usize) -> SysResult {
+    let task = current_task().unwrap();
+    let inner = task.get_mutable_inner();
+    let Some(Some(file)) = inner.fd_table.get(oldfd) else {
+        return Err(SysError::EBADF);
+    };
+    let file = file.file.clone();
+    let newfd = inner.alloc_fd();
+    inner.fd_table[newfd] = Some(FileDescriptor::new(file, FdFlags::empty()));
+    Ok(newfd)
+}
+
+/// Duplicate `oldfd` to `newfd`, closing what `newfd` referred to. `flags`
+/// may only be `O_CLOEXEC`.
+pub fn sys_dup3(oldfd: usize, newfd: usize, flags: u32) -> SysResult {
+    let task = current_task().unwrap();
+    let inner = task.get_mutable_inner();
+    let fd_flags = match flags {
+        0 => FdFlags::empty(),
+        flags if flags == OpenFlags::CLOEXEC.bits() => FdFlags::CLOEXEC,
+        _ => return Err(SysError::EINVAL),
+    };
+    if oldfd == newfd {
+        return Err(SysError::EINVAL);
+    }
+    if newfd >= FD_MAX {
+        return Err(SysError::EBADF);
+    }
+    let Some(Some(file)) = inner.fd_table.get(oldfd) else {
+        return Err(SysError::EBADF);
+    };
+    let file = file.file.clone();
+    if newfd >= inner.fd_table.len() {
+        inner.fd_table.resize(newfd + 1, None);
+    }
+    inner.fd_table[newfd] = Some(FileDescriptor::new(file, fd_flags));
+    Ok(newfd)
+}
+
+const F_GETFD: usize = 1;
+const F_SETFD: usize = 2;
+
+/// Only `F_GETFD` and `F_SETFD` are supported.
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> SysResult {
+    let task = current_task().unwrap();
+    let Some(Some(fd)) = task.get_mutable_inner().fd_table.get_mut(fd) else {
+        return Err(SysError::EBADF);
+    };
+    match cmd {
+        F_GETFD => Ok(fd.flags.bits() as usize),
+        F_SETFD => {
+            fd.flags = FdFlags::from_bits_truncate(arg as u32);
+            Ok(0)
+        }
+        _ => Err(SysError::EINVAL),
+    }
+}
+
+/// A device specific request `cmd` on `fd`, which files that are not
+/// devices don't support.
+pub fn sys_ioctl(fd: usize, cmd: usize, arg: usize) -> SysResult {
+    let task = current_task().unwrap();
+    let Some(Some(fd)) = task.get_inner().fd_table.get(fd) else {
+        return Err(SysError::EBADF);
+    };
+    let file = fd.file.clone();
+    file.ioctl(cmd, arg)
+}
+
+/// Store the read end and the write end of a new pipe in `pipe[0]` and
+/// `pipe[1]`.
+pub fn sys_pipe(pipe: UserPtr<[usize; 2]>) -> SysResult {
+    let task = current_task().unwrap();
+    let inner = task.get_mutable_inner();
+    let (pipe_read, pipe_write) = make_pipe();
+    let read_fd = inner.alloc_fd();
+    inner.fd_table[read_fd] = Some(FileDescriptor::new(pipe_read, FdFlags::empty()));
+    let write_fd = inner.alloc_fd();
+    inner.fd_table[write_fd] = Some(FileDescriptor::new(pipe_write, FdFlags::empty()));
+    if let Err(err) = pipe.write([read_fd, write_fd]) {
+        inner.fd_table[read_fd] = None;
+        inner.fd_table[write_fd] = None;
+        return Err(err);
+    }
+    Ok(0)
+}
+
+pub fn sys_mkdir(path: UserPtr<u8>) -> SysResult {
+    let path = user_path(path)?;
+    if create_dir(&path) {
+        Ok(0)
+    } else if stat_file(&path).is_some() {
+        Err(SysError::EEXIST)
+    } else {
+        Err(SysError::ENOENT)
+    }
+}
+
+/// Remove the directory at `path`, which has to be empty
+fn remove_dir_at(path: &str) -> SysResult {
+    if remove_dir(path) {
+        Ok(0)
+    } else if is_dir(path) {
+        Err(SysError::ENOTEMPTY)
+    } else {
+        Err(error_at(path, SysError::ENOTDIR))
+    }
+}
+
+/// Only an empty directory can be removed.
+pub fn sys_rmdir(path: UserPtr<u8>) -> SysResult {
+    remove_dir_at(&user_path(path)?)
+}
+
+pub fn sys_unlinkat(dirfd: isize, path: UserPtr<u8>, flags: u32) -> SysResult {
+    let path = user_path_at(dirfd, path)?;
+    if flags & AT_REMOVEDIR != 0 {
+        remove_dir_at(&path)
+    } else if unlink_file(&path) {
+        Ok(0)
+    } else {
+        Err(error_at(&path, SysError::EISDIR))
+    }
+}
+
+/// Hard links to directories are not allowed, `flags` is ignored.
+pub fn sys_linkat(
+    olddirfd: isize,
+    oldpath: UserPtr<u8>,
+    newdirfd: isize,
+    newpath: UserPtr<u8>,
+    _flags: u32,
+) -> SysResult {
+    let oldpath = user_path_at(olddirfd, oldpath)?;
+    let newpath = user_path_at(newdirfd, newpath)?;
+    if link_file(&oldpath, &newpath) {
+        Ok(0)
+    } else if is_dir(&oldpath) {
+        Err(SysError::EPERM)
+    } else if stat_file(&newpath).is_some() {
+        Err(SysError::EEXIST)
+    } else {
+        Err(SysError::ENOENT)
+    }
+}
+
+/// A directory can't be moved into itself.
+pub fn sys_renameat(
+    olddirfd: isize,
+    oldpath: UserPtr<u8>,
+    newdirfd: isize,
+    newpath: UserPtr<u8>,
+) -> SysResult {
+    let oldpath = user_path_at(olddirfd, oldpath)?;
+    let newpath = user_path_at(newdirfd, newpath)?;
+    if rename_file(&oldpath, &newpath) {
+        Ok(0)
+    } else {
+        Err(error_at(&oldpath, SysError::EINVAL))
+    }
+}
+
+pub fn sys_chdir(path: UserPtr<u8>) -> SysResult {
+    let path = user_path(path)?;
+    if !is_dir(&path) {
+        return Err(error_at(&path, SysError::ENOTDIR));
+    }
+    current_process().unwrap().get_mutable_inner().cwd = path;
+    Ok(0)
+}
+
+/// Returns the length of the path including the terminating 0, fails with
+/// `ERANGE` if it does not fit into `buf`.
+pub fn sys_getcwd(buf: UserSlice) -> SysResult {
+    let process = current_process().unwrap();
+    let mut cwd = process.get_inner().cwd.clone().into_bytes();
+    cwd.push(0);
+    if cwd.len() > buf.len() {
+        return Err(SysError::ERANGE);
+    }
+    buf.write(&cwd)?;
+    Ok(cwd.len())
 }
@@ -0,0 +1,184 @@
+//! Memory mapping syscalls
+use bitflags::bitflags;
+
+use crate::config::{MMAP_BASE, PAGE_SIZE, USER_SPACE_MAX, USER_SPACE_MIN};
+use crate::mm::address::{VirtAddr, VirtPageNum};
+use crate::mm::memory_set::{MapArea, MapFile, MapPermission, MapType};
+use crate::task::{current_process, current_task};
+
+use super::{SysError, SysResult};
+
+bitflags! {
+    /// Protection of a mapping, `PROT_*`
+    #[derive(Clone, Copy)]
+    struct MmapProt: usize {
+        const READ = 1 << 0;
+        const WRITE = 1 << 1;
+        const EXEC = 1 << 2;
+    }
+}
+
+bitflags! {
+    /// Flags of a mapping, `MAP_*`
+    #[derive(Clone, Copy)]
+    struct MmapFlags: usize {
+        const SHARED = 1 << 0;
+        const PRIVATE = 1 << 1;
+        const FIXED = 1 << 4;
+        const ANONYMOUS = 1 << 5;
+    }
+}
+
+impl MmapProt {
+    fn to_map_perm(self) -> MapPermission {
+        let mut perm = MapPermission::U;
+        // a writable page table entry must be readable as well
+        if self.intersects(Self::READ | Self::WRITE) {
+            perm |= MapPermission::R;
+        }
+        if self.contains(Self::WRITE) {
+            perm |= MapPermission::W;
+        }
+        if self.contains(Self::EXEC) {
+            perm |= MapPermission::X;
+        }
+        perm
+    }
+}
+
+/// Check that `[addr, addr + len)` is a non-empty, page aligned range of user
+/// space and return its pages.
+fn user_range(addr: usize, len: usize) -> Option<(VirtPageNum, VirtPageNum)> {
+    let end = addr.checked_add(len.checked_next_multiple_of(PAGE_SIZE)?)?;
+    if len == 0 || addr % PAGE_SIZE != 0 || addr < USER_SPACE_MIN || end > USER_SPACE_MAX {
+        return None;
+    }
+    Some((VirtPageNum(addr >> 12), VirtPageNum(end >> 12)))
+}
+
+/// Pages are allocated, or read from the file, on the first touch. Returns
+/// the address of the mapping.
+pub fn sys_mmap(
+    addr: usize,
+    len: usize,
+    prot: usize,
+    flags: usize,
+    fd: usize,
+    offset: usize,
+) -> SysResult {
+    let (Some(prot), Some(flags)) = (MmapProt::from_bits(prot), MmapFlags::from_bits(flags)) else {
+        return Err(SysError::EINVAL);
+    };
+    if flags.contains(MmapFlags::SHARED) == flags.contains(MmapFlags::PRIVATE)
+        || offset % PAGE_SIZE != 0
+    {
+        return Err(SysError::EINVAL);
+    }
+    let shared = flags.contains(MmapFlags::SHARED);
+    let file = if flags.contains(MmapFlags::ANONYMOUS) {
+        None
+    } else {
+        let task = current_task().unwrap();
+        let Some(Some(file)) = task.get_inner().fd_table.get(fd) else {
+            return Err(SysError::EBADF);
+        };
+        if !file.readable() || (shared && prot.contains(MmapProt::WRITE) && !file.writable()) {
+            return Err(SysError::EACCES);
+        }
+        let inode = file.inode().ok_or(SysError::ENODEV)?;
+        Some(MapFile { inode, offset })
+    };
+
+    let task = current_task().unwrap();
+    let memory_set = task.get_inner().memory_set.get_mut();
+    let (start, end) = if flags.contains(MmapFlags::FIXED) {
+        let (start, end) = user_range(addr, len).ok_or(SysError::EINVAL)?;
+        memory_set.remove_range(start, end);
+        (start, end)
+    } else {
+        let Some(pages) = len
+            .checked_next_multiple_of(PAGE_SIZE)
+            .map(|len| len / PAGE_SIZE)
+            .filter(|&pages| pages != 0)
+        else {
+            return Err(SysError::EINVAL);
+        };
+        // compared in pages, shifting them back into addresses may wrap
+        let user_pages = (USER_SPACE_MAX + 1) / PAGE_SIZE;
+        if pages > user_pages {
+            return Err(SysError::ENOMEM);
+        }
+        let hint = VirtAddr::from(addr.max(MMAP_BASE)).floor();
+        let start = memory_set.find_free_area(hint, pages);
+        match start.0.checked_add(pages) {
+            Some(end) if end <= user_pages => (start, VirtPageNum(end)),
+            _ => return Err(SysError::ENOMEM),
+        }
+    };
+
+    let mut area = MapArea::new(
+        start.into(),
+        end.into(),
+        MapType::Framed,
+        prot.to_map_perm(),
+    );
+    area.file = file;
+    area.shared = shared;
+    memory_set.push(area, None);
+    Ok(start.0 << 12)
+}
+
+/// Shared file mappings are written back before their pages go.
+pub fn sys_munmap(addr: usize, len: usize) -> SysResult {
+    let (start, end) = user_range(addr, len).ok_or(SysError::EINVAL)?;
+    let task = current_task().unwrap();
+    task.get_inner()
+        .memory_set
+        .get_mut()
+        .remove_range(start, end);
+    Ok(0)
+}
+
+/// Every page in the range must be mapped. `PROT_NONE` is only supported by
+/// `mmap`, a page table entry cannot describe it.
+pub fn sys_mprotect(addr: usize, len: usize, prot: usize) -> SysResult {
+    let Some(prot) = MmapProt::from_bits(prot).filter(|prot| !prot.is_empty()) else {
+        return Err(SysError::EINVAL);
+    };
+    let (start, end) = user_range(addr, len).ok_or(SysError::EINVAL)?;
+    let task = current_task().unwrap();
+    let memory_set = task.get_inner().memory_set.get_mut();
+    if memory_set.protect_range(start, end, prot.to_map_perm()) {
+        Ok(0)
+    } else {
+        Err(SysError::ENOMEM)
+    }
+}
+
+/// Writes are synchronous, `flags` is ignored.
+pub fn sys_msync(addr: usize, len: usize, _flags: usize) -> SysResult {
+    let (start, end) = user_range(addr, len).ok_or(SysError::EINVAL)?;
+    let task = current_task().unwrap();
+    task.get_inner().memory_set.get_mut().sync_range(start, end);
+    Ok(0)
+}
+
+/// Returns the new break, or the current one if `addr` is 0 or the break
+/// cannot move there.
+pub fn sys_brk(addr: usize) -> SysResult {
+    let process = current_process().unwrap();
+    if addr != 0 {
+        process.set_program_brk(addr);
+    }
+    Ok(process.get_inner().program_brk)
+}
+
+/// Returns the old break.
+pub fn sys_sbrk(size: isize) -> SysResult {
+    let process = current_process().unwrap();
+    let old_brk = process.get_inner().program_brk;
+    match old_brk.checked_add_signed(size) {
+        Some(new_brk) if process.set_program_brk(new_brk) => Ok(old_brk),
+        _ => Err(SysError::ENOMEM),
+    }
+}
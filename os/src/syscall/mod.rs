@@ -8,66 +8,200 @@
 //!
 //! For clarity, each single syscall is implemented as its own function, named
 //! `sys_` then the name of the syscall. You can find functions like this in
-//! submodules, and you should also implement syscalls this way.
+//! submodules, and you should also implement syscalls this way. They return
+//! a [`SysResult`], whose error reaches user space as a negated errno.
 const SYSCALL_SHUTDOWN: usize = 1;
+const SYSCALL_GETCWD: usize = 17;
+const SYSCALL_DUP: usize = 23;
+const SYSCALL_DUP3: usize = 24;
+const SYSCALL_FCNTL: usize = 25;
+const SYSCALL_IOCTL: usize = 29;
+const SYSCALL_UNLINKAT: usize = 35;
+const SYSCALL_LINKAT: usize = 37;
+const SYSCALL_RENAMEAT: usize = 38;
+const SYSCALL_CHDIR: usize = 49;
 const SYSCALL_OPEN: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_PIPE: usize = 59;
+const SYSCALL_LSEEK: usize = 62;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_PREAD64: usize = 67;
+const SYSCALL_PWRITE64: usize = 68;
+const SYSCALL_FSTATAT: usize = 79;
+const SYSCALL_FSTAT: usize = 80;
 const SYSCALL_EXIT: usize = 93;
 const SYSCALL_ABORT: usize = 94;
+const SYSCALL_FUTEX: usize = 98;
+const SYSCALL_NANOSLEEP: usize = 101;
+const SYSCALL_CLOCK_NANOSLEEP: usize = 115;
+const SYSCALL_SCHED_SETSCHEDULER: usize = 119;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_KILL: usize = 129;
+const SYSCALL_SIGACTION: usize = 134;
+const SYSCALL_SIGPROCMASK: usize = 135;
+const SYSCALL_SIGRETURN: usize = 139;
+const SYSCALL_SET_PRIORITY: usize = 140;
+const SYSCALL_SETPGID: usize = 154;
+const SYSCALL_GETPGID: usize = 155;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
-const SYSCALL_SPAWN: usize = 220;
+const SYSCALL_BRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
+const SYSCALL_FORK: usize = 220;
+const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_MPROTECT: usize = 226;
+const SYSCALL_MSYNC: usize = 227;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_SPAWN: usize = 400;
+const SYSCALL_SBRK: usize = 401;
+const SYSCALL_MKDIR: usize = 402;
+const SYSCALL_RMDIR: usize = 403;
 const SYSCALL_THREAD_CREATE: usize = 1000;
 const SYSCALL_GETTID: usize = 1001;
 const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_THREAD_EXIT: usize = 1003;
+const SYSCALL_THREAD_DETACH: usize = 1004;
 const SYSCALL_MUTEX_CREATE: usize = 1010;
 const SYSCALL_MUTEX_LOCK: usize = 1011;
 const SYSCALL_MUTEX_UNLOCK: usize = 1012;
+const SYSCALL_SEMAPHORE_CREATE: usize = 1020;
+const SYSCALL_SEMAPHORE_UP: usize = 1021;
+const SYSCALL_SEMAPHORE_DOWN: usize = 1022;
+const SYSCALL_CONDVAR_CREATE: usize = 1030;
+const SYSCALL_CONDVAR_SIGNAL: usize = 1031;
+const SYSCALL_CONDVAR_WAIT: usize = 1032;
+const SYSCALL_CONDVAR_BROADCAST: usize = 1033;
 
+mod errno;
 mod fs;
+mod mm;
 mod power;
 mod process;
+mod signal;
 mod sync;
 mod thread;
+mod time;
 
+pub use errno::{SysError, SysResult};
 use fs::*;
-use log::trace;
+use log::{info, trace};
+use mm::*;
 use power::*;
 use process::*;
 use riscv::register::sstatus;
+use signal::*;
 use sync::*;
 use thread::*;
-/// handle syscall exception with `syscall_id` and other arguments
-pub fn handle_syscall(syscall_id: usize, args: [usize; 3]) -> isize {
+use time::*;
+
+use crate::mm::{UserPtr, UserSlice};
+/// handle syscall exception with `syscall_id` and the arguments in a0..a5,
+/// an unknown `syscall_id` fails with `ENOSYS`
+pub fn handle_syscall(syscall_id: usize, args: [usize; 6]) -> isize {
     trace!("handle syscall id: {}", syscall_id);
     unsafe {
         sstatus::set_sum();
     }
     let res = match syscall_id {
         SYSCALL_SHUTDOWN => sys_shutdown(),
-        SYSCALL_OPEN => sys_open(args[0] as *const u8, args[1] as u32),
+        SYSCALL_GETCWD => sys_getcwd(UserSlice::new(args[0], args[1])),
+        SYSCALL_DUP => sys_dup(args[0]),
+        SYSCALL_DUP3 => sys_dup3(args[0], args[1], args[2] as u32),
+        SYSCALL_FCNTL => sys_fcntl(args[0], args[1], args[2]),
+        SYSCALL_IOCTL => sys_ioctl(args[0], args[1], args[2]),
+        SYSCALL_UNLINKAT => sys_unlinkat(args[0] as isize, UserPtr::new(args[1]), args[2] as u32),
+        SYSCALL_LINKAT => sys_linkat(
+            args[0] as isize,
+            UserPtr::new(args[1]),
+            args[2] as isize,
+            UserPtr::new(args[3]),
+            args[4] as u32,
+        ),
+        SYSCALL_RENAMEAT => sys_renameat(
+            args[0] as isize,
+            UserPtr::new(args[1]),
+            args[2] as isize,
+            UserPtr::new(args[3]),
+        ),
+        SYSCALL_CHDIR => sys_chdir(UserPtr::new(args[0])),
+        SYSCALL_OPEN => sys_open(UserPtr::new(args[0]), args[1] as u32),
         SYSCALL_CLOSE => sys_close(args[0]),
-        SYSCALL_READ => sys_read(args[0], args[1] as *const u8, args[2]),
-        SYSCALL_WRITE => sys_write(args[0], args[1] as *const u8, args[2]),
+        SYSCALL_PIPE => sys_pipe(UserPtr::new(args[0])),
+        SYSCALL_READ => sys_read(args[0], UserSlice::new(args[1], args[2])),
+        SYSCALL_LSEEK => sys_lseek(args[0], args[1] as isize, args[2]),
+        SYSCALL_WRITE => sys_write(args[0], UserSlice::new(args[1], args[2])),
+        SYSCALL_PREAD64 => sys_pread64(args[0], UserSlice::new(args[1], args[2]), args[3]),
+        SYSCALL_PWRITE64 => sys_pwrite64(args[0], UserSlice::new(args[1], args[2]), args[3]),
+        SYSCALL_FSTATAT => sys_fstatat(
+            args[0] as isize,
+            UserPtr::new(args[1]),
+            UserPtr::new(args[2]),
+        ),
+        SYSCALL_FSTAT => sys_fstat(args[0], UserPtr::new(args[1])),
         SYSCALL_EXIT => sys_exit(args[0] as i32),
         SYSCALL_ABORT => sys_abort(),
+        SYSCALL_FUTEX => sys_futex(args[0], args[1], args[2]),
+        SYSCALL_NANOSLEEP => sys_nanosleep(UserPtr::new(args[0]), UserPtr::new(args[1])),
+        SYSCALL_CLOCK_NANOSLEEP => sys_clock_nanosleep(
+            args[0],
+            args[1],
+            UserPtr::new(args[2]),
+            UserPtr::new(args[3]),
+        ),
+        SYSCALL_SCHED_SETSCHEDULER => sys_sched_setscheduler(args[0]),
         SYSCALL_YIELD => sys_yield(),
+        SYSCALL_KILL => sys_kill(args[0] as isize, args[1]),
+        SYSCALL_SIGACTION => sys_sigaction(args[0], UserPtr::new(args[1]), UserPtr::new(args[2])),
+        SYSCALL_SIGPROCMASK => {
+            sys_sigprocmask(args[0], UserPtr::new(args[1]), UserPtr::new(args[2]))
+        }
+        SYSCALL_SIGRETURN => sys_sigreturn(),
+        SYSCALL_SET_PRIORITY => sys_set_priority(args[0], args[1] as isize),
+        SYSCALL_SETPGID => sys_setpgid(args[0], args[1]),
+        SYSCALL_GETPGID => sys_getpgid(args[0]),
         SYSCALL_GET_TIME => sys_get_time(),
         SYSCALL_GETPID => sys_getpid(),
-        SYSCALL_SPAWN => sys_spawn(args[0] as *const u8),
-        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
+        SYSCALL_FORK => sys_fork(),
+        SYSCALL_EXEC => sys_exec(
+            UserPtr::new(args[0]),
+            UserPtr::new(args[1]),
+            UserPtr::new(args[2]),
+        ),
+        SYSCALL_BRK => sys_brk(args[0]),
+        SYSCALL_MUNMAP => sys_munmap(args[0], args[1]),
+        SYSCALL_MMAP => sys_mmap(args[0], args[1], args[2], args[3], args[4], args[5]),
+        SYSCALL_MPROTECT => sys_mprotect(args[0], args[1], args[2]),
+        SYSCALL_MSYNC => sys_msync(args[0], args[1], args[2]),
+        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, UserPtr::new(args[1]), args[2]),
+        SYSCALL_SPAWN => sys_spawn(UserPtr::new(args[0])),
+        SYSCALL_SBRK => sys_sbrk(args[0] as isize),
+        SYSCALL_MKDIR => sys_mkdir(UserPtr::new(args[0])),
+        SYSCALL_RMDIR => sys_rmdir(UserPtr::new(args[0])),
         SYSCALL_THREAD_CREATE => sys_thread_create(args[0], args[1]),
         SYSCALL_GETTID => sys_gettid(),
-        SYSCALL_WAITTID => sys_waittid(args[0]) as isize,
+        SYSCALL_WAITTID => sys_waittid(args[0]),
+        SYSCALL_THREAD_EXIT => sys_thread_exit(args[0] as i32),
+        SYSCALL_THREAD_DETACH => sys_thread_detach(args[0]),
         SYSCALL_MUTEX_CREATE => sys_mutex_create(),
         SYSCALL_MUTEX_LOCK => sys_mutex_lock(args[0]),
         SYSCALL_MUTEX_UNLOCK => sys_mutex_unlock(args[0]),
-        _ => panic!("Unsupported syscall_id: {}", syscall_id),
+        SYSCALL_SEMAPHORE_CREATE => sys_semaphore_create(args[0]),
+        SYSCALL_SEMAPHORE_UP => sys_semaphore_up(args[0]),
+        SYSCALL_SEMAPHORE_DOWN => sys_semaphore_down(args[0]),
+        SYSCALL_CONDVAR_CREATE => sys_condvar_create(),
+        SYSCALL_CONDVAR_SIGNAL => sys_condvar_signal(args[0]),
+        SYSCALL_CONDVAR_WAIT => sys_condvar_wait(args[0], args[1]),
+        SYSCALL_CONDVAR_BROADCAST => sys_condvar_broadcast(args[0]),
+        _ => {
+            info!("Unsupported syscall_id: {}", syscall_id);
+            Err(SysError::ENOSYS)
+        }
     };
-    trace!("handle syscall {} done, res {}", syscall_id, res);
-    res
+    trace!("handle syscall {} done, res {:?}", syscall_id, res);
+    match res {
+        Ok(value) => value as isize,
+        Err(err) => err.to_ret(),
+    }
 }
@@ -1,92 +1,217 @@
-use core::ffi::CStr;
-
 use crate::cpu::processor::PROCESSOR;
 use crate::fs::{OpenFlags, open_file};
-use crate::task::schedule::{self, add_task};
-use crate::task::{Task, TaskStatus, current_task};
+use crate::mm::UserPtr;
+use crate::mm::memory_set::parse_elf;
+use crate::task::schedule::{self, NICE_MAX, NICE_MIN, SchedPolicy, add_task};
+use crate::task::stack::{ARGS_SPACE, args_size};
+use crate::task::wait::{self, Wait, WaitTarget, stopped_code};
+use crate::task::{INITPROC, Task, TaskStatus, current_process, current_task, find_process};
 use crate::timer::get_time_ms;
+
+use super::fs::user_path;
+use super::{SysError, SysResult};
+use alloc::string::String;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 
-pub fn sys_exit(exit_code: i32) -> isize {
+pub fn sys_exit(exit_code: i32) -> SysResult {
     PROCESSOR.as_mut().exit_current(exit_code);
-    0
+    Ok(0)
 }
 
-pub fn sys_abort() -> isize {
+pub fn sys_abort() -> SysResult {
     PROCESSOR.as_mut().abort_current();
-    0
+    Ok(0)
 }
 
-pub fn sys_yield() -> isize {
+pub fn sys_yield() -> SysResult {
     PROCESSOR.as_mut().yield_current();
-    0
+    Ok(0)
+}
+
+/// Set the niceness of every thread of the process `pid`, 0 for the
+/// caller, clamped to the range there is. Children and threads created
+/// later inherit it. Only a process itself may lower its niceness, and not
+/// below 0, initproc may lower any.
+pub fn sys_set_priority(pid: usize, nice: isize) -> SysResult {
+    let current = current_process().unwrap();
+    let process = match pid {
+        0 => Some(current.clone()),
+        _ => find_process(pid),
+    }
+    .ok_or(SysError::ESRCH)?;
+    let nice = nice.clamp(NICE_MIN, NICE_MAX);
+    let may_lower =
+        Arc::ptr_eq(&current, &INITPROC) || (Arc::ptr_eq(&process, &current) && nice >= 0);
+    if nice < process.get_inner().sched.nice && !may_lower {
+        return Err(SysError::EPERM);
+    }
+    // the main thread is the process itself, its slot `threads[0]` is empty
+    process.get_mutable_inner().sched.nice = nice;
+    let inner = process.get_inner();
+    for thread in inner.threads.iter().flatten() {
+        thread.get_mutable_inner().sched.nice = nice;
+    }
+    Ok(0)
+}
+
+/// Switch the whole system to the scheduling `policy`, returns the one
+/// before. Only initproc may pick another policy than the one there is.
+pub fn sys_sched_setscheduler(policy: usize) -> SysResult {
+    let policy = SchedPolicy::from_raw(policy).ok_or(SysError::EINVAL)?;
+    let current = current_process().unwrap();
+    if policy != schedule::policy() && !Arc::ptr_eq(&current, &INITPROC) {
+        return Err(SysError::EPERM);
+    }
+    Ok(schedule::set_policy(policy) as usize)
+}
+
+pub fn sys_get_time() -> SysResult {
+    Ok(get_time_ms())
 }
 
-pub fn sys_get_time() -> isize {
-    get_time_ms() as isize
+pub fn sys_getpid() -> SysResult {
+    Ok(current_task().unwrap().taskid.value)
 }
 
-pub fn sys_getpid() -> isize {
-    current_task().unwrap().taskid.value as isize
+/// Only the main thread may fork, the child is a copy of it alone.
+/// Fails with `EINVAL` when called from another thread.
+pub fn sys_fork() -> SysResult {
+    let current = current_task().unwrap();
+    if current.tid != 0 {
+        return Err(SysError::EINVAL);
+    }
+    let new_task = current.fork();
+    let pid = new_task.taskid.value;
+    current.get_mutable_inner().children.push(new_task.clone());
+    add_task(new_task);
+    Ok(pid)
 }
 
-pub fn sys_spawn(path: *const u8) -> isize {
-    let path = unsafe {
-        match CStr::from_ptr(path).to_str() {
-            Ok(path) => path,
-            Err(_) => return -1,
+/// Longest argument or environment string that exec takes
+const ARG_MAX: usize = 4096;
+
+/// Collect a null-terminated array of C strings, a null array is empty.
+fn read_str_array(mut ptr: UserPtr<UserPtr<u8>>) -> Result<Vec<String>, SysError> {
+    let mut strs = Vec::new();
+    if ptr.is_null() {
+        return Ok(strs);
+    }
+    loop {
+        let str = ptr.read()?;
+        if str.is_null() {
+            return Ok(strs);
         }
-    };
-    if let Some(app_inode) = open_file(path, OpenFlags::RDONLY) {
+        strs.push(str.read_cstr(ARG_MAX)?);
+        ptr = ptr.add(1);
+    }
+}
+
+/// The caller must be the only thread of its process, it fails with `EBUSY`
+/// if it is not. Fails with `E2BIG` if the arguments take more than their
+/// part of the stack and with `ENOEXEC` if the file is not a program, the
+/// caller goes on then. Returns argc to the new program.
+pub fn sys_exec(
+    path: UserPtr<u8>,
+    argv: UserPtr<UserPtr<u8>>,
+    envp: UserPtr<UserPtr<u8>>,
+) -> SysResult {
+    let current = current_task().unwrap();
+    if current.tid != 0
+        || current
+            .get_inner()
+            .threads
+            .iter()
+            .flatten()
+            .any(|thread| thread.get_inner().status != TaskStatus::Zombie)
+    {
+        return Err(SysError::EBUSY);
+    }
+    let path = user_path(path)?;
+    let args = read_str_array(argv)?;
+    let envs = read_str_array(envp)?;
+    if args_size(&args, &envs) > ARGS_SPACE {
+        return Err(SysError::E2BIG);
+    }
+    let app_inode = open_file(&path, OpenFlags::RDONLY).ok_or(SysError::ENOENT)?;
+    let all_data = app_inode.read_all();
+    parse_elf(&all_data).ok_or(SysError::ENOEXEC)?;
+    current.exec(all_data.as_slice(), &args, &envs)
+}
+
+pub fn sys_spawn(path: UserPtr<u8>) -> SysResult {
+    let path = user_path(path)?;
+    if let Some(app_inode) = open_file(&path, OpenFlags::RDONLY) {
         let all_data = app_inode.read_all();
         let current = current_task().unwrap();
-        let new_task = Task::new_with_elf(all_data.as_slice());
+        let new_task = Task::new_with_elf(all_data.as_slice()).ok_or(SysError::ENOEXEC)?;
         let pid = new_task.taskid.value;
         new_task.get_mutable_inner().parent = Some(Arc::downgrade(&current));
+        new_task.get_mutable_inner().cwd = current_process().unwrap().get_inner().cwd.clone();
+        new_task.get_mutable_inner().pgid = current_process().unwrap().get_inner().pgid;
+        // the new program inherits the open files like after fork and exec
+        new_task.get_mutable_inner().fd_table = current.get_inner().fd_table.clone();
+        new_task.get_mutable_inner().close_on_exec();
         current.get_mutable_inner().children.push(new_task.clone());
         add_task(new_task);
-        pid as isize
+        Ok(pid)
     } else {
-        -1
+        Err(SysError::ENOENT)
     }
 }
 
-/// If there is not a child process whose pid is same as given, return -1.
-/// Else if there is a child process but it is still running, return -2.
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
-    let current = current_task().unwrap();
-    // find a child process
+/// Return without waiting if no child has exited
+const WNOHANG: usize = 1;
+/// Also return if a child is stopped
+const WUNTRACED: usize = 2;
 
+/// Wait for the child `pid`, any child if `pid` is -1, to exit and store
+/// its exit code. Returns the pid of the child, 0 if it is still running
+/// with `WNOHANG`. With `WUNTRACED` a stopped child is returned too, with
+/// the code of [`stopped_code`]. Fails with `ECHILD` if there is no such
+/// child, but initproc waiting for any child blocks until an orphan handed
+/// over to it exits. The caller blocks until the child itself hands over
+/// its status, see [`crate::task::wait`].
+pub fn sys_waitpid(pid: isize, exit_code_ptr: UserPtr<i32>, options: usize) -> SysResult {
+    let current = current_task().unwrap();
     let inner = current.get_mutable_inner();
-    if !inner
-        .children
-        .iter()
-        .any(|p| pid == -1 || pid as usize == p.taskid.value)
-    {
-        return -1;
+    let is_target = |child: &Arc<Task>| pid == -1 || pid as usize == child.taskid.value;
+    let adopts = pid == -1 && Arc::ptr_eq(&current, &INITPROC);
+    if !adopts && !inner.children.iter().any(is_target) {
+        return Err(SysError::ECHILD);
     }
-    let pair = inner
+    // a wait queue the child was parked on when it got killed may
+    // still hold it, it is freed once that queue is woken up
+    if let Some(idx) = inner
         .children
         .iter()
-        .enumerate()
-        .find(|(_, p)| pid == -1 || pid as usize == p.taskid.value);
-    if let Some((idx, child)) = pair {
-        if child.get_inner().status != TaskStatus::Zombie {
-            current.get_mutable_inner().status = TaskStatus::Waiting;
-            schedule::park_current(&mut child.get_mutable_inner().waiting_tasks);
-            return -1;
+        .position(|child| is_target(child) && child.get_inner().status == TaskStatus::Zombie)
+    {
+        // the child stays until its status is stored
+        if !exit_code_ptr.is_null() {
+            exit_code_ptr.write(inner.children[idx].get_inner().exit_code)?;
         }
-
         let child = inner.children.remove(idx);
-        // confirm that child will be deallocated after being removed from children list
-        assert_eq!(Arc::strong_count(&child), 1);
-        let found_pid = child.taskid.value;
-        let exit_code = child.get_inner().exit_code;
-        unsafe {
-            *exit_code_ptr = exit_code;
+        return Ok(child.taskid.value);
+    }
+    if options & WUNTRACED != 0 {
+        if let Some((child, signal)) = inner
+            .children
+            .iter()
+            .filter(|child| is_target(child))
+            .find_map(|child| Some((child, child.get_inner().stop_signal?)))
+        {
+            if !exit_code_ptr.is_null() {
+                exit_code_ptr.write(stopped_code(signal))?;
+            }
+            return Ok(child.taskid.value);
         }
-        found_pid as isize
-    } else {
-        -2
     }
+    if options & WNOHANG != 0 {
+        return Ok(0);
+    }
+    let untraced = options & WUNTRACED != 0;
+    let wait = Wait::new(WaitTarget::Child { pid, untraced }, exit_code_ptr);
+    wait::block_on(&current, &mut inner.child_waiters, wait);
+    Ok(0)
 }
@@ -0,0 +1,148 @@
+use crate::mm::UserPtr;
+use crate::task::signal::{SignalAction, SignalFlags, restore_frame};
+use crate::task::{INITPROC, current_process, current_task, find_group, find_process, processes};
+use alloc::{sync::Arc, vec::Vec};
+
+use super::{SysError, SysResult};
+
+const SIG_BLOCK: usize = 0;
+const SIG_UNBLOCK: usize = 1;
+const SIG_SETMASK: usize = 2;
+
+/// Send the signal `signum` to the process `pid`, to the process group
+/// `-pid` if `pid` is below -1, to the group of the caller if `pid` is 0,
+/// or to every process but the caller if `pid` is -1. Signal 0 only checks
+/// that there is a target. The init process can't be signalled.
+pub fn sys_kill(pid: isize, signum: usize) -> SysResult {
+    let signal = match signum {
+        0 => None,
+        _ => match SignalFlags::from_signum(signum) {
+            Some(signal) => Some(signal),
+            None => return Err(SysError::EINVAL),
+        },
+    };
+    let current = current_process().unwrap();
+    let targets: Vec<_> = match pid {
+        0 => find_group(current.get_inner().pgid),
+        -1 => processes()
+            .into_iter()
+            .filter(|process| !Arc::ptr_eq(process, &current))
+            .collect(),
+        ..-1 => find_group(pid.unsigned_abs()),
+        _ => find_process(pid as usize).into_iter().collect(),
+    };
+    let targets: Vec<_> = targets
+        .into_iter()
+        .filter(|process| !Arc::ptr_eq(process, &INITPROC))
+        .collect();
+    if targets.is_empty() {
+        return Err(SysError::ESRCH);
+    }
+    if let Some(signal) = signal {
+        for process in targets {
+            process.send_signal(signal);
+        }
+    }
+    Ok(0)
+}
+
+/// Move the process `pid` into the process group `pgid`. A `pid` of 0 is
+/// the caller and a `pgid` of 0 is `pid`, so `setpgid(0, 0)` makes the
+/// caller lead a new group. Only the caller and its children can be moved.
+pub fn sys_setpgid(pid: usize, pgid: usize) -> SysResult {
+    let current = current_process().unwrap();
+    let process = if pid == 0 || pid == current.taskid.value {
+        current.clone()
+    } else {
+        let inner = current.get_inner();
+        match inner
+            .children
+            .iter()
+            .find(|child| child.taskid.value == pid)
+        {
+            Some(child) => child.clone(),
+            None => return Err(SysError::ESRCH),
+        }
+    };
+    let pgid = match pgid {
+        0 => process.taskid.value,
+        _ => pgid,
+    };
+    process.get_mutable_inner().pgid = pgid;
+    Ok(0)
+}
+
+/// The process group of the process `pid`, 0 is the caller
+pub fn sys_getpgid(pid: usize) -> SysResult {
+    let process = match pid {
+        0 => current_process(),
+        _ => find_process(pid),
+    };
+    match process {
+        Some(process) => Ok(process.get_inner().pgid),
+        None => Err(SysError::ESRCH),
+    }
+}
+
+/// Store the action for `signum` in `old_action` and replace it with
+/// `action`, either may be null. The action of SIGKILL and SIGSTOP can't be
+/// changed.
+pub fn sys_sigaction(
+    signum: usize,
+    action: UserPtr<SignalAction>,
+    old_action: UserPtr<SignalAction>,
+) -> SysResult {
+    let Some(signal) = SignalFlags::from_signum(signum) else {
+        return Err(SysError::EINVAL);
+    };
+    if !action.is_null() && signal.intersects(SignalFlags::UNCATCHABLE) {
+        return Err(SysError::EINVAL);
+    }
+    let process = current_process().unwrap();
+    let actions = &mut process.get_mutable_inner().signal_actions;
+    if !old_action.is_null() {
+        old_action.write(actions[signum])?;
+    }
+    if !action.is_null() {
+        actions[signum] = action.read()?;
+        actions[signum].mask -= SignalFlags::UNCATCHABLE;
+    }
+    Ok(0)
+}
+
+/// Store the blocked signals in `old_set` and change them with `set` as
+/// `how` says, either may be null. SIGKILL and SIGSTOP are never blocked.
+pub fn sys_sigprocmask(
+    how: usize,
+    set: UserPtr<SignalFlags>,
+    old_set: UserPtr<SignalFlags>,
+) -> SysResult {
+    let task = current_task().unwrap();
+    let blocked = &mut task.get_mutable_inner().blocked_signals;
+    if !old_set.is_null() {
+        old_set.write(*blocked)?;
+    }
+    if set.is_null() {
+        return Ok(0);
+    }
+    let set = set.read()?;
+    match how {
+        SIG_BLOCK => *blocked |= set,
+        SIG_UNBLOCK => *blocked -= set,
+        SIG_SETMASK => *blocked = set,
+        _ => return Err(SysError::EINVAL),
+    }
+    *blocked -= SignalFlags::UNCATCHABLE;
+    Ok(0)
+}
+
+/// Return from a signal handler. Returns the a0 it restores, fails with
+/// `EFAULT` if the user sp does not point at a signal frame.
+pub fn sys_sigreturn() -> SysResult {
+    let task = current_task().unwrap();
+    let inner = task.get_mutable_inner();
+    if !restore_frame(inner) {
+        return Err(SysError::EFAULT);
+    }
+    Ok(inner.user_ctx.general.a0)
+}
@@ -1,25 +1,136 @@
-use crate::{sync::mutex::Mutex, task::current_process};
+use crate::{
+    mm::memory_set::MapPermission,
+    sync::{
+        condvar::Condvar,
+        futex::{futex_wait, futex_wake},
+        mutex::Mutex,
+        semaphore::Semaphore,
+    },
+    task::{current_process, current_task},
+};
 use alloc::sync::Arc;
-pub fn sys_mutex_create() -> isize {
+
+use super::{SysError, SysResult};
+
+pub fn sys_mutex_create() -> SysResult {
     let process = current_process().unwrap();
     let process_inner = process.get_mutable_inner();
     let mutex_id = process_inner.alloc_mutex();
     process_inner.mutex_list[mutex_id] = Some(Arc::new(Mutex::new()));
-    mutex_id as isize
+    Ok(mutex_id)
 }
 
-pub fn sys_mutex_lock(mutex_id: usize) -> isize {
+pub fn sys_mutex_lock(mutex_id: usize) -> SysResult {
     let process = current_process().unwrap();
-    let process_inner = process.get_mutable_inner();
-    let mutex = process_inner.mutex_list[mutex_id].as_ref().unwrap();
+    let Some(Some(mutex)) = process.get_inner().mutex_list.get(mutex_id) else {
+        return Err(SysError::EINVAL);
+    };
     mutex.lock();
-    0
+    Ok(0)
 }
 
-pub fn sys_mutex_unlock(mutex_id: usize) -> isize {
+pub fn sys_mutex_unlock(mutex_id: usize) -> SysResult {
     let process = current_process().unwrap();
-    let process_inner = process.get_mutable_inner();
-    let mutex = process_inner.mutex_list[mutex_id].as_ref().unwrap();
+    let Some(Some(mutex)) = process.get_inner().mutex_list.get(mutex_id) else {
+        return Err(SysError::EINVAL);
+    };
     mutex.unlock();
-    0
+    Ok(0)
+}
+
+/// Create a semaphore holding `count` resources and return its id
+pub fn sys_semaphore_create(count: usize) -> SysResult {
+    let process = current_process().unwrap();
+    let process_inner = process.get_mutable_inner();
+    let sem_id = process_inner.alloc_semaphore();
+    process_inner.semaphore_list[sem_id] = Some(Arc::new(Semaphore::new(count)));
+    Ok(sem_id)
+}
+
+pub fn sys_semaphore_up(sem_id: usize) -> SysResult {
+    let process = current_process().unwrap();
+    let Some(Some(semaphore)) = process.get_inner().semaphore_list.get(sem_id) else {
+        return Err(SysError::EINVAL);
+    };
+    semaphore.up();
+    Ok(0)
+}
+
+/// Take a resource of the semaphore, waiting until there is one
+pub fn sys_semaphore_down(sem_id: usize) -> SysResult {
+    let process = current_process().unwrap();
+    let Some(Some(semaphore)) = process.get_inner().semaphore_list.get(sem_id) else {
+        return Err(SysError::EINVAL);
+    };
+    semaphore.down();
+    Ok(0)
+}
+
+pub fn sys_condvar_create() -> SysResult {
+    let process = current_process().unwrap();
+    let process_inner = process.get_mutable_inner();
+    let condvar_id = process_inner.alloc_condvar();
+    process_inner.condvar_list[condvar_id] = Some(Arc::new(Condvar::new()));
+    Ok(condvar_id)
+}
+
+/// Wake one task waiting on the condition variable
+pub fn sys_condvar_signal(condvar_id: usize) -> SysResult {
+    let process = current_process().unwrap();
+    let Some(Some(condvar)) = process.get_inner().condvar_list.get(condvar_id) else {
+        return Err(SysError::EINVAL);
+    };
+    condvar.signal();
+    Ok(0)
+}
+
+/// Wake every task waiting on the condition variable
+pub fn sys_condvar_broadcast(condvar_id: usize) -> SysResult {
+    let process = current_process().unwrap();
+    let Some(Some(condvar)) = process.get_inner().condvar_list.get(condvar_id) else {
+        return Err(SysError::EINVAL);
+    };
+    condvar.broadcast();
+    Ok(0)
+}
+
+/// Release the mutex `mutex_id`, which the caller holds, wait on the
+/// condition variable and take the mutex again before returning
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> SysResult {
+    let process = current_process().unwrap();
+    let inner = process.get_inner();
+    let (Some(Some(condvar)), Some(Some(mutex))) = (
+        inner.condvar_list.get(condvar_id),
+        inner.mutex_list.get(mutex_id),
+    ) else {
+        return Err(SysError::EINVAL);
+    };
+    condvar.wait(mutex);
+    Ok(0)
+}
+
+const FUTEX_WAIT: usize = 0;
+const FUTEX_WAKE: usize = 1;
+/// The futex is not shared with other processes, which all of them are
+const FUTEX_PRIVATE_FLAG: usize = 128;
+
+/// `FUTEX_WAIT` waits on the `u32` at `uaddr` if it holds `val`, failing
+/// with `EAGAIN` right away if it does not. `FUTEX_WAKE` wakes at most
+/// `val` waiters and returns how many it woke. The timeout and the other
+/// operations are not supported.
+pub fn sys_futex(uaddr: usize, op: usize, val: usize) -> SysResult {
+    let task = current_task().unwrap();
+    let allowed = task.get_inner().memory_set.get_mut().user_range_allows(
+        uaddr.into(),
+        size_of::<u32>(),
+        MapPermission::R,
+    );
+    if uaddr % size_of::<u32>() != 0 || !allowed {
+        return Err(SysError::EINVAL);
+    }
+    match op & !FUTEX_PRIVATE_FLAG {
+        FUTEX_WAIT => futex_wait(uaddr, val as u32),
+        FUTEX_WAKE => Ok(futex_wake(uaddr, val)),
+        _ => Err(SysError::ENOSYS),
+    }
 }
@@ -1,41 +1,77 @@
+use crate::cpu::processor::PROCESSOR;
+use crate::mm::UserPtr;
 use crate::task::{
     Task, TaskStatus, current_process, current_task,
-    schedule::{self, add_task},
+    schedule::add_task,
+    wait::{self, Wait, WaitTarget},
 };
 
-pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+use super::{SysError, SysResult};
+
+pub fn sys_thread_create(entry: usize, arg: usize) -> SysResult {
     let process = current_process().unwrap();
     // create a new thread
     let thread = Task::new_thread(&process, entry, arg);
     let tid = thread.tid;
     add_task(thread);
-    tid as isize
+    Ok(tid)
+}
+
+pub fn sys_gettid() -> SysResult {
+    Ok(current_task().unwrap().tid)
 }
 
-pub fn sys_gettid() -> isize {
-    current_task().unwrap().tid as isize
+/// End the calling thread only, unlike `exit`, which ends the process.
+/// The main thread ends the process all the same.
+pub fn sys_thread_exit(exit_code: i32) -> SysResult {
+    PROCESSOR.as_mut().exit_current_thread(exit_code);
+    Ok(0)
 }
 
-/// thread does not exist, return -1
-/// thread has not exited yet, return -2
-/// otherwise, return thread's exit code
-pub fn sys_waittid(tid: usize) -> i32 {
+/// Let the thread `tid` be released as soon as it exits, nobody can wait
+/// for it then. Fails with `ESRCH` if there is no such thread, `EINVAL` if
+/// it is the main thread or already detached.
+pub fn sys_thread_detach(tid: usize) -> SysResult {
+    let process = current_process().unwrap();
+    let process_inner = process.get_mutable_inner();
+    let Some(Some(thread)) = process_inner.threads.get(tid) else {
+        return Err(SysError::ESRCH);
+    };
+    let thread_inner = thread.get_mutable_inner();
+    if tid == 0 || thread_inner.detached {
+        return Err(SysError::EINVAL);
+    }
+    thread_inner.detached = true;
+    process_inner.reap_thread(tid);
+    Ok(0)
+}
+
+/// Wait for the thread `tid` to exit, reap it and return its exit code.
+/// Fails with `ESRCH` if there is no such thread, `EDEADLK` if it is the
+/// caller and `EINVAL` if it is detached.
+pub fn sys_waittid(tid: usize) -> SysResult {
     let current = current_task().unwrap();
     // a thread cannot wait for itself
     if current.tid == tid {
-        return -1;
+        return Err(SysError::EDEADLK);
     }
     let process = current_process().unwrap();
     let process_inner = process.get_mutable_inner();
-    let waited_task = process_inner.threads[tid].as_ref();
-    if let Some(waited_task) = waited_task {
-        if waited_task.get_inner().status != TaskStatus::Zombie {
-            schedule::park_current(&mut (waited_task.get_mutable_inner().waiting_tasks));
-            return -1;
-        }
-        return waited_task.get_inner().exit_code;
-    } else {
-        // waited thread does not exist
-        return -1;
+    let Some(Some(waited_task)) = process_inner.threads.get(tid) else {
+        return Err(SysError::ESRCH);
+    };
+    if waited_task.get_inner().detached {
+        return Err(SysError::EINVAL);
+    }
+    if waited_task.get_inner().status != TaskStatus::Zombie {
+        let wait = Wait::new(WaitTarget::Thread { tid }, UserPtr::new(0));
+        wait::block_on(
+            &current,
+            &mut waited_task.get_mutable_inner().waiting_tasks,
+            wait,
+        );
+        return Ok(0);
     }
+    let exit_code = process_inner.reap_thread(tid).unwrap();
+    Ok(exit_code as usize)
 }
@@ -0,0 +1,59 @@
+use crate::mm::UserPtr;
+use crate::sleep::{Sleep, sleep_current};
+use crate::task::current_task;
+use crate::timer::{TimeSpec, get_time};
+
+use super::{SysError, SysResult};
+
+/// Wall clock time, which counts from boot too without a real time clock
+const CLOCK_REALTIME: usize = 0;
+/// Time since boot
+const CLOCK_MONOTONIC: usize = 1;
+/// The request is an absolute time of the clock
+const TIMER_ABSTIME: usize = 1;
+
+/// Sleep until `deadline` in ticks of `time`. The syscall restarts until
+/// then, it keeps the deadline of its first run. A signal handler ends it
+/// early, see [`Sleep::interrupt`].
+fn sleep_until(deadline: usize, rem: UserPtr<TimeSpec>) -> SysResult {
+    let task = current_task().unwrap();
+    let inner = task.get_mutable_inner();
+    let sleep = *inner.sleep.get_or_insert(Sleep { deadline, rem });
+    if get_time() >= sleep.deadline {
+        inner.sleep = None;
+        return Ok(0);
+    }
+    sleep_current(sleep.deadline);
+    Ok(0)
+}
+
+/// Sleep for `req`. A signal handler interrupts it with `EINTR`, the time
+/// left goes to `rem` then unless it is null.
+pub fn sys_nanosleep(req: UserPtr<TimeSpec>, rem: UserPtr<TimeSpec>) -> SysResult {
+    let Some(ticks) = req.read()?.to_ticks() else {
+        return Err(SysError::EINVAL);
+    };
+    sleep_until(get_time().saturating_add(ticks), rem)
+}
+
+/// Sleep for `req` on `clock`, or until the clock reads `req` with
+/// `TIMER_ABSTIME`, which leaves `rem` alone. Both clocks count from boot.
+/// Like [`sys_nanosleep`] otherwise.
+pub fn sys_clock_nanosleep(
+    clock: usize,
+    flags: usize,
+    req: UserPtr<TimeSpec>,
+    rem: UserPtr<TimeSpec>,
+) -> SysResult {
+    if clock != CLOCK_REALTIME && clock != CLOCK_MONOTONIC {
+        return Err(SysError::EINVAL);
+    }
+    let Some(ticks) = req.read()?.to_ticks() else {
+        return Err(SysError::EINVAL);
+    };
+    if flags & TIMER_ABSTIME != 0 {
+        sleep_until(ticks, UserPtr::new(0))
+    } else {
+        sleep_until(get_time().saturating_add(ticks), rem)
+    }
+}
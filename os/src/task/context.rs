@@ -3,7 +3,6 @@
 pub(crate) struct TaskContext {
     pub regs: CalleeRegs, // 0..12
     pub pc: usize,        // 13
-    pub tls: usize,       // 14
 }
 
 /// Callee-saved registers.
@@ -65,20 +64,9 @@ impl TaskContext {
         TaskContext {
             regs: CalleeRegs::new(),
             pc: 0,
-            tls: 0,
         }
     }
 
-    /// Sets thread-local storage pointer.
-    pub fn set_tls_pointer(&mut self, tls: usize) {
-        self.tls = tls;
-    }
-
-    /// Gets thread-local storage pointer.
-    pub fn tls_pointer(&self) -> usize {
-        self.tls
-    }
-
     pub fn set_instruction_pointer(&mut self, ip: usize) {
         self.pc = ip;
     }
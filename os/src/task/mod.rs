@@ -4,16 +4,18 @@
 
 pub(crate) mod context;
 pub(crate) mod schedule;
-mod stack;
+pub(crate) mod signal;
+pub(crate) mod stack;
 mod taskid;
 mod utils;
+pub(crate) mod wait;
 
 use core::cell::UnsafeCell;
 
 use crate::{
     cpu::{local::CpuLocalCell, processor::PROCESSOR},
     fs::{Stdin, Stdout},
-    sync::mutex::Lock,
+    sync::{condvar::Condvar, mutex::Lock, semaphore::Semaphore},
 };
 use context::TaskContext;
 use lazy_static::lazy_static;
@@ -23,24 +25,32 @@ use riscv::{
         Trap,
         supervisor::{Exception, Interrupt},
     },
-    register::sstatus::set_sum,
+    register::{sstatus::set_sum, stval},
 };
-use schedule::add_task;
+use schedule::{SchedEntity, add_task};
+use signal::{NSIG, SignalAction, SignalFlags, handle_signals};
 use utils::ForceSync;
+use wait::Wait;
 
 core::arch::global_asm!(include_str!("switch.S"));
 
-use crate::fs::File;
 use crate::fs::OpenFlags;
+use crate::fs::{FdFlags, FileDescriptor};
+use crate::mm::address::VirtAddr;
 use crate::mm::memory_set::KERNEL_SPACE;
-use crate::mm::memory_set::MemorySet;
-use crate::syscall;
+use crate::mm::memory_set::{MapPermission, MemorySet, parse_elf};
+use crate::syscall::{self, SysError};
 use crate::task::stack::*;
 use crate::task::taskid::*;
 use crate::trap::context::UserContext;
-use crate::{fs::open_file, timer::set_next_trigger};
+use crate::{
+    fs::{TTY, open_file},
+    sleep::{self, Sleep},
+    timer::{self, set_next_trigger},
+};
 
-use alloc::{collections::vec_deque::VecDeque, vec::Vec};
+use crate::config::PAGE_SIZE;
+use alloc::{collections::vec_deque::VecDeque, string::String, vec::Vec};
 use alloc::{
     sync::{Arc, Weak},
     vec,
@@ -62,7 +72,10 @@ pub struct Task {
 }
 
 impl Task {
-    pub fn new_with_elf(elf_data: &[u8]) -> Arc<Self> {
+    /// A new process running `elf_data`, None if it is not a program
+    /// [`parse_elf`] accepts
+    pub fn new_with_elf(elf_data: &[u8]) -> Option<Arc<Self>> {
+        parse_elf(elf_data)?;
         let kernel_space = KERNEL_SPACE.lock();
         let taskid = taskid_alloc();
         let pt = kernel_space.get_page_table().spawn(taskid.value);
@@ -80,15 +93,20 @@ impl Task {
         }
 
         // memory_set with elf
-        let (memory_set, entry_point) = MemorySet::from_elf(elf_data, pt, cur_pt);
+        let (memory_set, heap_bottom, entry_point) = MemorySet::from_elf(elf_data, pt, cur_pt)?;
         let task = Task::new(
             Arc::new(CpuLocalCell::new(memory_set)),
             taskid,
             0,
             entry_point,
         );
-        task.get_mutable_inner().process = Arc::downgrade(&task);
-        task
+        let inner = task.get_mutable_inner();
+        inner.process = Arc::downgrade(&task);
+        // a new process leads a new group
+        inner.pgid = task.taskid.value;
+        inner.heap_bottom = heap_bottom;
+        inner.program_brk = heap_bottom;
+        Some(task)
     }
 
     pub fn new(
@@ -97,80 +115,38 @@ impl Task {
         tid: usize,
         entry_point: usize,
     ) -> Arc<Self> {
-        extern "C" fn task_kernel_entry() {
-            trace!("task_kernel_entry");
-            loop {
-                let current_task = current_task().unwrap();
-                let inner = current_task.get_mutable_inner();
-                trace!("run task {}", current_task.taskid.value);
-                inner.status = TaskStatus::Running;
-                inner.user_ctx.run();
-                let cause = riscv::register::scause::read().cause();
-                trace!(
-                    "trap from task {} cause: {:?}",
-                    current_task.taskid.value, cause
-                );
-                unsafe {
-                    set_sum();
-                }
-                let syscall_res;
-                match cause.try_into().unwrap() {
-                    Trap::Interrupt(Interrupt::SupervisorTimer) => {
-                        set_next_trigger();
-                        drop(current_task);
-                        schedule::yield_now();
-                        unreachable!()
-                    }
-                    Trap::Exception(Exception::UserEnvCall) => {
-                        inner.user_ctx.sepc += 4;
-                        syscall_res = syscall::handle_syscall(
-                            inner.user_ctx.get_syscall_num(),
-                            inner.user_ctx.get_syscall_args(),
-                        ) as usize;
-                    }
-                    _ => {
-                        info!("Unsupported trap {:?}", cause);
-                        PROCESSOR.as_mut().abort_current();
-                        break;
-                    }
-                }
+        let mut user_ctx = UserContext::default();
 
-                let status = current_task.get_inner().status;
-                if status == TaskStatus::Ready || status == TaskStatus::Waiting {
-                    drop(current_task);
-                    // this function does not return, manually drop all the variables on the stack
-                    schedule::yield_now();
-                    unreachable!()
-                } else if status == TaskStatus::Zombie {
-                    drop(current_task);
-                    break;
-                }
+        let user_stack = UserStack::new(tid);
+        let user_stack_top = user_stack.area.vpn_range.get_end().0 << 12;
 
-                inner.user_ctx.general.a0 = syscall_res;
-            }
+        memory_set.get_mut().push(user_stack.area, None);
+        let (sp, tp) = push_tls(memory_set.get_mut(), user_stack_top);
 
-            // this function does not return, manually drop all the variables on the stack
-            schedule::exit_current();
-            unreachable!()
-        }
+        user_ctx.set_ip(entry_point);
+        user_ctx.set_sp(sp);
+        user_ctx.set_tls(tp);
+
+        Self::with_user_ctx(memory_set, taskid, tid, user_ctx)
+    }
 
+    /// Create a task that enters user mode with `user_ctx`, whose user stack
+    /// is already mapped in `memory_set`.
+    fn with_user_ctx(
+        memory_set: Arc<CpuLocalCell<MemorySet>>,
+        taskid: TaskId,
+        tid: usize,
+        user_ctx: UserContext,
+    ) -> Arc<Self> {
         let kernel_stack = KernelStack::new(taskid.value);
         let kernel_stack_top = kernel_stack.area.vpn_range.get_end().0 << 12;
-        let mut user_ctx = UserContext::default();
         let mut task_ctx = TaskContext::default();
 
-        let user_stack = UserStack::new(tid);
-        let user_stack_top = user_stack.area.vpn_range.get_end().0 << 12;
-
         memory_set.get_mut().push(kernel_stack.area, None);
-        memory_set.get_mut().push(user_stack.area, None);
 
         task_ctx.set_instruction_pointer(task_kernel_entry as usize);
         task_ctx.set_stack_pointer(kernel_stack_top);
 
-        user_ctx.set_ip(entry_point);
-        user_ctx.set_sp(user_stack_top);
-
         debug!("create task id {}", taskid.value);
 
         let task = Arc::new(Self {
@@ -186,21 +162,160 @@ impl Task {
                 exit_code: 0,
                 fd_table: vec![
                     // 0 -> stdin
-                    Some(Arc::new(Stdin)),
+                    Some(FileDescriptor::new(Arc::new(Stdin), FdFlags::empty())),
                     // 1 -> stdout
-                    Some(Arc::new(Stdout)),
+                    Some(FileDescriptor::new(Arc::new(Stdout), FdFlags::empty())),
                     // 2 -> stdout as stderr
-                    Some(Arc::new(Stdout)),
+                    Some(FileDescriptor::new(Arc::new(Stdout), FdFlags::empty())),
                 ],
                 mutex_list: [].to_vec(),
+                semaphore_list: Vec::new(),
+                condvar_list: Vec::new(),
+                heap_bottom: 0,
+                program_brk: 0,
+                cwd: String::from("/"),
+                pgid: 0,
                 threads: vec![None],
                 waiting_tasks: VecDeque::new(),
+                child_waiters: VecDeque::new(),
                 status: TaskStatus::Ready,
+                pending_signals: SignalFlags::empty(),
+                blocked_signals: SignalFlags::empty(),
+                signal_actions: [SignalAction::default(); NSIG],
+                stop_signal: None,
+                sleep: None,
+                wait: None,
+                detached: false,
+                sched: SchedEntity::default(),
             })),
         });
         task
     }
 
+    /// Duplicate the process of a main thread. The child shares the parent's
+    /// pages copy-on-write, inherits its open files and signal handlers and
+    /// returns 0 from the syscall that forked it.
+    pub fn fork(self: &Arc<Self>) -> Arc<Self> {
+        let taskid = taskid_alloc();
+        let pt = KERNEL_SPACE.lock().get_page_table().spawn(taskid.value);
+        let inner = self.get_inner();
+        let memory_set = inner.memory_set.get_mut().fork(pt);
+        let mut user_ctx = inner.user_ctx.clone();
+        user_ctx.set_syscall_ret(0);
+
+        let task = Task::with_user_ctx(
+            Arc::new(CpuLocalCell::new(memory_set)),
+            taskid,
+            self.tid,
+            user_ctx,
+        );
+        let child_inner = task.get_mutable_inner();
+        child_inner.process = Arc::downgrade(&task);
+        child_inner.parent = Some(Arc::downgrade(self));
+        child_inner.fd_table = inner.fd_table.clone();
+        child_inner.mutex_list = inner.mutex_list.clone();
+        child_inner.semaphore_list = inner.semaphore_list.clone();
+        child_inner.condvar_list = inner.condvar_list.clone();
+        child_inner.heap_bottom = inner.heap_bottom;
+        child_inner.program_brk = inner.program_brk;
+        child_inner.cwd = inner.cwd.clone();
+        child_inner.pgid = inner.pgid;
+        child_inner.blocked_signals = inner.blocked_signals;
+        child_inner.signal_actions = inner.signal_actions;
+        child_inner.sched.nice = inner.sched.nice;
+        task
+    }
+
+    /// Replace the image of a process with `elf_data`, keeping its pid,
+    /// relatives and open files except those marked close-on-exec. Caught
+    /// signals get their default action back. The program starts with
+    /// `args` and `envs` on a fresh user stack. Returns argc, which the
+    /// syscall leaves in a0. Fails with `ENOEXEC` if `elf_data` is not a
+    /// program and with `E2BIG` if the arguments do not fit, the process
+    /// is left as it was then.
+    pub fn exec(
+        &self,
+        elf_data: &[u8],
+        args: &[String],
+        envs: &[String],
+    ) -> Result<usize, SysError> {
+        let elf = parse_elf(elf_data).ok_or(SysError::ENOEXEC)?;
+        let inner = self.get_mutable_inner();
+        let memory_set = inner.memory_set.get_mut();
+        let pt = KERNEL_SPACE
+            .lock()
+            .get_page_table()
+            .spawn(self.taskid.value);
+        let (mut new_set, heap_bottom, entry_point) =
+            MemorySet::from_elf(elf_data, pt, memory_set.get_page_table())
+                .ok_or(SysError::ENOEXEC)?;
+
+        let user_stack = UserStack::new(self.tid);
+        let user_stack_top = user_stack.area.vpn_range.get_end().0 << 12;
+        new_set.push(user_stack.area, None);
+        let (sp, tp) = push_tls(&mut new_set, user_stack_top);
+
+        let ph_offset = elf.header.pt2.ph_offset();
+        let mut auxv = vec![
+            (AT_PAGESZ, PAGE_SIZE),
+            (AT_PHENT, elf.header.pt2.ph_entry_size() as usize),
+            (AT_PHNUM, elf.header.pt2.ph_count() as usize),
+            (AT_ENTRY, entry_point),
+        ];
+        // the program headers are only visible if a segment loads them
+        if let Some(ph) = elf.program_iter().find(|ph| {
+            ph.get_type() == Ok(xmas_elf::program::Type::Load)
+                && (ph.offset()..ph.offset() + ph.file_size()).contains(&ph_offset)
+        }) {
+            auxv.push((
+                AT_PHDR,
+                (ph.virtual_addr() + ph_offset - ph.offset()) as usize,
+            ));
+        }
+        let (sp, argv, envp) =
+            push_args(&mut new_set, sp, args, envs, &auxv).ok_or(SysError::E2BIG)?;
+
+        // nothing fails from here on, the old image goes
+        // keep the kernel stack we are running on
+        new_set.take_kernel_areas(memory_set);
+        new_set.activate();
+        *memory_set = new_set;
+        inner.close_on_exec();
+        inner.reset_signal_actions();
+
+        let mut user_ctx = UserContext::default();
+        user_ctx.set_ip(entry_point);
+        user_ctx.set_sp(sp);
+        user_ctx.set_tls(tp);
+        user_ctx.general.a1 = argv;
+        user_ctx.general.a2 = envp;
+        inner.user_ctx = user_ctx;
+        inner.mutex_list.clear();
+        inner.semaphore_list.clear();
+        inner.condvar_list.clear();
+        inner.heap_bottom = heap_bottom;
+        inner.program_brk = heap_bottom;
+        Ok(args.len())
+    }
+
+    /// Move the program break of a process to `new_brk`, the heap area
+    /// follows it page by page. Returns false if the break would go below
+    /// the heap bottom or into another area.
+    pub fn set_program_brk(&self, new_brk: usize) -> bool {
+        let inner = self.get_mutable_inner();
+        if new_brk < inner.heap_bottom {
+            return false;
+        }
+        let resized = inner.memory_set.get_mut().resize_area(
+            VirtAddr::from(inner.heap_bottom).floor(),
+            VirtAddr::from(new_brk).ceil(),
+        );
+        if resized {
+            inner.program_brk = new_brk;
+        }
+        resized
+    }
+
     pub fn new_thread(process: &Arc<Task>, entry_point: usize, arg: usize) -> Arc<Self> {
         let threadid = process.get_mutable_inner().alloc_thread();
         let taskid = taskid_alloc();
@@ -213,9 +328,68 @@ impl Task {
         process.get_mutable_inner().threads[threadid] = Some(thread.clone());
         thread.get_mutable_inner().user_ctx.general.a0 = arg;
         thread.get_mutable_inner().process = Arc::downgrade(process);
+        thread.get_mutable_inner().sched.nice = process.get_inner().sched.nice;
         thread
     }
 
+    /// End the process of a main thread with `exit_code`. Its other threads
+    /// end with it, and its files and user memory are released right away,
+    /// the zombie only keeps what `waitpid` needs. Its children, zombies
+    /// too, go to [`INITPROC`], and the parent gets SIGCHLD.
+    pub fn exit_process(self: &Arc<Self>, exit_code: i32) {
+        let inner = self.get_mutable_inner();
+        if inner.status == TaskStatus::Zombie {
+            return;
+        }
+        inner.exit_code = exit_code;
+        inner.status = TaskStatus::Zombie;
+        // a thread that is parked never runs again, a ready one exits when
+        // it is picked
+        for thread in inner.threads.iter().skip(1).flatten() {
+            let thread_inner = thread.get_mutable_inner();
+            if thread_inner.status != TaskStatus::Zombie {
+                thread_inner.exit_code = exit_code;
+                thread_inner.status = TaskStatus::Zombie;
+                thread_inner.fd_table.clear();
+            }
+        }
+        // close its files now, the other end of a pipe must not wait for
+        // the parent to reap it
+        inner.fd_table.clear();
+        inner.mutex_list.clear();
+        inner.semaphore_list.clear();
+        inner.condvar_list.clear();
+        inner.memory_set.get_mut().release_user_areas();
+        self.notify_joiners();
+
+        if !Arc::ptr_eq(self, &INITPROC) {
+            for child in core::mem::take(&mut inner.children) {
+                child.get_mutable_inner().parent = Some(Arc::downgrade(&INITPROC));
+                INITPROC.get_mutable_inner().children.push(child.clone());
+                // initproc may be waiting for any child
+                child.notify_parent();
+            }
+        }
+        self.notify_parent();
+        if let Some(parent) = inner.parent.as_ref().and_then(Weak::upgrade) {
+            parent.send_signal(SignalFlags::SIGCHLD);
+        }
+    }
+
+    /// End a thread other than the main one with `exit_code`, which is
+    /// kept for `waittid` unless the thread is detached. Its stacks are
+    /// released once it is reaped, it still runs on its kernel stack. A
+    /// detached thread is reaped by the next task on the processor.
+    pub fn exit_thread(&self, exit_code: i32) {
+        let inner = self.get_mutable_inner();
+        inner.exit_code = exit_code;
+        inner.status = TaskStatus::Zombie;
+        // close its files now, the other end of a pipe must not wait for
+        // the parent to reap it
+        inner.fd_table.clear();
+        self.notify_joiners();
+    }
+
     fn as_mut_ptr(&self) -> *mut TaskInner {
         self.inner.get() as *mut TaskInner
     }
@@ -238,11 +412,41 @@ pub struct TaskInner {
     pub parent: Option<Weak<Task>>,
     pub children: Vec<Arc<Task>>,
     pub exit_code: i32,
-    pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
+    pub fd_table: Vec<Option<FileDescriptor>>,
     pub mutex_list: Vec<Option<Arc<dyn Lock>>>,
+    pub semaphore_list: Vec<Option<Arc<Semaphore>>>,
+    pub condvar_list: Vec<Option<Arc<Condvar>>>,
+    /// Start of the heap grown by brk and sbrk
+    pub heap_bottom: usize,
+    pub program_brk: usize,
+    /// Absolute path of the current directory
+    pub cwd: String,
+    /// Process group, which job control signals all at once
+    pub pgid: usize,
     pub threads: Vec<Option<Arc<Task>>>,
     pub waiting_tasks: VecDeque<Arc<Task>>,
+    /// Tasks of the process parked in `waitpid` until a child exits or
+    /// stops
+    pub child_waiters: VecDeque<Arc<Task>>,
     pub status: TaskStatus,
+    /// Signals sent but not handled yet
+    pub pending_signals: SignalFlags,
+    /// Signals that stay pending until they are unblocked
+    pub blocked_signals: SignalFlags,
+    /// Indexed by signal number. Those of the main thread are the actions of
+    /// the whole process, the other threads go through `process`.
+    pub signal_actions: [SignalAction; NSIG],
+    /// The signal that stopped the task, which stays stopped until SIGCONT
+    pub stop_signal: Option<SignalFlags>,
+    /// The sleep syscall the task is in
+    pub sleep: Option<Sleep>,
+    /// The `waitpid` or `waittid` the task is blocked in
+    pub wait: Option<Wait>,
+    /// A thread that is released once it exits instead of waiting for
+    /// `waittid`
+    pub detached: bool,
+    /// What the scheduler keeps about the task
+    pub sched: SchedEntity,
 }
 
 impl TaskInner {
@@ -255,6 +459,34 @@ impl TaskInner {
         }
     }
 
+    /// Close the file descriptors marked close-on-exec
+    pub fn close_on_exec(&mut self) {
+        for fd in self.fd_table.iter_mut() {
+            if fd
+                .as_ref()
+                .is_some_and(|fd| fd.flags.contains(FdFlags::CLOEXEC))
+            {
+                *fd = None;
+            }
+        }
+    }
+
+    /// Release the exited thread `tid` of this process, its stacks and its
+    /// slot. Returns its exit code, None if there is no such thread or it
+    /// has not exited.
+    pub fn reap_thread(&mut self, tid: usize) -> Option<i32> {
+        let thread = self.threads.get(tid)?.as_ref()?;
+        if tid == 0 || thread.get_inner().status != TaskStatus::Zombie {
+            return None;
+        }
+        let memory_set = self.memory_set.get_mut();
+        memory_set.remove_area(VirtAddr::from(UserStack::bottom(tid)).floor());
+        memory_set.remove_area(VirtAddr::from(KernelStack::bottom(thread.taskid.value)).floor());
+        let exit_code = thread.get_inner().exit_code;
+        self.threads[tid] = None;
+        Some(exit_code)
+    }
+
     pub fn alloc_thread(&mut self) -> usize {
         if let Some(tid) = (1..self.threads.len()).find(|tid| self.threads[*tid].is_none()) {
             tid
@@ -265,13 +497,152 @@ impl TaskInner {
     }
 
     pub fn alloc_mutex(&mut self) -> usize {
-        if let Some(tid) = (0..self.mutex_list.len()).find(|tid| self.mutex_list[*tid].is_none()) {
-            tid
-        } else {
-            self.mutex_list.push(None);
-            self.mutex_list.len() - 1
+        alloc_id(&mut self.mutex_list)
+    }
+
+    pub fn alloc_semaphore(&mut self) -> usize {
+        alloc_id(&mut self.semaphore_list)
+    }
+
+    pub fn alloc_condvar(&mut self) -> usize {
+        alloc_id(&mut self.condvar_list)
+    }
+}
+
+/// The first free slot of a per-process table, which grows if it is full
+fn alloc_id<T>(list: &mut Vec<Option<T>>) -> usize {
+    if let Some(id) = (0..list.len()).find(|id| list[*id].is_none()) {
+        id
+    } else {
+        list.push(None);
+        list.len() - 1
+    }
+}
+
+extern "C" fn task_kernel_entry() {
+    trace!("task_kernel_entry");
+    PROCESSOR.as_mut().reap_exited();
+    loop {
+        let current_task = current_task().unwrap();
+        let inner = current_task.get_mutable_inner();
+        if inner.status == TaskStatus::Zombie {
+            // its process exited while it was ready
+            drop(current_task);
+            break;
         }
+        wait::finish_wait(inner);
+        handle_signals(&current_task);
+        match inner.status {
+            TaskStatus::Waiting => {
+                // stopped by a signal
+                drop(current_task);
+                schedule::yield_now();
+                unreachable!()
+            }
+            TaskStatus::Zombie => {
+                drop(current_task);
+                break;
+            }
+            _ => {}
+        }
+        trace!("run task {}", current_task.taskid.value);
+        inner.status = TaskStatus::Running;
+        inner.user_ctx.run();
+        let cause = riscv::register::scause::read().cause();
+        trace!(
+            "trap from task {} cause: {:?}",
+            current_task.taskid.value, cause
+        );
+        unsafe {
+            set_sum();
+        }
+        let syscall_res;
+        match cause.try_into().unwrap() {
+            Trap::Interrupt(Interrupt::SupervisorTimer) => {
+                sleep::wake_expired();
+                // a sleeper may be due before the time slice is over
+                let slice_over = timer::next_slice();
+                set_next_trigger();
+                TTY.poll();
+                if !slice_over || !schedule::tick(&current_task) {
+                    continue;
+                }
+                drop(current_task);
+                schedule::yield_now();
+                unreachable!()
+            }
+            Trap::Exception(Exception::UserEnvCall) => {
+                inner.user_ctx.sepc += 4;
+                syscall_res = syscall::handle_syscall(
+                    inner.user_ctx.get_syscall_num(),
+                    inner.user_ctx.get_syscall_args(),
+                ) as usize;
+            }
+            Trap::Exception(
+                exception @ (Exception::LoadPageFault
+                | Exception::StorePageFault
+                | Exception::InstructionPageFault),
+            ) => {
+                let addr = stval::read();
+                let access = match exception {
+                    Exception::LoadPageFault => MapPermission::R,
+                    Exception::StorePageFault => MapPermission::W,
+                    _ => MapPermission::X,
+                };
+                if inner
+                    .memory_set
+                    .get_mut()
+                    .handle_page_fault(addr.into(), access, true)
+                {
+                    continue;
+                }
+                info!(
+                    "Task {} segmentation fault at {:#x}, pc {:#x}",
+                    current_task.taskid.value, addr, inner.user_ctx.sepc
+                );
+                inner.force_signal(SignalFlags::SIGSEGV);
+                continue;
+            }
+            Trap::Exception(exception) => {
+                info!(
+                    "Task {} {:?} at pc {:#x}",
+                    current_task.taskid.value, exception, inner.user_ctx.sepc
+                );
+                let signal = match exception {
+                    Exception::IllegalInstruction => SignalFlags::SIGILL,
+                    Exception::Breakpoint => SignalFlags::SIGTRAP,
+                    Exception::InstructionMisaligned
+                    | Exception::LoadMisaligned
+                    | Exception::StoreMisaligned => SignalFlags::SIGBUS,
+                    _ => SignalFlags::SIGSEGV,
+                };
+                inner.force_signal(signal);
+                continue;
+            }
+            _ => {
+                info!("Unsupported trap {:?}", cause);
+                PROCESSOR.as_mut().abort_current();
+                break;
+            }
+        }
+
+        let status = current_task.get_inner().status;
+        if status == TaskStatus::Ready || status == TaskStatus::Waiting {
+            drop(current_task);
+            // this function does not return, manually drop all the variables on the stack
+            schedule::yield_now();
+            unreachable!()
+        } else if status == TaskStatus::Zombie {
+            drop(current_task);
+            break;
+        }
+
+        inner.user_ctx.general.a0 = syscall_res;
     }
+
+    // this function does not return, manually drop all the variables on the stack
+    schedule::exit_current();
+    unreachable!()
 }
 
 unsafe extern "C" {
@@ -283,7 +654,7 @@ lazy_static! {
     pub static ref INITPROC: Arc<Task> = {
         let inode = open_file("initproc", OpenFlags::RDONLY).unwrap();
         let v = inode.read_all();
-        Task::new_with_elf(v.as_slice())
+        Task::new_with_elf(v.as_slice()).unwrap()
     };
 }
 
@@ -291,6 +662,33 @@ pub fn add_initproc() {
     add_task(INITPROC.clone());
 }
 
+/// Every process, that is [`INITPROC`] and its descendants
+pub fn processes() -> Vec<Arc<Task>> {
+    let mut processes = vec![INITPROC.clone()];
+    let mut i = 0;
+    while i < processes.len() {
+        let children = processes[i].get_inner().children.clone();
+        processes.extend(children);
+        i += 1;
+    }
+    processes
+}
+
+/// Find the process `pid`
+pub fn find_process(pid: usize) -> Option<Arc<Task>> {
+    processes()
+        .into_iter()
+        .find(|process| process.taskid.value == pid)
+}
+
+/// The processes in the process group `pgid`
+pub fn find_group(pgid: usize) -> Vec<Arc<Task>> {
+    processes()
+        .into_iter()
+        .filter(|process| process.get_inner().pgid == pgid)
+        .collect()
+}
+
 pub fn current_task() -> Option<Arc<Task>> {
     PROCESSOR.as_mut().current()
 }
@@ -0,0 +1,88 @@
+//! Multi-level feedback queue
+//!
+//! Tasks start in the first queue and run round robin within a queue, a
+//! queue only runs when those above it are empty. A task that uses up its
+//! time slice moves down one queue, the lower queues have longer slices. A
+//! task that blocks before stays where it is with a fresh slice. Every
+//! [`BOOST_PERIOD`] ticks all tasks move back to the first queue, so that
+//! none starves.
+use alloc::{collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+
+use super::{SchedPolicy, Scheduler};
+use crate::task::Task;
+
+/// Time slice of each queue in ticks
+const TIME_SLICES: [usize; 3] = [1, 2, 4];
+const BOOST_PERIOD: usize = 50;
+
+pub struct Mlfq {
+    queues: [VecDeque<Arc<Task>>; TIME_SLICES.len()],
+    /// Ticks since the last boost
+    ticks: usize,
+}
+
+impl Mlfq {
+    pub fn new() -> Self {
+        Self {
+            queues: Default::default(),
+            ticks: 0,
+        }
+    }
+
+    /// Move every task back to the first queue
+    fn boost(&mut self, current: &Task) {
+        for level in 1..self.queues.len() {
+            let tasks = core::mem::take(&mut self.queues[level]);
+            self.queues[0].extend(tasks);
+        }
+        for task in self.queues[0].iter().map(|task| &**task).chain([current]) {
+            let sched = &mut task.get_mutable_inner().sched;
+            sched.level = 0;
+            sched.ticks = 0;
+        }
+    }
+}
+
+impl Scheduler for Mlfq {
+    fn policy(&self) -> SchedPolicy {
+        SchedPolicy::Mlfq
+    }
+
+    fn enqueue(&mut self, task: Arc<Task>) {
+        // it may have been scheduled by another policy before
+        let level = task.get_inner().sched.level.min(self.queues.len() - 1);
+        self.queues[level].push_back(task);
+    }
+
+    fn pick_next(&mut self) -> Option<Arc<Task>> {
+        self.queues.iter_mut().find_map(VecDeque::pop_front)
+    }
+
+    fn on_tick(&mut self, task: &Task) -> bool {
+        self.ticks += 1;
+        if self.ticks >= BOOST_PERIOD {
+            self.ticks = 0;
+            self.boost(task);
+            return true;
+        }
+        let sched = &mut task.get_mutable_inner().sched;
+        sched.ticks += 1;
+        if sched.ticks < TIME_SLICES[sched.level] {
+            return false;
+        }
+        sched.ticks = 0;
+        sched.level = (sched.level + 1).min(self.queues.len() - 1);
+        true
+    }
+
+    fn on_block(&mut self, task: &Task) {
+        task.get_mutable_inner().sched.ticks = 0;
+    }
+
+    fn drain(&mut self) -> Vec<Arc<Task>> {
+        self.queues
+            .iter_mut()
+            .flat_map(|queue| queue.drain(..))
+            .collect()
+    }
+}
@@ -1,107 +1,234 @@
+//! Scheduling of the tasks ready to run
+//!
+//! The ready tasks belong to a [`Scheduler`], which decides the order they
+//! run in. The running task is not among them, it goes back when it yields
+//! or is preempted. The policy is picked at build time with the
+//! `sched-stride` or `sched-mlfq` feature, round robin otherwise, and
+//! initproc may switch it at run time with [`set_policy`].
+mod mlfq;
+mod round_robin;
+mod stride;
+
 use core::arch::asm;
 
-use alloc::{collections::vec_deque::VecDeque, sync::Arc};
+use alloc::{boxed::Box, collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
 use lazy_static::lazy_static;
 use log::debug;
 use spin::Mutex;
 
 use crate::{
     cpu::processor,
+    fs::TTY,
+    sleep,
     task::{Task, TaskStatus, current_task},
+    timer,
 };
+use mlfq::Mlfq;
+use round_robin::RoundRobin;
+use stride::Stride;
 
-enum ReschedAction {
-    /// Keep running current task and do nothing.
-    DoNothing,
-    /// Loop until finding a task to swap out the current.
-    Retry,
-    /// Switch to target task.
-    SwitchTo(Arc<Task>),
+/// A scheduling policy, which owns the tasks ready to run
+pub trait Scheduler: Send {
+    fn policy(&self) -> SchedPolicy;
+    /// Make `task` ready to run
+    fn enqueue(&mut self, task: Arc<Task>);
+    /// Take the task to run next off the ready queue
+    fn pick_next(&mut self) -> Option<Arc<Task>>;
+    /// A timer tick came while `task` ran. Returns whether it is to make
+    /// room for another task.
+    fn on_tick(&mut self, task: &Task) -> bool;
+    /// `task` blocked before its time slice was over
+    fn on_block(&mut self, _task: &Task) {}
+    /// Hand over all the ready tasks, in the order they would run
+    fn drain(&mut self) -> Vec<Arc<Task>>;
 }
 
-pub struct TaskManager {
-    ready_queue: VecDeque<Arc<Task>>,
+/// The policies a [`Scheduler`] may follow, the values are those of
+/// `sched_setscheduler`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SchedPolicy {
+    /// Round robin, the tasks run one tick each in the order they got ready
+    RoundRobin = 0,
+    /// Stride scheduling, nicer tasks get fewer ticks
+    Stride = 1,
+    /// Multi-level feedback queue, tasks that use up their time slices sink
+    Mlfq = 2,
 }
 
-impl TaskManager {
-    pub fn new() -> Self {
-        Self {
-            ready_queue: VecDeque::new(),
+impl SchedPolicy {
+    pub fn from_raw(policy: usize) -> Option<Self> {
+        match policy {
+            0 => Some(Self::RoundRobin),
+            1 => Some(Self::Stride),
+            2 => Some(Self::Mlfq),
+            _ => None,
         }
     }
-    pub fn add_task(&mut self, task: Arc<Task>) {
-        self.ready_queue.push_back(task);
+
+    fn new_scheduler(self) -> Box<dyn Scheduler> {
+        match self {
+            Self::RoundRobin => Box::new(RoundRobin::new()),
+            Self::Stride => Box::new(Stride::new()),
+            Self::Mlfq => Box::new(Mlfq::new()),
+        }
     }
 }
 
+/// The policy the kernel boots with
+const DEFAULT_POLICY: SchedPolicy = if cfg!(feature = "sched-mlfq") {
+    SchedPolicy::Mlfq
+} else if cfg!(feature = "sched-stride") {
+    SchedPolicy::Stride
+} else {
+    SchedPolicy::RoundRobin
+};
+
+/// Niceness of a task, from the most favoured to the least
+pub const NICE_MIN: isize = -20;
+pub const NICE_MAX: isize = 19;
+
+/// What the schedulers keep about a task
+#[derive(Clone, Copy, Default)]
+pub struct SchedEntity {
+    /// From [`NICE_MIN`] to [`NICE_MAX`], inherited by children and threads
+    pub nice: isize,
+    /// Stride: virtual time the task has been given
+    pub pass: u64,
+    /// MLFQ: the queue the task is in, 0 is the first to run
+    pub level: usize,
+    /// MLFQ: ticks the task has used of its time slice
+    pub ticks: usize,
+}
+
 lazy_static! {
-    pub static ref TASK_MANAGER: Mutex<TaskManager> = Mutex::new(TaskManager::new());
+    pub static ref SCHEDULER: Mutex<Box<dyn Scheduler>> =
+        Mutex::new(DEFAULT_POLICY.new_scheduler());
 }
 
 pub fn add_task(task: Arc<Task>) {
-    TASK_MANAGER.lock().add_task(task);
+    SCHEDULER.lock().enqueue(task);
+}
+
+/// The policy the ready tasks are scheduled by
+pub fn policy() -> SchedPolicy {
+    SCHEDULER.lock().policy()
 }
 
+/// Switch to `policy`, the ready tasks move over to it. Returns the policy
+/// before.
+pub fn set_policy(policy: SchedPolicy) -> SchedPolicy {
+    let mut scheduler = SCHEDULER.lock();
+    let old = scheduler.policy();
+    if policy != old {
+        // the virtual time of the new scheduler starts over, passes left
+        // from an earlier one would keep the ready tasks and the current
+        // one from running for long
+        let mut new = policy.new_scheduler();
+        for task in scheduler.drain() {
+            task.get_mutable_inner().sched.pass = 0;
+            new.enqueue(task);
+        }
+        if let Some(current) = current_task() {
+            current.get_mutable_inner().sched.pass = 0;
+        }
+        *scheduler = new;
+    }
+    old
+}
+
+/// Account a timer tick to the current task. Returns whether it is to
+/// yield to another task.
+pub fn tick(current: &Task) -> bool {
+    SCHEDULER.lock().on_tick(current)
+}
+
+/// Make a parked task runnable again. Returns false, doing nothing, if it
+/// has already been woken up or has exited.
+pub fn wake(task: Arc<Task>) -> bool {
+    let inner = task.get_mutable_inner();
+    if inner.status != TaskStatus::Waiting {
+        return false;
+    }
+    inner.status = TaskStatus::Ready;
+    add_task(task);
+    true
+}
+
+/// Take the current task off the CPU until [`wake`] makes it ready again,
+/// the syscall it is in restarts then.
 pub fn park_current(waiting_queue: &mut VecDeque<Arc<Task>>) {
-    reschedule(|rq| {
-        let current = rq.pop_front().unwrap();
-        current.get_mutable_inner().user_ctx.sepc -= 4;
-        current.get_mutable_inner().status = TaskStatus::Waiting;
-        waiting_queue.push_back(current);
-        ReschedAction::DoNothing
-    });
+    park_current_with(|task| waiting_queue.push_back(task));
+}
+
+/// Like [`park_current`], but the syscall does not restart, whoever wakes
+/// the task leaves the result for it
+pub fn block_current(waiting_queue: &mut VecDeque<Arc<Task>>) {
+    waiting_queue.push_back(block());
+}
+
+/// Like [`park_current`], but `park` decides where the task waits
+pub fn park_current_with(park: impl FnOnce(Arc<Task>)) {
+    let current = block();
+    current.get_mutable_inner().user_ctx.sepc -= 4;
+    park(current);
+}
+
+/// Yield and run the syscall the current task is in again, the task gets
+/// to act on its signals before.
+pub fn restart_current() {
+    let current = current_task().unwrap();
+    let inner = current.get_mutable_inner();
+    inner.user_ctx.sepc -= 4;
+    inner.status = TaskStatus::Ready;
+}
+
+/// Take the current task off the CPU without a syscall to restart, nothing
+/// but a signal wakes it up.
+pub fn suspend_current() {
+    block();
+}
+
+/// Mark the current task waiting, it runs on until it reaches the
+/// scheduler
+fn block() -> Arc<Task> {
+    let current = current_task().unwrap();
+    current.get_mutable_inner().status = TaskStatus::Waiting;
+    SCHEDULER.lock().on_block(&current);
+    current
 }
 
 pub fn exit_current() {
-    reschedule(|rq| {
-        rq.pop_front();
-        if let Some(next) = rq.front() {
-            ReschedAction::SwitchTo(next.clone())
-        } else {
-            ReschedAction::Retry
-        }
-    });
+    run_next();
 }
 
 pub fn yield_now() {
     debug!("yield");
-    reschedule(|rq| {
-        if let Some(current_task) = current_task() {
-            if current_task.get_inner().status != TaskStatus::Waiting {
-                rq.pop_front();
-                rq.push_back(current_task);
-            }
+    if let Some(current) = current_task() {
+        let inner = current.get_mutable_inner();
+        if matches!(inner.status, TaskStatus::Ready | TaskStatus::Running) {
+            inner.status = TaskStatus::Ready;
+            add_task(current);
         }
-        if let Some(next_task) = rq.front() {
-            ReschedAction::SwitchTo(next_task.clone())
-        } else {
-            ReschedAction::Retry
-        }
-    })
+    }
+    run_next();
 }
 
-fn reschedule<F>(mut get_action: F)
-where
-    F: FnMut(&mut VecDeque<Arc<Task>>) -> ReschedAction,
-{
+/// Switch to the task the scheduler picks, waiting for one if none is
+/// ready
+fn run_next() {
     let next_task = loop {
-        let action = get_action(&mut TASK_MANAGER.lock().ready_queue);
-        match action {
-            ReschedAction::DoNothing => {
-                debug!("do nothing");
-                return;
-            }
-            ReschedAction::Retry => {
-                debug!("retry");
-                unsafe {
-                    asm!("wfi");
-                }
-                continue;
-            }
-            ReschedAction::SwitchTo(next_task) => {
-                break next_task;
-            }
-        };
+        if let Some(next_task) = SCHEDULER.lock().pick_next() {
+            break next_task;
+        }
+        debug!("idle");
+        // a reader may be waiting for the console, a sleeper for the time
+        TTY.poll();
+        sleep::wake_expired();
+        timer::next_slice();
+        timer::set_next_trigger();
+        unsafe {
+            asm!("wfi");
+        }
     };
 
     processor::switch_to_task(next_task);
@@ -0,0 +1,40 @@
+//! Round robin, the way the kernel has always scheduled
+use alloc::{collections::vec_deque::VecDeque, sync::Arc, vec::Vec};
+
+use super::{SchedPolicy, Scheduler};
+use crate::task::Task;
+
+/// Tasks run in the order they got ready, for a tick each
+pub struct RoundRobin {
+    ready_queue: VecDeque<Arc<Task>>,
+}
+
+impl RoundRobin {
+    pub fn new() -> Self {
+        Self {
+            ready_queue: VecDeque::new(),
+        }
+    }
+}
+
+impl Scheduler for RoundRobin {
+    fn policy(&self) -> SchedPolicy {
+        SchedPolicy::RoundRobin
+    }
+
+    fn enqueue(&mut self, task: Arc<Task>) {
+        self.ready_queue.push_back(task);
+    }
+
+    fn pick_next(&mut self) -> Option<Arc<Task>> {
+        self.ready_queue.pop_front()
+    }
+
+    fn on_tick(&mut self, _task: &Task) -> bool {
+        true
+    }
+
+    fn drain(&mut self) -> Vec<Arc<Task>> {
+        self.ready_queue.drain(..).collect()
+    }
+}
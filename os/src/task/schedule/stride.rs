@@ -0,0 +1,72 @@
+//! Stride scheduling
+//!
+//! Every task has a pass, the virtual time it has been given, and the task
+//! with the lowest pass runs next. Each turn moves its pass on by a stride
+//! inversely proportional to its weight, so a task gets turns in proportion
+//! to its weight. The weight follows from the niceness.
+use alloc::{sync::Arc, vec::Vec};
+
+use super::{NICE_MAX, SchedPolicy, Scheduler};
+use crate::task::Task;
+
+/// The stride of a task of weight 1
+const BIG_STRIDE: u64 = 1 << 20;
+
+pub struct Stride {
+    ready_tasks: Vec<Arc<Task>>,
+    /// Pass of the task picked last, where the virtual time is now
+    pass: u64,
+}
+
+impl Stride {
+    pub fn new() -> Self {
+        Self {
+            ready_tasks: Vec::new(),
+            pass: 0,
+        }
+    }
+}
+
+/// How far a turn moves a task of niceness `nice` on, from 40 times as far
+/// for the nicest task as for the least nice one
+fn stride(nice: isize) -> u64 {
+    BIG_STRIDE / (NICE_MAX + 1 - nice) as u64
+}
+
+impl Scheduler for Stride {
+    fn policy(&self) -> SchedPolicy {
+        SchedPolicy::Stride
+    }
+
+    fn enqueue(&mut self, task: Arc<Task>) {
+        // a task that is new or has slept long may not catch up on the time
+        // it missed all at once
+        let sched = &mut task.get_mutable_inner().sched;
+        sched.pass = sched.pass.max(self.pass);
+        self.ready_tasks.push(task);
+    }
+
+    fn pick_next(&mut self) -> Option<Arc<Task>> {
+        // the first of the lowest, tasks of the same pass take turns
+        let (index, _) = self
+            .ready_tasks
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, task)| task.get_inner().sched.pass)?;
+        let task = self.ready_tasks.remove(index);
+        let sched = &mut task.get_mutable_inner().sched;
+        self.pass = sched.pass;
+        sched.pass += stride(sched.nice);
+        Some(task)
+    }
+
+    fn on_tick(&mut self, _task: &Task) -> bool {
+        true
+    }
+
+    fn drain(&mut self) -> Vec<Arc<Task>> {
+        let mut tasks: Vec<_> = self.ready_tasks.drain(..).collect();
+        tasks.sort_by_key(|task| task.get_inner().sched.pass);
+        tasks
+    }
+}
@@ -0,0 +1,289 @@
+//! POSIX-style signals
+//!
+//! A signal sent to a task stays pending until the task is on its way back
+//! to user mode, [`handle_signals`] then runs the default action or enters
+//! the handler on a [`SignalFrame`] pushed onto the user stack. The handler
+//! returns into the restorer of its [`SignalAction`], which calls
+//! `sigreturn` to resume from the frame.
+
+use alloc::sync::Arc;
+use bitflags::bitflags;
+use log::info;
+
+use super::{INITPROC, Task, TaskInner, find_group, schedule};
+use crate::cpu::processor::PROCESSOR;
+use crate::mm::{Pod, UserPtr};
+use crate::trap::context::UserContext;
+
+/// Signals are numbered from 1 to `NSIG - 1`
+pub const NSIG: usize = 32;
+
+/// Handler of the default action
+pub const SIG_DFL: usize = 0;
+/// Handler that ignores the signal
+pub const SIG_IGN: usize = 1;
+
+bitflags! {
+    /// A set of signals, bit n stands for signal n
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct SignalFlags: u64 {
+        const SIGHUP = 1 << 1;
+        const SIGINT = 1 << 2;
+        const SIGQUIT = 1 << 3;
+        const SIGILL = 1 << 4;
+        const SIGTRAP = 1 << 5;
+        const SIGABRT = 1 << 6;
+        const SIGBUS = 1 << 7;
+        const SIGFPE = 1 << 8;
+        const SIGKILL = 1 << 9;
+        const SIGUSR1 = 1 << 10;
+        const SIGSEGV = 1 << 11;
+        const SIGUSR2 = 1 << 12;
+        const SIGPIPE = 1 << 13;
+        const SIGALRM = 1 << 14;
+        const SIGTERM = 1 << 15;
+        const SIGCHLD = 1 << 17;
+        const SIGCONT = 1 << 18;
+        const SIGSTOP = 1 << 19;
+        const SIGTSTP = 1 << 20;
+        const SIGTTIN = 1 << 21;
+        const SIGTTOU = 1 << 22;
+        const SIGURG = 1 << 23;
+        const SIGWINCH = 1 << 28;
+    }
+}
+
+impl SignalFlags {
+    /// Signals that can be neither caught, ignored nor blocked
+    pub const UNCATCHABLE: Self = Self::SIGKILL.union(Self::SIGSTOP);
+    /// Signals whose default action stops the task
+    pub const STOP: Self = Self::SIGSTOP
+        .union(Self::SIGTSTP)
+        .union(Self::SIGTTIN)
+        .union(Self::SIGTTOU);
+    /// Signals whose default action is to do nothing
+    pub const IGNORED: Self = Self::SIGCHLD
+        .union(Self::SIGCONT)
+        .union(Self::SIGURG)
+        .union(Self::SIGWINCH);
+
+    /// The signal numbered `signum`, None if there is no such signal
+    pub fn from_signum(signum: usize) -> Option<Self> {
+        (1..NSIG)
+            .contains(&signum)
+            .then(|| Self::from_bits_retain(1 << signum))
+    }
+
+    /// Number of the lowest signal in the set
+    pub fn signum(&self) -> usize {
+        self.bits().trailing_zeros() as usize
+    }
+}
+
+/// What a task does on a signal, the layout is shared with user space
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct SignalAction {
+    /// [`SIG_DFL`], [`SIG_IGN`] or the address of a handler taking the
+    /// signal number
+    pub handler: usize,
+    /// Signals blocked while the handler runs, besides the signal itself
+    pub mask: SignalFlags,
+    /// Code the handler returns into, it has to call `sigreturn`
+    pub restorer: usize,
+}
+
+unsafe impl Pod for SignalFlags {}
+unsafe impl Pod for SignalAction {}
+
+impl Default for SignalAction {
+    fn default() -> Self {
+        Self {
+            handler: SIG_DFL,
+            mask: SignalFlags::empty(),
+            restorer: 0,
+        }
+    }
+}
+
+/// Saved on the user stack while a handler runs
+#[repr(C)]
+struct SignalFrame {
+    /// Where the task was interrupted
+    user_ctx: UserContext,
+    /// Blocked signals before the handler
+    blocked: SignalFlags,
+}
+
+// registers and signal bits only
+unsafe impl Pod for SignalFrame {}
+
+impl TaskInner {
+    /// Deliver a signal raised by the task itself, like a fault. It cannot
+    /// be blocked or ignored, the task would only fault again.
+    pub fn force_signal(&mut self, signal: SignalFlags) {
+        self.blocked_signals.remove(signal);
+        let process = self.process.upgrade().unwrap();
+        let action = &mut process.get_mutable_inner().signal_actions[signal.signum()];
+        if action.handler == SIG_IGN {
+            action.handler = SIG_DFL;
+        }
+        self.pending_signals.insert(signal);
+    }
+
+    /// Keep the ignored signals and reset the handlers, which are gone with
+    /// the old program
+    pub fn reset_signal_actions(&mut self) {
+        for action in self.signal_actions.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SignalAction::default();
+            }
+        }
+    }
+}
+
+impl Task {
+    /// Make `signal` pending and wake the task if it has to act on it, an
+    /// ignored signal does not interrupt a sleep. A task stopped by a signal
+    /// only wakes up for SIGCONT and SIGKILL.
+    pub fn send_signal(self: &Arc<Self>, signal: SignalFlags) {
+        let inner = self.get_mutable_inner();
+        if signal.intersects(SignalFlags::STOP) {
+            inner.pending_signals.remove(SignalFlags::SIGCONT);
+        } else if signal == SignalFlags::SIGCONT {
+            // continuing takes effect on sending, even if it is blocked
+            inner.pending_signals.remove(SignalFlags::STOP);
+            inner.stop_signal = None;
+        }
+        inner.pending_signals.insert(signal);
+        let blocked = inner.blocked_signals - SignalFlags::UNCATCHABLE;
+        let process = inner.process.upgrade().unwrap();
+        let handler = process.get_inner().signal_actions[signal.signum()].handler;
+        let ignored =
+            handler == SIG_IGN || (handler == SIG_DFL && signal.intersects(SignalFlags::IGNORED));
+        let wake = if inner.stop_signal.is_some() {
+            signal == SignalFlags::SIGKILL
+        } else {
+            !blocked.contains(signal) && (!ignored || signal == SignalFlags::SIGCONT)
+        };
+        if wake {
+            schedule::wake(self.clone());
+        }
+    }
+}
+
+/// Act on the pending signals of the current task before it returns to
+/// user mode. The task may exit, be stopped with status `Waiting`, or
+/// resume in a handler.
+pub fn handle_signals(task: &Arc<Task>) {
+    let inner = task.get_mutable_inner();
+    let process = inner.process.upgrade().unwrap();
+    loop {
+        if inner.stop_signal.is_some() {
+            if inner.pending_signals.contains(SignalFlags::SIGKILL) {
+                PROCESSOR
+                    .as_mut()
+                    .exit_current(exit_code(SignalFlags::SIGKILL));
+            } else {
+                schedule::suspend_current();
+            }
+            return;
+        }
+        let deliverable =
+            inner.pending_signals - (inner.blocked_signals - SignalFlags::UNCATCHABLE);
+        if deliverable.is_empty() {
+            return;
+        }
+        let signal = SignalFlags::from_bits_retain(1 << deliverable.signum());
+        inner.pending_signals.remove(signal);
+        let action = process.get_inner().signal_actions[signal.signum()];
+        match action.handler {
+            SIG_IGN => {}
+            SIG_DFL if signal.intersects(SignalFlags::IGNORED) => {}
+            SIG_DFL if signal.intersects(SignalFlags::STOP) => {
+                inner.stop_signal = Some(signal);
+                // a parent waiting for this task may want to know
+                task.notify_parent();
+            }
+            SIG_DFL => {
+                info!(
+                    "Task {} killed by signal {}",
+                    task.taskid.value,
+                    signal.signum()
+                );
+                PROCESSOR.as_mut().exit_current(exit_code(signal));
+                return;
+            }
+            handler => {
+                if enter_handler(inner, signal, handler, &action) {
+                    return;
+                }
+                // there is no room for the frame
+                inner.force_signal(SignalFlags::SIGSEGV);
+                process.get_mutable_inner().signal_actions[SignalFlags::SIGSEGV.signum()] =
+                    SignalAction::default();
+            }
+        }
+    }
+}
+
+/// Send `signal` to every process in the group `pgid` but the init
+/// process. Returns false if there is none.
+pub fn signal_group(pgid: usize, signal: SignalFlags) -> bool {
+    let mut found = false;
+    for process in find_group(pgid) {
+        if !Arc::ptr_eq(&process, &INITPROC) {
+            process.send_signal(signal);
+            found = true;
+        }
+    }
+    found
+}
+
+/// Exit code of a task killed by `signal`, the negated signal number
+pub fn exit_code(signal: SignalFlags) -> i32 {
+    -(signal.signum() as i32)
+}
+
+/// Push a [`SignalFrame`] below the user stack and make the task run
+/// `handler` on top of it. Returns false if the stack is not writable.
+fn enter_handler(
+    inner: &mut TaskInner,
+    signal: SignalFlags,
+    handler: usize,
+    action: &SignalAction,
+) -> bool {
+    let size = core::mem::size_of::<SignalFrame>();
+    let sp = inner.user_ctx.get_sp().wrapping_sub(size) & !0xf;
+    // the handler may sleep itself, the sleep it interrupts is over
+    if let Some(sleep) = inner.sleep.take() {
+        sleep.interrupt(&mut inner.user_ctx);
+    }
+    let frame = SignalFrame {
+        user_ctx: inner.user_ctx.clone(),
+        blocked: inner.blocked_signals,
+    };
+    if UserPtr::new(sp).write(frame).is_err() {
+        return false;
+    }
+    inner.blocked_signals |= action.mask | signal;
+    inner.user_ctx.set_ip(handler);
+    inner.user_ctx.set_sp(sp);
+    inner.user_ctx.general.a0 = signal.signum();
+    inner.user_ctx.general.ra = action.restorer;
+    true
+}
+
+/// Resume from the [`SignalFrame`] at the user sp when a handler returns.
+/// Returns false, changing nothing, if there is no readable frame.
+pub fn restore_frame(inner: &mut TaskInner) -> bool {
+    let Ok(frame) = UserPtr::<SignalFrame>::new(inner.user_ctx.get_sp()).read() else {
+        return false;
+    };
+    // the privilege bits must not come from user memory
+    let sstatus = inner.user_ctx.sstatus;
+    inner.user_ctx = frame.user_ctx;
+    inner.user_ctx.sstatus = sstatus;
+    inner.blocked_signals = frame.blocked - SignalFlags::UNCATCHABLE;
+    true
+}
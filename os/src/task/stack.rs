@@ -1,6 +1,13 @@
+use core::mem::size_of;
+
+use alloc::{string::String, vec, vec::Vec};
+
 use crate::{
-    config::{KERNEL_SPACE_OFFSET, KERNEL_STACK_SIZE, PAGE_SIZE, USER_STACK_SIZE},
-    mm::memory_set::{MapArea, MapPermission, MapType},
+    config::{KERNEL_STACK_SIZE, PAGE_SIZE, USER_SPACE_MAX, USER_STACK_SIZE},
+    mm::{
+        address::VirtAddr,
+        memory_set::{MapArea, MapPermission, MapType, MemorySet},
+    },
 };
 
 pub struct KernelStack {
@@ -11,29 +18,138 @@ impl KernelStack {
     pub fn new(pid: usize) -> Self {
         Self {
             area: MapArea::new(
-                (usize::MAX - (KERNEL_STACK_SIZE + PAGE_SIZE) * pid - KERNEL_STACK_SIZE + 1).into(),
+                Self::bottom(pid).into(),
                 (usize::MAX - (KERNEL_STACK_SIZE + PAGE_SIZE) * pid).into(),
                 MapType::Framed,
                 MapPermission::R | MapPermission::W,
             ),
         }
     }
+
+    /// Lowest address of the kernel stack of the task `pid`
+    pub fn bottom(pid: usize) -> usize {
+        usize::MAX - (KERNEL_STACK_SIZE + PAGE_SIZE) * pid - KERNEL_STACK_SIZE + 1
+    }
 }
 
 pub struct UserStack {
     pub area: MapArea,
 }
 
-const USER_SPACE_MAX: usize = usize::MAX - KERNEL_SPACE_OFFSET;
 impl UserStack {
     pub fn new(tid: usize) -> Self {
         Self {
             area: MapArea::new(
-                (USER_SPACE_MAX - (USER_STACK_SIZE + PAGE_SIZE) * tid - USER_STACK_SIZE + 1).into(),
+                Self::bottom(tid).into(),
                 (USER_SPACE_MAX - (USER_STACK_SIZE + PAGE_SIZE) * tid).into(),
                 MapType::Framed,
                 MapPermission::R | MapPermission::W | MapPermission::U,
             ),
         }
     }
+
+    /// Lowest address of the user stack of the thread `tid`
+    pub fn bottom(tid: usize) -> usize {
+        USER_SPACE_MAX - (USER_STACK_SIZE + PAGE_SIZE) * tid - USER_STACK_SIZE + 1
+    }
+}
+
+/// Put a fresh copy of the thread-local data of the program at the top of
+/// a user stack below `sp`. The RISC-V psABI has tp point right at it, with
+/// no control block before. `memory_set` need not be active.
+///
+/// Returns the new sp and tp, which is 0 if the program has no TLS.
+pub fn push_tls(memory_set: &mut MemorySet, sp: usize) -> (usize, usize) {
+    let Some(tls) = memory_set.tls.as_ref() else {
+        return (sp, 0);
+    };
+    let tp = (sp - tls.mem_size) & !(tls.align - 1);
+    // whole pages from the one tp is in, the stack is still all zeros
+    let start = VirtAddr::from(tp).floor();
+    let offset = tp - VirtAddr::from(start).0;
+    let mut block = vec![0u8; sp - VirtAddr::from(start).0];
+    block[offset..offset + tls.data.len()].copy_from_slice(&tls.data);
+    memory_set.write_pages(start, &block);
+    (tp & !0xf, tp)
+}
+
+/// Keys of the auxiliary vector, see `getauxval(3)`.
+pub const AT_NULL: usize = 0;
+pub const AT_PHDR: usize = 3;
+pub const AT_PHENT: usize = 4;
+pub const AT_PHNUM: usize = 5;
+pub const AT_PAGESZ: usize = 6;
+pub const AT_ENTRY: usize = 9;
+
+/// Most auxv pairs the kernel passes, `AT_NULL` included
+const AUXV_MAX: usize = 8;
+/// Most of the user stack the initial arguments may take, the rest is
+/// left to the program
+pub const ARGS_SPACE: usize = USER_STACK_SIZE / 4;
+
+/// Bytes [`push_args`] takes for `args` and `envs`, with room for the
+/// alignment and the most auxv pairs
+pub fn args_size(args: &[String], envs: &[String]) -> usize {
+    let strs: usize = args.iter().chain(envs).map(|s| s.len() + 1).sum();
+    let words = 1 + args.len() + 1 + envs.len() + 1 + 2 * AUXV_MAX;
+    strs + words * size_of::<usize>() + 0xf
+}
+
+/// Lay out the initial stack of a program below `sp` as the SysV ABI does:
+/// argc at the new sp, followed by the argv and envp arrays, each ended by
+/// null, and the auxv pairs ended by `AT_NULL`. The strings sit above them.
+/// The stack is written through `memory_set`, which need not be active.
+///
+/// Returns the new sp, argv and envp, or None if it all takes more than
+/// [`ARGS_SPACE`].
+pub fn push_args(
+    memory_set: &mut MemorySet,
+    sp: usize,
+    args: &[String],
+    envs: &[String],
+    auxv: &[(usize, usize)],
+) -> Option<(usize, usize, usize)> {
+    if auxv.len() >= AUXV_MAX || args_size(args, envs) > ARGS_SPACE {
+        return None;
+    }
+    // the strings go right below sp, the words below them
+    let strs_len: usize = args.iter().chain(envs).map(|s| s.len() + 1).sum();
+    let words_len = 1 + args.len() + 1 + envs.len() + 1 + 2 * (auxv.len() + 1);
+    // sp must be 16-byte aligned at the entry
+    let new_sp = (sp - strs_len - words_len * size_of::<usize>()) & !0xf;
+
+    // whole pages from the one the new sp is in, the stack below sp is
+    // still all zeros
+    let start = VirtAddr::from(new_sp).floor();
+    let base = VirtAddr::from(start).0;
+    let mut block = vec![0u8; sp - base];
+    let mut str_addr = sp - strs_len;
+    let mut push_str = |s: &String| {
+        let offset = str_addr - base;
+        block[offset..offset + s.len()].copy_from_slice(s.as_bytes());
+        str_addr += s.len() + 1;
+        str_addr - s.len() - 1
+    };
+    let arg_ptrs: Vec<usize> = args.iter().map(&mut push_str).collect();
+    let env_ptrs: Vec<usize> = envs.iter().map(&mut push_str).collect();
+
+    let mut words = Vec::with_capacity(words_len);
+    words.push(args.len());
+    words.extend(arg_ptrs);
+    words.push(0);
+    words.extend(env_ptrs);
+    words.push(0);
+    for (key, value) in auxv {
+        words.extend([*key, *value]);
+    }
+    words.extend([AT_NULL, 0]);
+    for (i, word) in words.iter().enumerate() {
+        let offset = new_sp - base + i * size_of::<usize>();
+        block[offset..offset + size_of::<usize>()].copy_from_slice(&word.to_ne_bytes());
+    }
+    memory_set.write_pages(start, &block);
+
+    let argv = new_sp + size_of::<usize>();
+    let envp = argv + (args.len() + 1) * size_of::<usize>();
+    Some((new_sp, argv, envp))
 }
@@ -0,0 +1,157 @@
+//! Waiting for children and threads to change state
+//!
+//! A task in `waitpid` or `waittid` that has to wait records a [`Wait`] and
+//! blocks without restarting its syscall. The child or thread it waits for
+//! fills in the result when it exits or stops and wakes the task, which
+//! stores the status in its own address space with [`finish_wait`] before it
+//! returns to user mode. An exited child is reaped only then, it stays
+//! around for another wait if the status can't be stored. A signal handler
+//! that runs in between leaves the wait unfinished, the syscall restarts
+//! after the handler then.
+use alloc::collections::vec_deque::VecDeque;
+use alloc::sync::{Arc, Weak};
+use riscv::register::sstatus::set_sum;
+
+use super::signal::SignalFlags;
+use super::{Task, TaskInner, TaskStatus, schedule};
+use crate::mm::UserPtr;
+
+/// What a blocked task waits for
+pub enum WaitTarget {
+    /// A child that `pid` stands for, -1 for any, to exit, or to stop with
+    /// `untraced`
+    Child { pid: isize, untraced: bool },
+    /// The thread `tid` of the process to exit, it is reaped then
+    Thread { tid: usize },
+}
+
+/// A `waitpid` or `waittid` the task is blocked in
+pub struct Wait {
+    pub target: WaitTarget,
+    /// Where `waitpid` stores the status, may be null
+    status_ptr: UserPtr<i32>,
+    /// What the syscall returns and the status to store, once it is over
+    result: Option<(usize, Option<i32>)>,
+    /// The exited child to reap once the status is stored
+    zombie: Option<Arc<Task>>,
+}
+
+impl Wait {
+    pub fn new(target: WaitTarget, status_ptr: UserPtr<i32>) -> Self {
+        Self {
+            target,
+            status_ptr,
+            result: None,
+            zombie: None,
+        }
+    }
+}
+
+/// Status of a child stopped by `signal`, like a Linux wait status
+pub fn stopped_code(signal: SignalFlags) -> i32 {
+    (signal.signum() as i32) << 8 | 0x7f
+}
+
+/// The unfinished wait of `task` if it is still after a child
+fn child_wait(task: &Task) -> Option<&mut Wait> {
+    task.get_mutable_inner()
+        .wait
+        .as_mut()
+        .filter(|wait| wait.result.is_none() && matches!(wait.target, WaitTarget::Child { .. }))
+}
+
+impl Task {
+    /// Finish the waits of the parent that this child, which has just
+    /// exited or stopped, satisfies. An exited child is handed to the first
+    /// of them to reap, a stop is reported to the first as well.
+    pub fn notify_parent(self: &Arc<Self>) {
+        let inner = self.get_inner();
+        let Some(parent) = inner.parent.as_ref().and_then(Weak::upgrade) else {
+            return;
+        };
+        let exited = inner.status == TaskStatus::Zombie;
+        let status = match inner.stop_signal {
+            _ if exited => inner.exit_code,
+            Some(signal) => stopped_code(signal),
+            None => return,
+        };
+        let parent_inner = parent.get_mutable_inner();
+        let mut waiters = core::mem::take(&mut parent_inner.child_waiters);
+        while let Some(waiter) = waiters.pop_front() {
+            // the task is waiting for something else since
+            let Some(wait) = child_wait(&waiter) else {
+                continue;
+            };
+            let WaitTarget::Child { pid, untraced } = wait.target else {
+                unreachable!()
+            };
+            if (pid != -1 && pid as usize != self.taskid.value) || !(exited || untraced) {
+                parent_inner.child_waiters.push_back(waiter);
+                continue;
+            }
+            wait.result = Some((self.taskid.value, Some(status)));
+            wait.zombie = exited.then(|| self.clone());
+            schedule::wake(waiter);
+            break;
+        }
+        parent_inner.child_waiters.extend(waiters);
+    }
+
+    /// Finish the `waittid`s on this thread, which has just exited
+    pub fn notify_joiners(&self) {
+        let inner = self.get_mutable_inner();
+        for waiter in inner.waiting_tasks.drain(..) {
+            let waiter_inner = waiter.get_mutable_inner();
+            if let Some(wait) = waiter_inner.wait.as_mut().filter(|wait| {
+                wait.result.is_none() && matches!(wait.target, WaitTarget::Thread { .. })
+            }) {
+                wait.result = Some((inner.exit_code as usize, None));
+            }
+            schedule::wake(waiter);
+        }
+    }
+}
+
+/// Record `wait` for the current task and take it off the ready queue
+/// until it is over, the task waits in `queue`
+pub fn block_on(current: &Task, queue: &mut VecDeque<Arc<Task>>, wait: Wait) {
+    current.get_mutable_inner().wait = Some(wait);
+    schedule::block_current(queue);
+}
+
+/// Return from the wait the current task was woken from, if any. The
+/// result goes to a0 and the status to user memory, a wait that is not
+/// over restarts its syscall. A thread that was waited for is reaped here,
+/// where it no longer runs on its kernel stack, an exited child once its
+/// status is stored.
+pub fn finish_wait(inner: &mut TaskInner) {
+    let Some(wait) = inner.wait.take() else {
+        return;
+    };
+    let Some((ret, status)) = wait.result else {
+        inner.user_ctx.sepc -= 4;
+        return;
+    };
+    if let WaitTarget::Thread { tid } = wait.target {
+        let process = inner.process.upgrade().unwrap();
+        process.get_mutable_inner().reap_thread(tid);
+    }
+    let stored = match status {
+        Some(status) if !wait.status_ptr.is_null() => {
+            unsafe {
+                set_sum();
+            }
+            wait.status_ptr.write(status)
+        }
+        _ => Ok(()),
+    };
+    inner.user_ctx.general.a0 = match stored {
+        Ok(()) => {
+            if let Some(zombie) = wait.zombie {
+                inner.children.retain(|child| !Arc::ptr_eq(child, &zombie));
+            }
+            ret
+        }
+        Err(err) => err.to_ret() as usize,
+    };
+}
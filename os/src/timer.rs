@@ -1,11 +1,53 @@
 //! RISC-V timer-related functionality
 
+use core::sync::atomic::{AtomicUsize, Ordering};
+
 use crate::config::CLOCK_FREQ;
+use crate::mm::Pod;
 use crate::sbi::set_timer;
+use crate::sleep;
 use riscv::register::time;
 
 const TICKS_PER_SEC: usize = 100;
 const MSEC_PER_SEC: usize = 1000;
+const NSEC_PER_SEC: usize = 1_000_000_000;
+
+/// When the time slice running now is over, in ticks of the `time` CSR
+static SLICE_END: AtomicUsize = AtomicUsize::new(0);
+
+/// A time in seconds and nanoseconds, `struct timespec`
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+unsafe impl Pod for TimeSpec {}
+
+impl TimeSpec {
+    /// The time in ticks of the `time` CSR, None if `nsec` is not below one
+    /// second
+    pub fn to_ticks(&self) -> Option<usize> {
+        if self.nsec >= NSEC_PER_SEC {
+            return None;
+        }
+        let ticks = self.nsec as u128 * CLOCK_FREQ as u128 / NSEC_PER_SEC as u128;
+        self.sec
+            .checked_mul(CLOCK_FREQ)?
+            .checked_add(ticks as usize)
+    }
+
+    /// The time `ticks` of the `time` CSR take
+    pub fn from_ticks(ticks: usize) -> Self {
+        let nsec = (ticks % CLOCK_FREQ) as u128 * NSEC_PER_SEC as u128 / CLOCK_FREQ as u128;
+        Self {
+            sec: ticks / CLOCK_FREQ,
+            nsec: nsec as usize,
+        }
+    }
+}
+
 ///get current time
 pub fn get_time() -> usize {
     time::read()
@@ -14,7 +56,20 @@ pub fn get_time() -> usize {
 pub fn get_time_ms() -> usize {
     time::read() / (CLOCK_FREQ / MSEC_PER_SEC)
 }
-/// set the next timer interrupt
+/// Start the next time slice if the one running is over, returns whether
+/// it was
+pub fn next_slice() -> bool {
+    let now = get_time();
+    if now < SLICE_END.load(Ordering::Relaxed) {
+        return false;
+    }
+    SLICE_END.store(now + CLOCK_FREQ / TICKS_PER_SEC, Ordering::Relaxed);
+    true
+}
+/// Set the next timer interrupt at the end of the time slice, or at the
+/// deadline of a sleeper if that is nearer
 pub fn set_next_trigger() {
-    set_timer(get_time() + CLOCK_FREQ / TICKS_PER_SEC);
+    let slice_end = SLICE_END.load(Ordering::Relaxed);
+    let next = sleep::next_deadline().map_or(slice_end, |deadline| deadline.min(slice_end));
+    set_timer(next);
 }
@@ -2,7 +2,7 @@
 use core::ops::{Deref, DerefMut};
 
 #[repr(C)]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 ///trap context structure containing sstatus, sepc and registers
 pub struct TrapFrame {
     /// General registers, 0 ~ 31
@@ -52,7 +52,7 @@ pub struct GeneralRegs {
 }
 
 #[repr(C)]
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct UserContext(TrapFrame);
 
 impl Deref for UserContext {
@@ -86,14 +86,14 @@ impl UserContext {
     }
 
     /// Get syscall args
-    pub fn get_syscall_args(&self) -> [usize; 3] {
+    pub fn get_syscall_args(&self) -> [usize; 6] {
         [
             self.general.a0,
             self.general.a1,
             self.general.a2,
-            // self.general.a3,
-            // self.general.a4,
-            // self.general.a5,
+            self.general.a3,
+            self.general.a4,
+            self.general.a5,
         ]
     }
 
@@ -114,7 +114,7 @@ impl UserContext {
 
     /// Set tls pointer
     pub fn set_tls(&mut self, tls: usize) {
-        self.general.gp = tls;
+        self.general.tp = tls;
     }
 
     pub fn run(&self) {
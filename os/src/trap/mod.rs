@@ -3,9 +3,21 @@ pub mod context;
 use core::arch::global_asm;
 
 use context::TrapFrame;
-use riscv::register::{sepc, sie, stvec};
+use riscv::{
+    interrupt::{
+        Trap,
+        supervisor::{Exception, Interrupt},
+    },
+    register::{scause, sepc, sie, stval, stvec},
+};
 
-use crate::{lang_items::print_backtrace, println, sbi::shutdown};
+use crate::{
+    lang_items::print_backtrace,
+    mm::{memory_set::MapPermission, user_ptr},
+    println,
+    sbi::shutdown,
+    task::current_task,
+};
 
 global_asm!(include_str!("trap.S"));
 
@@ -30,7 +42,26 @@ pub fn enable_timer_interrupt() {
 
 #[unsafe(no_mangle)]
 #[allow(unused)]
-fn trap_handler(trapframe: &TrapFrame) -> ! {
+fn trap_handler(trapframe: &mut TrapFrame) {
+    let cause = scause::read().cause();
+    // the kernel touches a user page that is not present or shared yet
+    let access = match cause.try_into::<Interrupt, Exception>() {
+        Ok(Trap::Exception(Exception::LoadPageFault)) => Some(MapPermission::R),
+        Ok(Trap::Exception(Exception::StorePageFault)) => Some(MapPermission::W),
+        _ => None,
+    };
+    if let (Some(access), Some(task)) = (access, current_task()) {
+        let memory_set = task.get_inner().memory_set.get_mut();
+        if memory_set.handle_page_fault(stval::read().into(), access, false) {
+            return;
+        }
+    }
+    // a copy from or to user space fails instead
+    if let Some(fixup) = access.and_then(|_| user_ptr::fixup(trapframe.sepc)) {
+        trapframe.sepc = fixup;
+        return;
+    }
+
     let sepc = sepc::read();
     println!("[trap] a trap occurs!");
     unsafe {
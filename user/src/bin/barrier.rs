@@ -0,0 +1,86 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    condvar_broadcast, condvar_create, condvar_signal, condvar_wait, mutex_create, mutex_lock,
+    mutex_unlock, thread_create, thread_exit, waittid,
+};
+
+const THREADS: usize = 4;
+const ROUNDS: usize = 20;
+
+/// Guards the state of the barrier and of the handoff
+const MUTEX: usize = 0;
+/// All threads have arrived at the barrier
+const CONDVAR_BARRIER: usize = 0;
+/// The handoff has happened
+const CONDVAR_HANDOFF: usize = 1;
+
+static ARRIVED: AtomicUsize = AtomicUsize::new(0);
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+static ROUND: [AtomicUsize; THREADS] = [const { AtomicUsize::new(0) }; THREADS];
+static HANDED_OFF: AtomicUsize = AtomicUsize::new(0);
+
+/// Wait until all threads have called it
+fn barrier_wait() {
+    mutex_lock(MUTEX);
+    let generation = GENERATION.load(Ordering::Relaxed);
+    if ARRIVED.fetch_add(1, Ordering::Relaxed) + 1 == THREADS {
+        ARRIVED.store(0, Ordering::Relaxed);
+        GENERATION.store(generation + 1, Ordering::Relaxed);
+        condvar_broadcast(CONDVAR_BARRIER);
+    } else {
+        while GENERATION.load(Ordering::Relaxed) == generation {
+            condvar_wait(CONDVAR_BARRIER, MUTEX);
+        }
+    }
+    mutex_unlock(MUTEX);
+}
+
+fn worker(id: usize) {
+    for round in 1..=ROUNDS {
+        ROUND[id].store(round, Ordering::SeqCst);
+        barrier_wait();
+        // nobody gets past the barrier of the next round yet
+        for other in ROUND.iter() {
+            let other = other.load(Ordering::SeqCst);
+            assert!(other == round || other == round + 1);
+        }
+    }
+    thread_exit(0);
+}
+
+fn waiter(_arg: usize) {
+    mutex_lock(MUTEX);
+    while HANDED_OFF.load(Ordering::Relaxed) == 0 {
+        condvar_wait(CONDVAR_HANDOFF, MUTEX);
+    }
+    mutex_unlock(MUTEX);
+    thread_exit(0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(mutex_create(), MUTEX as isize);
+    assert_eq!(condvar_create(), CONDVAR_BARRIER as isize);
+    assert_eq!(condvar_create(), CONDVAR_HANDOFF as isize);
+
+    let tid = thread_create(waiter as usize, 0);
+    mutex_lock(MUTEX);
+    HANDED_OFF.store(1, Ordering::Relaxed);
+    condvar_signal(CONDVAR_HANDOFF);
+    mutex_unlock(MUTEX);
+    assert_eq!(waittid(tid as usize), 0);
+
+    let tids: [isize; THREADS] = core::array::from_fn(|id| thread_create(worker as usize, id));
+    for tid in tids {
+        assert!(tid > 0);
+        assert_eq!(waittid(tid as usize), 0);
+    }
+    println!("barrier passed!");
+    0
+}
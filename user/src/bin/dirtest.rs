@@ -0,0 +1,53 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{chdir, close, getcwd, mkdir, open, read, rmdir, write, Errno, OpenFlags};
+
+fn cwd(buf: &mut [u8]) -> &str {
+    let len = getcwd(buf);
+    assert!(len > 0);
+    core::str::from_utf8(&buf[..len as usize - 1]).unwrap()
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut buf = [0u8; 64];
+    let test_str = "Hello, directory!";
+    assert_eq!(mkdir("dirtest_a\0"), 0);
+    assert_eq!(mkdir("dirtest_a\0"), Errno::EEXIST.to_ret());
+    assert_eq!(mkdir("dirtest_a/b\0"), 0);
+    // a file is not a directory
+    assert!(chdir("initproc\0") < 0);
+
+    assert_eq!(chdir("dirtest_a/b\0"), 0);
+    assert_eq!(cwd(&mut buf), "/dirtest_a/b");
+    assert_eq!(getcwd(&mut buf[..4]), Errno::ERANGE.to_ret());
+    let fd = open("file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, test_str.as_bytes());
+    close(fd as usize);
+
+    assert_eq!(chdir("..\0"), 0);
+    assert_eq!(cwd(&mut buf), "/dirtest_a");
+    let fd = open("./b/../b/file\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let len = read(fd as usize, &mut buf) as usize;
+    close(fd as usize);
+    assert_eq!(core::str::from_utf8(&buf[..len]).unwrap(), test_str);
+    // directories can't be opened for writing
+    assert!(open("b\0", OpenFlags::WRONLY) < 0);
+
+    // only empty directories can be removed
+    assert!(rmdir("b\0") < 0);
+    assert_eq!(mkdir("empty\0"), 0);
+    assert_eq!(rmdir("/dirtest_a/empty\0"), 0);
+    assert!(chdir("empty\0") < 0);
+
+    assert_eq!(chdir("/\0"), 0);
+    assert_eq!(cwd(&mut buf), "/");
+    println!("dirtest passed!");
+    0
+}
@@ -0,0 +1,92 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+extern crate alloc;
+
+use alloc::format;
+use user_lib::{
+    close, dup, dup2, dup3, exec, fcntl, fork, open, read, unlink, waitpid, write, Errno,
+    OpenFlags, FD_CLOEXEC, F_GETFD, F_SETFD,
+};
+
+/// After exec, `argv[1]` has to be closed and `argv[2]` still open.
+fn check_exec(argv: &[&str]) -> i32 {
+    let closed: usize = argv[1].parse().unwrap();
+    let open: usize = argv[2].parse().unwrap();
+    assert!(fcntl(closed, F_GETFD, 0) < 0);
+    assert_eq!(fcntl(open, F_GETFD, 0), 0);
+    0
+}
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc == 3 {
+        return check_exec(argv);
+    }
+    let fd = open("duptest_a\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    // duplicates share the offset
+    let fd2 = dup(fd);
+    assert!(fd2 > 0);
+    let fd2 = fd2 as usize;
+    write(fd, b"ab");
+    write(fd2, b"cd");
+    assert_eq!(fcntl(fd2, F_GETFD, 0), 0);
+    close(fd2);
+
+    // redirect stdout to the file and back
+    let stdout = dup(1) as usize;
+    assert_eq!(dup2(fd, 1), 1);
+    println!("redirected");
+    assert_eq!(dup2(stdout, 1), 1);
+    close(stdout);
+    assert_eq!(dup2(fd, fd), fd as isize);
+    assert!(dup3(fd, fd, OpenFlags::empty()) < 0);
+    // the fd table does not grow without bound
+    assert_eq!(
+        dup3(fd, usize::MAX, OpenFlags::empty()),
+        Errno::EBADF.to_ret()
+    );
+    assert_eq!(dup3(fd, 10, OpenFlags::CLOEXEC), 10);
+    assert_eq!(fcntl(10, F_GETFD, 0), FD_CLOEXEC as isize);
+    assert_eq!(fcntl(10, F_SETFD, 0), 0);
+    assert_eq!(fcntl(10, F_GETFD, 0), 0);
+    close(10);
+    close(fd);
+
+    let fd = open("duptest_a\0", OpenFlags::RDONLY | OpenFlags::CLOEXEC);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(fcntl(fd, F_GETFD, 0), FD_CLOEXEC as isize);
+    let mut buf = [0u8; 32];
+    let len = read(fd, &mut buf) as usize;
+    assert_eq!(&buf[..len], b"abcdredirected\n");
+
+    // close-on-exec is not inherited by duplicates
+    let plain = dup(fd) as usize;
+    let pid = fork();
+    if pid == 0 {
+        let closed = format!("{}\0", fd);
+        let open = format!("{}\0", plain);
+        let args = [
+            "duptest\0".as_ptr(),
+            closed.as_ptr(),
+            open.as_ptr(),
+            core::ptr::null(),
+        ];
+        exec("duptest\0", &args, &[core::ptr::null()]);
+        return -1;
+    }
+    let mut exit_code = -1;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    close(plain);
+    close(fd);
+    assert_eq!(unlink("duptest_a\0"), 0);
+    println!("duptest passed!");
+    0
+}
@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::slice;
+use user_lib::{close, fstat, open, pipe, read, unlink, write, Errno, OpenFlags, Stat};
+
+/// Where the kernel is mapped, above all of user space
+const KERNEL_ADDR: usize = 0xffff_ffc0_8020_0000;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let efault = Errno::EFAULT.to_ret();
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(write(pipe_fd[1], b"efault"), 6);
+
+    // reading into memory the task can't write
+    let kernel = unsafe { slice::from_raw_parts_mut(KERNEL_ADDR as *mut u8, 6) };
+    assert_eq!(read(pipe_fd[0], kernel), efault);
+    let text = unsafe { slice::from_raw_parts_mut(main as usize as *mut u8, 6) };
+    assert_eq!(read(pipe_fd[0], text), efault);
+    // the data is still there after the failed reads
+    let mut buf = [0u8; 6];
+    assert_eq!(read(pipe_fd[0], &mut buf), 6);
+    assert_eq!(&buf, b"efault");
+
+    // writing from memory the task can't read
+    let kernel = unsafe { slice::from_raw_parts(KERNEL_ADDR as *const u8, 6) };
+    assert_eq!(write(pipe_fd[1], kernel), efault);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    let path = unsafe {
+        core::str::from_utf8_unchecked(slice::from_raw_parts(KERNEL_ADDR as *const u8, 1))
+    };
+    assert_eq!(open(path, OpenFlags::RDONLY), efault);
+    let fd = open("efaulttest_a\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let st = unsafe { &mut *(KERNEL_ADDR as *mut Stat) };
+    assert_eq!(fstat(fd as usize, st), efault);
+    close(fd as usize);
+    assert_eq!(unlink("efaulttest_a\0"), 0);
+
+    // the console takes any bytes, not only UTF-8
+    assert_eq!(write(1, b"\xff\xfe\n"), 3);
+    println!("efaulttest passed!");
+    0
+}
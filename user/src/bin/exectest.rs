@@ -0,0 +1,33 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+extern crate alloc;
+
+use alloc::vec;
+use user_lib::{close, exec, open, spawn, unlink, write, Errno, OpenFlags};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // a file that is no program leaves the caller running
+    let fd = open("exectest_a\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, b"not a program\n"), 14);
+    close(fd as usize);
+    let args = [core::ptr::null::<u8>()];
+    assert_eq!(exec("exectest_a\0", &args, &args), Errno::ENOEXEC.to_ret());
+    assert_eq!(spawn("exectest_a\0"), Errno::ENOEXEC.to_ret());
+    assert_eq!(unlink("exectest_a\0"), 0);
+
+    // so do more arguments than the stack has room for
+    let mut args = vec![b"x\0".as_ptr(); 5000];
+    args.push(core::ptr::null());
+    assert_eq!(
+        exec("cmdline_args\0", &args, &[core::ptr::null()]),
+        Errno::E2BIG.to_ret()
+    );
+    println!("exectest passed!");
+    0
+}
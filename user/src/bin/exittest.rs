@@ -0,0 +1,66 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    exit, fork, sigaction, sleep, thread_create, wait, waitpid, yield_, Errno, SignalAction,
+    SignalFlags, SIGCHLD,
+};
+
+/// SIGCHLDs handled so far
+static CHILD_SIGNALS: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn sigchld_handler(_signum: usize) {
+    CHILD_SIGNALS.fetch_add(1, Ordering::SeqCst);
+}
+
+fn spin(_arg: usize) {
+    loop {
+        yield_();
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // the parent hears of an exiting child
+    let action = SignalAction::new(sigchld_handler as usize, SignalFlags::empty());
+    assert_eq!(sigaction(SIGCHLD, Some(&action), None), 0);
+    let pid = fork();
+    if pid == 0 {
+        exit(3);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 3);
+    assert_eq!(CHILD_SIGNALS.load(Ordering::SeqCst), 1);
+    assert_eq!(sigaction(SIGCHLD, Some(&SignalAction::default()), None), 0);
+
+    // a grandchild outliving its parent is reaped by initproc
+    let pid = fork();
+    if pid == 0 {
+        if fork() == 0 {
+            sleep(50);
+            exit(0);
+        }
+        exit(4);
+    }
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 4);
+    assert_eq!(wait(&mut exit_code), Errno::ECHILD.to_ret());
+
+    // the threads of a process end with its main thread
+    let pid = fork();
+    if pid == 0 {
+        assert!(thread_create(spin as usize, 0) > 0);
+        assert!(thread_create(spin as usize, 0) > 0);
+        yield_();
+        exit(5);
+    }
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 5);
+    println!("exittest passed!");
+    0
+}
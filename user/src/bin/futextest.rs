@@ -0,0 +1,66 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use user_lib::sync::{Mutex, OnceCell, RwLock};
+use user_lib::{futex_wait, futex_wake, thread_create, thread_exit, waittid, yield_, Errno};
+
+const THREADS: usize = 4;
+const INCREMENTS: usize = 200;
+
+static COUNTER: Mutex<usize> = Mutex::new(0);
+/// Both halves are always written together
+static PAIR: RwLock<(usize, usize)> = RwLock::new((0, 0));
+static ONCE: OnceCell<usize> = OnceCell::new();
+static INITS: AtomicUsize = AtomicUsize::new(0);
+
+fn worker(id: usize) {
+    for _ in 0..INCREMENTS {
+        let mut counter = COUNTER.lock();
+        let value = *counter;
+        // let the others run into the lock
+        yield_();
+        *counter = value + 1;
+    }
+    for i in 0..INCREMENTS {
+        if i % 4 == id % 4 {
+            let mut pair = PAIR.write();
+            pair.0 += 1;
+            yield_();
+            pair.1 += 1;
+        } else {
+            let pair = PAIR.read();
+            assert_eq!(pair.0, pair.1);
+        }
+    }
+    let value = ONCE.get_or_init(|| {
+        INITS.fetch_add(1, Ordering::SeqCst);
+        yield_();
+        id
+    });
+    assert!(*value < THREADS);
+    thread_exit(0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // a futex that does not hold the expected value is not waited on
+    let futex = AtomicU32::new(1);
+    assert_eq!(futex_wait(&futex, 0), Errno::EAGAIN.to_ret());
+    assert_eq!(futex_wake(&futex, 1), 0);
+
+    let tids: [isize; THREADS] = core::array::from_fn(|id| thread_create(worker as usize, id));
+    for tid in tids {
+        assert!(tid > 0);
+        assert_eq!(waittid(tid as usize), 0);
+    }
+    assert_eq!(*COUNTER.lock(), THREADS * INCREMENTS);
+    let pair = PAIR.read();
+    assert_eq!(pair.0, pair.1);
+    assert_eq!(INITS.load(Ordering::SeqCst), 1);
+    println!("futextest passed!");
+    0
+}
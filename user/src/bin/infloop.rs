@@ -0,0 +1,12 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    println!("It should be killed by usertests!");
+    #[allow(clippy::empty_loop)]
+    loop {}
+}
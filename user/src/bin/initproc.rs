@@ -4,20 +4,24 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::{spawn, wait, yield_};
+use user_lib::{exec, fork, wait};
 
 #[no_mangle]
 fn main() -> i32 {
     println!("[initproc] initproc start");
-    let _ = spawn("user_shell\0");
+    if fork() == 0 {
+        exec(
+            "user_shell\0",
+            &[core::ptr::null::<u8>()],
+            &[core::ptr::null::<u8>()],
+        );
+        panic!("initproc cannot exec user_shell");
+    }
 
     loop {
+        // blocks while there is no child, until an orphan is handed over
         let mut exit_code: i32 = 0;
         let pid = wait(&mut exit_code);
-        if pid < 0 {
-            yield_();
-            continue;
-        }
         println!(
             "[initproc] Released a zombie process, pid={}, exit_code={}",
             pid, exit_code,
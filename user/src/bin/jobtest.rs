@@ -0,0 +1,110 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    exit, fork, getpgid, getpid, kill, read, setpgid, sleep, stop_signal, tcgetpgrp, tcsetpgrp,
+    waitpid, waitpid_with, Errno, WaitFlags, SIGCONT, SIGKILL, SIGSTOP, SIGTERM, SIGTTIN,
+};
+
+/// Fork a child in the process group `pgid`, 0 for a new one, that sleeps
+/// until it is killed
+fn sleeper(pgid: usize) -> isize {
+    let pid = fork();
+    if pid == 0 {
+        setpgid(0, pgid);
+        loop {
+            sleep(10);
+        }
+    }
+    // set it here as well, whichever of the two runs first
+    setpgid(pid as usize, if pgid == 0 { pid as usize } else { pgid });
+    pid
+}
+
+fn process_groups() {
+    let pid = getpid();
+    assert_eq!(getpgid(0), getpgid(pid as usize));
+    assert_eq!(setpgid(0, 0), 0);
+    assert_eq!(getpgid(0), pid);
+    assert_eq!(kill(0, 0), 0);
+
+    // the whole group gets the signal
+    let leader = sleeper(0);
+    let member = sleeper(leader as usize);
+    assert_eq!(getpgid(member as usize), leader);
+    assert_eq!(kill(-leader, SIGTERM), 0);
+    for pid in [leader, member] {
+        let mut exit_code = 0;
+        assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+        assert_eq!(exit_code, -(SIGTERM as i32));
+    }
+    assert_eq!(kill(-leader, 0), Errno::ESRCH.to_ret());
+    println!("process groups passed!");
+}
+
+fn wait_options() {
+    let pid = fork();
+    if pid == 0 {
+        sleep(50);
+        exit(3);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid_with(pid, &mut exit_code, WaitFlags::NOHANG), 0);
+    assert_eq!(waitpid_with(pid, &mut exit_code, WaitFlags::empty()), pid);
+    assert_eq!(exit_code, 3);
+
+    // a stopped child is reported with UNTRACED only
+    let pid = sleeper(0);
+    kill(pid, SIGSTOP);
+    assert_eq!(waitpid_with(pid, &mut exit_code, WaitFlags::UNTRACED), pid);
+    assert_eq!(stop_signal(exit_code), Some(SIGSTOP));
+    kill(pid, SIGCONT);
+    let options = WaitFlags::NOHANG | WaitFlags::UNTRACED;
+    assert_eq!(waitpid_with(pid, &mut exit_code, options), 0);
+    kill(pid, SIGKILL);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGKILL as i32));
+    println!("wait options passed!");
+}
+
+fn terminal() {
+    let old_foreground = tcgetpgrp(0);
+    assert!(old_foreground >= 0);
+    let pgid = getpgid(0) as usize;
+    assert_eq!(tcsetpgrp(0, pgid), 0);
+    assert_eq!(tcgetpgrp(0), pgid as isize);
+    assert_eq!(tcsetpgrp(0, 0), Errno::EINVAL.to_ret());
+
+    // reading from the background stops the reader
+    let pid = fork();
+    if pid == 0 {
+        setpgid(0, 0);
+        let mut buf = [0u8; 1];
+        read(0, &mut buf);
+        exit(0);
+    }
+    setpgid(pid as usize, pid as usize);
+    let mut exit_code = 0;
+    assert_eq!(waitpid_with(pid, &mut exit_code, WaitFlags::UNTRACED), pid);
+    assert_eq!(stop_signal(exit_code), Some(SIGTTIN));
+    kill(pid, SIGKILL);
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGKILL as i32));
+
+    if old_foreground > 0 {
+        tcsetpgrp(0, old_foreground as usize);
+    }
+    println!("terminal passed!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    process_groups();
+    wait_options();
+    terminal();
+    println!("jobtest passed!");
+    0
+}
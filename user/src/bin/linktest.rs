@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, link, mkdir, open, pread, read, rename, rmdir, unlink, write, OpenFlags};
+
+fn read_file(path: &str, buf: &mut [u8]) -> Option<usize> {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let len = read(fd as usize, buf) as usize;
+    close(fd as usize);
+    Some(len)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut buf = [0u8; 64];
+    let test_str = "Hello, link!";
+    let fd = open("linktest_a\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, test_str.as_bytes());
+    close(fd as usize);
+
+    assert_eq!(link("linktest_a\0", "linktest_b\0"), 0);
+    assert!(link("linktest_a\0", "linktest_b\0") < 0);
+    assert_eq!(unlink("linktest_a\0"), 0);
+    assert!(read_file("linktest_a\0", &mut buf).is_none());
+    let len = read_file("linktest_b\0", &mut buf).unwrap();
+    assert_eq!(core::str::from_utf8(&buf[..len]).unwrap(), test_str);
+
+    // an open file outlives its last link
+    let fd = open("linktest_e\0", OpenFlags::CREATE | OpenFlags::RDWR);
+    assert!(fd > 0);
+    write(fd as usize, test_str.as_bytes());
+    assert_eq!(unlink("linktest_e\0"), 0);
+    assert!(read_file("linktest_e\0", &mut buf).is_none());
+    let len = pread(fd as usize, &mut buf, 0) as usize;
+    assert_eq!(core::str::from_utf8(&buf[..len]).unwrap(), test_str);
+    close(fd as usize);
+
+    assert_eq!(mkdir("linktest_dir\0"), 0);
+    // directories are removed with rmdir and can't be linked
+    assert!(unlink("linktest_dir\0") < 0);
+    assert!(link("linktest_dir\0", "linktest_c\0") < 0);
+    assert_eq!(rename("linktest_b\0", "linktest_dir/c\0"), 0);
+    assert!(read_file("linktest_b\0", &mut buf).is_none());
+    let len = read_file("/linktest_dir/c\0", &mut buf).unwrap();
+    assert_eq!(core::str::from_utf8(&buf[..len]).unwrap(), test_str);
+    assert!(rename("linktest_dir\0", "linktest_dir/d\0") < 0);
+    assert!(rmdir("linktest_dir\0") < 0);
+    assert_eq!(unlink("linktest_dir/c\0"), 0);
+    assert_eq!(rmdir("linktest_dir\0"), 0);
+    println!("linktest passed!");
+    0
+}
@@ -0,0 +1,171 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, exit, fork, getpid, mmap, mprotect, munmap, open, read, wait, write, Errno, MapFlags,
+    OpenFlags, ProtFlags, SIGSEGV,
+};
+
+const PAGE_SIZE: usize = 0x1000;
+
+fn anonymous() {
+    let addr = mmap(
+        0,
+        3 * PAGE_SIZE,
+        ProtFlags::READ | ProtFlags::WRITE,
+        MapFlags::PRIVATE | MapFlags::ANONYMOUS,
+        usize::MAX,
+        0,
+    );
+    assert!(addr > 0);
+    let buf = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, 3 * PAGE_SIZE) };
+    assert!(buf.iter().all(|b| *b == 0));
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    assert_eq!(munmap(addr as usize + PAGE_SIZE, PAGE_SIZE), 0);
+    assert_eq!(buf[PAGE_SIZE - 1], (PAGE_SIZE - 1) as u8);
+    assert_eq!(buf[2 * PAGE_SIZE], (2 * PAGE_SIZE) as u8);
+
+    assert_eq!(mprotect(addr as usize, PAGE_SIZE, ProtFlags::READ), 0);
+    let pid = fork();
+    if pid == 0 {
+        buf[0] = 1;
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGSEGV as i32));
+    // the unmapped page in the middle is gone
+    assert_eq!(
+        mprotect(addr as usize, 3 * PAGE_SIZE, ProtFlags::READ),
+        Errno::ENOMEM.to_ret()
+    );
+    assert_eq!(munmap(addr as usize, 3 * PAGE_SIZE), 0);
+    println!("anonymous mmap passed!");
+}
+
+fn shared_anonymous() {
+    let addr = mmap(
+        0,
+        PAGE_SIZE,
+        ProtFlags::READ | ProtFlags::WRITE,
+        MapFlags::SHARED | MapFlags::ANONYMOUS,
+        usize::MAX,
+        0,
+    );
+    assert!(addr > 0);
+    let counter = addr as *mut usize;
+    let pid = fork();
+    if pid == 0 {
+        unsafe { counter.write_volatile(42) };
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(unsafe { counter.read_volatile() }, 42);
+    munmap(addr as usize, PAGE_SIZE);
+    println!("shared anonymous mmap passed!");
+}
+
+fn shared_file() {
+    let name = "mmapfile\0";
+    let fd = open(
+        name,
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+    );
+    assert!(fd > 0);
+    write(fd as usize, b"hello, mmap!");
+    close(fd as usize);
+
+    let fd = open(name, OpenFlags::RDWR);
+    assert!(fd > 0);
+    let addr = mmap(
+        0,
+        PAGE_SIZE,
+        ProtFlags::READ | ProtFlags::WRITE,
+        MapFlags::SHARED,
+        fd as usize,
+        0,
+    );
+    close(fd as usize);
+    assert!(addr > 0);
+    let buf = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, 12) };
+    assert_eq!(buf, b"hello, mmap!");
+    buf[..5].copy_from_slice(b"HELLO");
+    assert_eq!(munmap(addr as usize, PAGE_SIZE), 0);
+
+    let fd = open(name, OpenFlags::RDONLY);
+    let mut buffer = [0u8; 32];
+    let len = read(fd as usize, &mut buffer) as usize;
+    close(fd as usize);
+    assert_eq!(&buffer[..len], b"HELLO, mmap!");
+    println!("shared file mmap passed!");
+}
+
+/// The kernel stack of a task is mapped in its address space, but user
+/// mode may not touch it
+fn kernel_stack() {
+    let pid = fork();
+    if pid == 0 {
+        // the top of the kernel stack of task `pid`, see `KernelStack`
+        let top = usize::MAX - (16 * PAGE_SIZE + PAGE_SIZE) * getpid() as usize;
+        let word = unsafe { ((top - 8) as *const usize).read_volatile() };
+        exit(word as i32);
+    }
+    let mut exit_code = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGSEGV as i32));
+    println!("kernel stack access passed!");
+}
+
+/// The low 2 GiB hold the device registers, no mapping may reach there
+fn below_user_space() {
+    let uart = 0x1000_0000;
+    let addr = mmap(
+        uart,
+        PAGE_SIZE,
+        ProtFlags::READ | ProtFlags::WRITE,
+        MapFlags::PRIVATE | MapFlags::ANONYMOUS | MapFlags::FIXED,
+        usize::MAX,
+        0,
+    );
+    assert_eq!(addr, Errno::EINVAL.to_ret());
+    assert_eq!(munmap(uart, PAGE_SIZE), Errno::EINVAL.to_ret());
+    assert_eq!(
+        mprotect(uart, PAGE_SIZE, ProtFlags::READ),
+        Errno::EINVAL.to_ret()
+    );
+    println!("below user space passed!");
+}
+
+/// A length past the end of user space fails instead of wrapping around
+fn bad_lengths() {
+    for (len, errno) in [(0, Errno::EINVAL), (usize::MAX - PAGE_SIZE, Errno::ENOMEM)] {
+        let addr = mmap(
+            0,
+            len,
+            ProtFlags::READ,
+            MapFlags::PRIVATE | MapFlags::ANONYMOUS,
+            usize::MAX,
+            0,
+        );
+        assert_eq!(addr, errno.to_ret());
+    }
+    println!("bad lengths passed!");
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    anonymous();
+    shared_anonymous();
+    shared_file();
+    kernel_stack();
+    below_user_space();
+    bad_lengths();
+    println!("mmaptest passed!");
+    0
+}
@@ -0,0 +1,74 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+use user_lib::{
+    close, fork, pipe, read, sigaction, wait, write, Errno, SignalAction, SignalFlags, SIGPIPE,
+    SIG_IGN,
+};
+
+/// More than the pipe holds, so both ends have to wait for each other
+const LENGTH: usize = 3000;
+
+fn byte_at(i: usize) -> u8 {
+    b'a' + (i % 26) as u8
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let (read_end, write_end) = (pipe_fd[0], pipe_fd[1]);
+    if fork() == 0 {
+        // child reads until every write end is closed
+        close(write_end);
+        let mut received = Vec::new();
+        let mut buf = [0u8; 100];
+        loop {
+            let len = read(read_end, &mut buf);
+            assert!(len >= 0);
+            if len == 0 {
+                break;
+            }
+            received.extend_from_slice(&buf[..len as usize]);
+        }
+        close(read_end);
+        assert_eq!(received.len(), LENGTH);
+        assert!(received.iter().enumerate().all(|(i, &b)| b == byte_at(i)));
+        return 0;
+    }
+    close(read_end);
+    let data: Vec<u8> = (0..LENGTH).map(byte_at).collect();
+    let mut written = 0;
+    while written < LENGTH {
+        let len = write(write_end, &data[written..]);
+        assert!(len > 0);
+        written += len as usize;
+    }
+    close(write_end);
+    let mut exit_code = 0;
+    wait(&mut exit_code);
+    assert_eq!(exit_code, 0);
+
+    // writing to a pipe without readers kills the writer
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    close(pipe_fd[0]);
+    if fork() == 0 {
+        write(pipe_fd[1], b"lost");
+        return 0;
+    }
+    wait(&mut exit_code);
+    assert_eq!(exit_code, -(SIGPIPE as i32));
+    // or fails if it ignores SIGPIPE
+    let action = SignalAction::new(SIG_IGN, SignalFlags::empty());
+    assert_eq!(sigaction(SIGPIPE, Some(&action), None), 0);
+    assert_eq!(write(pipe_fd[1], b"lost"), Errno::EPIPE.to_ret());
+    close(pipe_fd[1]);
+    println!("pipetest passed!");
+    0
+}
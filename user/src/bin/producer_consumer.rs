@@ -0,0 +1,67 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    mutex_create, mutex_lock, mutex_unlock, semaphore_create, semaphore_down, semaphore_up,
+    thread_create, thread_exit, waittid,
+};
+
+const BUFFER_SIZE: usize = 4;
+const PRODUCERS: usize = 3;
+const ITEMS_PER_PRODUCER: usize = 50;
+
+/// Free slots of the buffer
+const SEM_EMPTY: usize = 0;
+/// Filled slots of the buffer
+const SEM_FULL: usize = 1;
+/// Guards the buffer and its ends
+const MUTEX: usize = 0;
+
+static BUFFER: [AtomicUsize; BUFFER_SIZE] = [const { AtomicUsize::new(0) }; BUFFER_SIZE];
+static HEAD: AtomicUsize = AtomicUsize::new(0);
+static TAIL: AtomicUsize = AtomicUsize::new(0);
+
+fn producer(id: usize) {
+    for i in 0..ITEMS_PER_PRODUCER {
+        semaphore_down(SEM_EMPTY);
+        mutex_lock(MUTEX);
+        let tail = TAIL.load(Ordering::Relaxed);
+        BUFFER[tail].store(id * ITEMS_PER_PRODUCER + i, Ordering::Relaxed);
+        TAIL.store((tail + 1) % BUFFER_SIZE, Ordering::Relaxed);
+        mutex_unlock(MUTEX);
+        semaphore_up(SEM_FULL);
+    }
+    thread_exit(0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(semaphore_create(BUFFER_SIZE), SEM_EMPTY as isize);
+    assert_eq!(semaphore_create(0), SEM_FULL as isize);
+    assert_eq!(mutex_create(), MUTEX as isize);
+    let tids: [isize; PRODUCERS] = core::array::from_fn(|id| thread_create(producer as usize, id));
+
+    // every item is consumed once
+    let mut seen = [false; PRODUCERS * ITEMS_PER_PRODUCER];
+    for _ in 0..seen.len() {
+        semaphore_down(SEM_FULL);
+        mutex_lock(MUTEX);
+        let head = HEAD.load(Ordering::Relaxed);
+        let item = BUFFER[head].load(Ordering::Relaxed);
+        HEAD.store((head + 1) % BUFFER_SIZE, Ordering::Relaxed);
+        mutex_unlock(MUTEX);
+        semaphore_up(SEM_EMPTY);
+        assert!(!seen[item]);
+        seen[item] = true;
+    }
+    for tid in tids {
+        assert!(tid > 0);
+        assert_eq!(waittid(tid as usize), 0);
+    }
+    println!("producer_consumer passed!");
+    0
+}
@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::vec::Vec;
+use user_lib::{brk, sbrk, Errno};
+
+const PAGE_SIZE: usize = 0x1000;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let bottom = sbrk(0);
+    assert!(bottom > 0);
+    let old = sbrk(PAGE_SIZE as isize * 2);
+    assert_eq!(old, bottom);
+    let heap = unsafe { core::slice::from_raw_parts_mut(old as *mut u8, PAGE_SIZE * 2) };
+    heap.fill(0x5a);
+    assert!(heap.iter().all(|b| *b == 0x5a));
+    assert_eq!(sbrk(-(PAGE_SIZE as isize)), bottom + PAGE_SIZE as isize * 2);
+    assert_eq!(brk(0), bottom + PAGE_SIZE as isize);
+    assert_eq!(brk(bottom as usize), bottom);
+    // the break cannot go below the heap bottom
+    assert_eq!(sbrk(-1), Errno::ENOMEM.to_ret());
+
+    // far more than the initial heap of user_lib
+    let mut v: Vec<usize> = Vec::new();
+    for i in 0..0x20000 {
+        v.push(i);
+    }
+    assert!(v.iter().enumerate().all(|(i, x)| i == *x));
+    println!("sbrk_test passed!");
+    0
+}
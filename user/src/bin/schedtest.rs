@@ -0,0 +1,91 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, exit, fork, get_time, getpid, pipe, read, sched_setscheduler, set_priority, wait, write,
+    Errno, SCHED_MLFQ, SCHED_RR, SCHED_STRIDE,
+};
+
+/// How long the workers count, in ms
+const RUN_TIME: isize = 500;
+
+/// Count for `RUN_TIME` with niceness `nice` in a child, which sends the
+/// count down `fd`
+fn worker(nice: isize, fd: usize) {
+    if fork() == 0 {
+        assert_eq!(set_priority(0, nice), 0);
+        let end = get_time() + RUN_TIME;
+        let mut count: usize = 0;
+        while get_time() < end {
+            count += 1;
+        }
+        write(fd, &count.to_ne_bytes());
+        exit(0);
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(set_priority(usize::MAX >> 1, 0), Errno::ESRCH.to_ret());
+    assert_eq!(sched_setscheduler(3), Errno::EINVAL.to_ret());
+
+    // only initproc switches policies, the one in force may be asked for
+    let mut policy = None;
+    for candidate in [SCHED_RR, SCHED_STRIDE, SCHED_MLFQ] {
+        match sched_setscheduler(candidate) {
+            ret if ret == Errno::EPERM.to_ret() => {}
+            ret => {
+                assert_eq!(ret, candidate as isize);
+                policy = Some(candidate);
+            }
+        }
+    }
+    let policy = policy.unwrap();
+
+    // a process may lower its own niceness down to 0, not that of another
+    let parent = getpid() as usize;
+    let mut exit_code = 0;
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(set_priority(0, 5), 0);
+        assert_eq!(set_priority(0, 0), 0);
+        assert_eq!(set_priority(0, -1), Errno::EPERM.to_ret());
+        assert_eq!(set_priority(parent, 5), 0);
+        assert_eq!(set_priority(parent, 0), Errno::EPERM.to_ret());
+        exit(7);
+    }
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 7);
+    assert_eq!(set_priority(0, 0), 0);
+
+    let mut read_fds = [0usize; 2];
+    for (fd, nice) in read_fds.iter_mut().zip([0, 10]) {
+        let mut pipe_fd = [0usize; 2];
+        assert_eq!(pipe(&mut pipe_fd), 0);
+        worker(nice, pipe_fd[1]);
+        close(pipe_fd[1]);
+        *fd = pipe_fd[0];
+    }
+    let mut counts = [0usize; 2];
+    for (count, fd) in counts.iter_mut().zip(read_fds) {
+        let mut buf = [0u8; 8];
+        assert_eq!(read(fd, &mut buf), 8);
+        close(fd);
+        *count = usize::from_ne_bytes(buf);
+    }
+    assert!(wait(&mut exit_code) > 0);
+    assert!(wait(&mut exit_code) > 0);
+    println!(
+        "nice 0 counted {}, nice 10 counted {}",
+        counts[0], counts[1]
+    );
+    // a less nice worker gets more of the CPU, if the policy heeds niceness
+    if policy == SCHED_STRIDE {
+        assert!(counts[0] > counts[1]);
+    }
+    println!("schedtest passed!");
+    0
+}
@@ -0,0 +1,66 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, lseek, open, pread, pwrite, read, unlink, write, Errno, OpenFlags, SEEK_CUR, SEEK_END,
+    SEEK_SET,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut buf = [0u8; 32];
+    let fd = open(
+        "seektest\0",
+        OpenFlags::CREATE | OpenFlags::RDWR | OpenFlags::TRUNC,
+    );
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(write(fd, b"0123456789"), 10);
+    // rewind without reopening
+    assert_eq!(lseek(fd, 0, SEEK_SET), 0);
+    assert_eq!(read(fd, &mut buf[..4]), 4);
+    assert_eq!(&buf[..4], b"0123");
+    assert_eq!(lseek(fd, 2, SEEK_CUR), 6);
+    assert_eq!(read(fd, &mut buf), 4);
+    assert_eq!(&buf[..4], b"6789");
+    assert_eq!(lseek(fd, -3, SEEK_END), 7);
+    assert_eq!(write(fd, b"abc"), 3);
+    assert!(lseek(fd, -11, SEEK_END) < 0);
+    assert!(lseek(1, 0, SEEK_SET) < 0);
+
+    // positional reads and writes leave the offset alone
+    assert_eq!(pwrite(fd, b"xy", 1), 2);
+    assert_eq!(pread(fd, &mut buf[..4], 0), 4);
+    assert_eq!(&buf[..4], b"0xy3");
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 10);
+
+    // no offset goes past the max file size
+    assert_eq!(lseek(fd, isize::MAX, SEEK_SET), Errno::EINVAL.to_ret());
+    assert_eq!(lseek(fd, isize::MAX, SEEK_END), Errno::EINVAL.to_ret());
+    assert_eq!(pwrite(fd, b"x", usize::MAX), Errno::EFBIG.to_ret());
+    assert_eq!(pread(fd, &mut buf, usize::MAX), 0);
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 10);
+    close(fd);
+
+    // every write goes to the end, creating an existing file keeps it
+    let fd = open(
+        "seektest\0",
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::APPEND,
+    );
+    assert!(fd > 0);
+    let fd = fd as usize;
+    lseek(fd, 0, SEEK_SET);
+    assert_eq!(write(fd, b"!"), 1);
+    assert_eq!(lseek(fd, 0, SEEK_CUR), 11);
+    close(fd);
+    let fd = open("seektest\0", OpenFlags::RDONLY) as usize;
+    let len = read(fd, &mut buf) as usize;
+    assert_eq!(&buf[..len], b"0xy3456abc!");
+    close(fd);
+    assert_eq!(unlink("seektest\0"), 0);
+    println!("seektest passed!");
+    0
+}
@@ -0,0 +1,187 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    close, exit, fork, getpid, kill, mmap, pipe, read, sigaction, sigprocmask, sleep,
+    thread_create, thread_exit, wait, waittid, Errno, MapFlags, ProtFlags, SignalAction,
+    SignalFlags, SIGCONT, SIGILL, SIGKILL, SIGSEGV, SIGSTOP, SIGTERM, SIGUSR1, SIGUSR2, SIG_BLOCK,
+    SIG_IGN, SIG_UNBLOCK,
+};
+
+/// Signals handled so far, one bit per signal
+static HANDLED: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn handler(signum: usize) {
+    HANDLED.fetch_or(1 << signum, Ordering::SeqCst);
+}
+
+/// Sends SIGUSR1 to itself, which has to wait for the handler to return
+extern "C" fn nested_handler(signum: usize) {
+    kill(getpid(), SIGUSR1);
+    assert_eq!(HANDLED.load(Ordering::SeqCst), 0);
+    handler(signum);
+}
+
+extern "C" fn segv_handler(_signum: usize) {
+    exit(77);
+}
+
+fn take_handled() -> usize {
+    HANDLED.swap(0, Ordering::SeqCst)
+}
+
+/// Runs `f` in a child and returns its exit code
+fn exit_code_of(f: fn()) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        f();
+        exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    exit_code
+}
+
+fn handlers() {
+    let pid = getpid();
+    let action = SignalAction::new(handler as usize, SignalFlags::empty());
+    assert_eq!(sigaction(SIGUSR1, Some(&action), None), 0);
+    // the handler runs before kill returns, which still returns 0
+    assert_eq!(kill(pid, SIGUSR1), 0);
+    assert_eq!(take_handled(), 1 << SIGUSR1);
+
+    // blocked signals wait until they are unblocked
+    assert_eq!(sigprocmask(SIG_BLOCK, Some(SignalFlags::SIGUSR1), None), 0);
+    kill(pid, SIGUSR1);
+    assert_eq!(take_handled(), 0);
+    let mut old_set = SignalFlags::empty();
+    sigprocmask(SIG_UNBLOCK, Some(SignalFlags::SIGUSR1), Some(&mut old_set));
+    assert_eq!(old_set, SignalFlags::SIGUSR1);
+    assert_eq!(take_handled(), 1 << SIGUSR1);
+
+    // the mask of an action is blocked while its handler runs
+    let action = SignalAction::new(nested_handler as usize, SignalFlags::SIGUSR1);
+    sigaction(SIGUSR2, Some(&action), None);
+    kill(pid, SIGUSR2);
+    assert_eq!(take_handled(), 1 << SIGUSR1 | 1 << SIGUSR2);
+
+    let action = SignalAction::new(SIG_IGN, SignalFlags::empty());
+    let mut old_action = SignalAction::default();
+    sigaction(SIGUSR2, Some(&action), Some(&mut old_action));
+    assert_eq!(old_action.handler, nested_handler as usize);
+    kill(pid, SIGUSR2);
+    assert_eq!(take_handled(), 0);
+
+    // SIGKILL can't be caught
+    assert_eq!(
+        sigaction(SIGKILL, Some(&action), None),
+        Errno::EINVAL.to_ret()
+    );
+    assert_eq!(kill(pid, 0), 0);
+    assert_eq!(kill(pid, 64), Errno::EINVAL.to_ret());
+    println!("signal handlers passed!");
+}
+
+fn default_actions() {
+    assert_eq!(
+        exit_code_of(|| unsafe { (0 as *mut u8).write_volatile(0) }),
+        -(SIGSEGV as i32)
+    );
+    assert_eq!(
+        exit_code_of(|| unsafe { core::arch::asm!("unimp") }),
+        -(SIGILL as i32)
+    );
+    // faults can be caught as well
+    assert_eq!(
+        exit_code_of(|| {
+            let action = SignalAction::new(segv_handler as usize, SignalFlags::empty());
+            sigaction(SIGSEGV, Some(&action), None);
+            unsafe { (0 as *mut u8).write_volatile(0) };
+        }),
+        77
+    );
+
+    // a blocked read is interrupted
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    let pid = fork();
+    if pid == 0 {
+        let mut buf = [0u8; 1];
+        read(pipe_fd[0], &mut buf);
+        exit(0);
+    }
+    sleep(50);
+    assert_eq!(kill(pid, SIGTERM), 0);
+    let mut exit_code = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGTERM as i32));
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    println!("default actions passed!");
+}
+
+fn stop_and_continue() {
+    let addr = mmap(
+        0,
+        0x1000,
+        ProtFlags::READ | ProtFlags::WRITE,
+        MapFlags::SHARED | MapFlags::ANONYMOUS,
+        usize::MAX,
+        0,
+    );
+    assert!(addr > 0);
+    let counter = unsafe { &*(addr as *const AtomicUsize) };
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+    sleep(50);
+    kill(pid, SIGSTOP);
+    sleep(50);
+    let stopped = counter.load(Ordering::SeqCst);
+    assert!(stopped > 0);
+    sleep(50);
+    assert_eq!(counter.load(Ordering::SeqCst), stopped);
+    kill(pid, SIGCONT);
+    sleep(50);
+    assert!(counter.load(Ordering::SeqCst) > stopped);
+
+    kill(pid, SIGKILL);
+    let mut exit_code = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGKILL as i32));
+    println!("stop and continue passed!");
+}
+
+fn install_handler(signum: usize) {
+    let action = SignalAction::new(handler as usize, SignalFlags::empty());
+    assert_eq!(sigaction(signum, Some(&action), None), 0);
+    thread_exit(0);
+}
+
+/// The actions belong to the process, a thread sets them for all
+fn thread_actions() {
+    let tid = thread_create(install_handler as usize, SIGUSR2);
+    assert!(tid > 0);
+    assert_eq!(waittid(tid as usize), 0);
+    assert_eq!(kill(getpid(), SIGUSR2), 0);
+    assert_eq!(take_handled(), 1 << SIGUSR2);
+    let action = SignalAction::default();
+    assert_eq!(sigaction(SIGUSR2, Some(&action), None), 0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    handlers();
+    default_actions();
+    stop_and_continue();
+    thread_actions();
+    println!("sigtest passed!");
+    0
+}
@@ -0,0 +1,105 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    clock_nanosleep, close, exit, fork, get_time, getpid, kill, nanosleep, pipe, read, sigaction,
+    sleep, wait, write, Errno, SignalAction, SignalFlags, TimeSpec, CLOCK_MONOTONIC, SIGUSR1,
+    TIMER_ABSTIME,
+};
+
+/// Sleepers wake in the order of their deadlines
+fn wake_order() {
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    let periods = [60, 20, 40];
+    for (i, period) in periods.iter().enumerate() {
+        if fork() == 0 {
+            close(pipe_fd[0]);
+            sleep(*period);
+            write(pipe_fd[1], &[i as u8]);
+            exit(0);
+        }
+    }
+    close(pipe_fd[1]);
+    let mut order = [0u8; 3];
+    for byte in order.iter_mut() {
+        assert_eq!(read(pipe_fd[0], core::slice::from_mut(byte)), 1);
+    }
+    assert_eq!(order, [1, 2, 0]);
+    close(pipe_fd[0]);
+    for _ in periods {
+        let mut exit_code = 0;
+        assert!(wait(&mut exit_code) > 0);
+        assert_eq!(exit_code, 0);
+    }
+}
+
+extern "C" fn handler(_signum: usize) {}
+
+/// A signal handler ends a sleep early, with the time left in `rem`
+fn interrupted() {
+    let action = SignalAction::new(handler as usize, SignalFlags::empty());
+    assert_eq!(sigaction(SIGUSR1, Some(&action), None), 0);
+    let pid = getpid();
+    if fork() == 0 {
+        sleep(50);
+        kill(pid as isize, SIGUSR1);
+        exit(0);
+    }
+    let req = TimeSpec::from_ms(10_000);
+    let mut rem = TimeSpec::default();
+    assert_eq!(nanosleep(&req, Some(&mut rem)), Errno::EINTR.to_ret());
+    assert!(rem.sec > 0 && rem.sec < req.sec);
+    let mut exit_code = 0;
+    assert!(wait(&mut exit_code) > 0);
+    assert_eq!(exit_code, 0);
+    let action = SignalAction::default();
+    assert_eq!(sigaction(SIGUSR1, Some(&action), None), 0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let start = get_time();
+    sleep(100);
+    let elapsed = get_time() - start;
+    assert!(elapsed >= 100, "woke up after {}ms", elapsed);
+
+    let start = get_time();
+    let req = TimeSpec {
+        sec: 0,
+        nsec: 50_000_000,
+    };
+    assert_eq!(nanosleep(&req, None), 0);
+    assert!(get_time() - start >= 50);
+
+    let deadline = get_time() as usize + 50;
+    let req = TimeSpec::from_ms(deadline);
+    assert_eq!(
+        clock_nanosleep(CLOCK_MONOTONIC, TIMER_ABSTIME, &req, None),
+        0
+    );
+    assert!(get_time() as usize >= deadline);
+    // a deadline that has passed returns right away
+    assert_eq!(
+        clock_nanosleep(CLOCK_MONOTONIC, TIMER_ABSTIME, &req, None),
+        0
+    );
+
+    let req = TimeSpec {
+        sec: 0,
+        nsec: 1_000_000_000,
+    };
+    assert_eq!(nanosleep(&req, None), Errno::EINVAL.to_ret());
+    assert_eq!(
+        clock_nanosleep(7, 0, &TimeSpec::default(), None),
+        Errno::EINVAL.to_ret()
+    );
+
+    wake_order();
+    interrupted();
+    println!("sleep passed!");
+    0
+}
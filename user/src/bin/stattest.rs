@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{close, fstat, link, mkdir, open, rmdir, stat, unlink, write, OpenFlags, Stat};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let test_str = "Hello, stat!";
+    let mut st = Stat::default();
+    let fd = open(
+        "stattest_a\0",
+        OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+    );
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(fstat(fd, &mut st), 0);
+    assert!(!st.is_dir());
+    assert_eq!(st.mode & Stat::S_IFMT, Stat::S_IFREG);
+    assert_eq!((st.nlink, st.size), (1, 0));
+    let created = st;
+    write(fd, test_str.as_bytes());
+    assert_eq!(fstat(fd, &mut st), 0);
+    assert_eq!(st.ino, created.ino);
+    assert_eq!(st.size, test_str.len() as u64);
+    assert!(st.mtime >= created.mtime);
+    close(fd);
+    // stdout is not a file in the filesystem
+    assert!(fstat(1, &mut st) < 0);
+
+    assert_eq!(link("stattest_a\0", "stattest_b\0"), 0);
+    assert_eq!(stat("stattest_b\0", &mut st), 0);
+    assert_eq!((st.ino, st.nlink), (created.ino, 2));
+    assert_eq!(unlink("stattest_a\0"), 0);
+    assert_eq!(unlink("stattest_b\0"), 0);
+    assert!(stat("stattest_b\0", &mut st) < 0);
+
+    assert_eq!(mkdir("stattest_dir\0"), 0);
+    assert_eq!(stat("stattest_dir\0", &mut st), 0);
+    assert!(st.is_dir());
+    assert_eq!(st.nlink, 2);
+    assert_eq!(rmdir("stattest_dir\0"), 0);
+    println!("stattest passed!");
+    0
+}
@@ -0,0 +1,44 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    close, pipe, read, tcgetattr, tcsetattr, Errno, LocalFlags, Termios, VEOF, VERASE, VINTR, VMIN,
+};
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut saved = Termios::default();
+    assert_eq!(tcgetattr(0, &mut saved), 0);
+    assert!(saved
+        .lflag
+        .contains(LocalFlags::ICANON | LocalFlags::ECHO | LocalFlags::ISIG));
+    assert_eq!(saved.cc[VINTR], 0x03);
+    assert_eq!(saved.cc[VEOF], 0x04);
+    assert_eq!(saved.cc[VERASE], 0x7f);
+
+    let mut raw = saved;
+    raw.make_raw();
+    raw.cc[VMIN] = 0;
+    assert_eq!(tcsetattr(0, &raw), 0);
+    let mut current = Termios::default();
+    tcgetattr(0, &mut current);
+    assert_eq!(current, raw);
+    // nothing has been typed and a raw read with VMIN 0 does not wait
+    let mut buf = [0u8; 16];
+    assert_eq!(read(0, &mut buf), 0);
+    assert_eq!(tcsetattr(0, &saved), 0);
+    tcgetattr(0, &mut current);
+    assert_eq!(current, saved);
+
+    // a pipe is not a terminal
+    let mut pipe_fd = [0usize; 2];
+    pipe(&mut pipe_fd);
+    assert_eq!(tcgetattr(pipe_fd[0], &mut current), Errno::ENOTTY.to_ret());
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+    println!("termiostest passed!");
+    0
+}
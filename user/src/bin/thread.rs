@@ -1,7 +1,9 @@
 #![no_std]
 #![no_main]
 
-use user_lib::{exit, mutex_create, mutex_lock, mutex_unlock, sleep, thread_create, waittid};
+use user_lib::{
+    mutex_create, mutex_lock, mutex_unlock, sleep, thread_create, thread_exit, waittid,
+};
 
 #[macro_use]
 extern crate user_lib;
@@ -19,7 +21,7 @@ fn func(tid: usize) {
         }
     };
 
-    exit(0);
+    thread_exit(0);
 }
 
 #[no_mangle]
@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{sleep, thread_create, thread_detach, thread_exit, waittid, yield_, Errno};
+
+/// Threads created in all
+const THREADS: usize = 4000;
+/// Threads alive at once
+const BATCH: usize = 16;
+
+fn worker(arg: usize) {
+    yield_();
+    thread_exit(arg as i32);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    for round in 0..THREADS / BATCH {
+        let mut tids = [0usize; BATCH];
+        for (i, tid) in tids.iter_mut().enumerate() {
+            let ret = thread_create(worker as usize, round * BATCH + i);
+            assert!(ret > 0);
+            *tid = ret as usize;
+        }
+        // half of them are released on exit, the others keep their code
+        for (i, &tid) in tids.iter().enumerate() {
+            if i % 2 == 1 {
+                assert_eq!(thread_detach(tid), 0);
+            } else {
+                assert_eq!(waittid(tid), (round * BATCH + i) as isize);
+            }
+        }
+    }
+    // a detached thread is released when it exits, not when the next one
+    // is created
+    let tid = thread_create(worker as usize, 0) as usize;
+    assert_eq!(thread_detach(tid), 0);
+    sleep(10);
+    assert_eq!(waittid(tid), Errno::ESRCH.to_ret());
+    println!("threadstress passed!");
+    0
+}
@@ -0,0 +1,66 @@
+#![no_std]
+#![no_main]
+#![feature(thread_local)]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::cell::Cell;
+use user_lib::{
+    close, dup3, errno, fork, thread_create, thread_exit, wait, waittid, yield_, Errno, OpenFlags,
+};
+
+/// Starts out in `.tdata`
+#[thread_local]
+static COUNT: Cell<usize> = Cell::new(7);
+/// Starts out in `.tbss`
+#[thread_local]
+static TOUCHED: Cell<usize> = Cell::new(0);
+
+fn worker(arg: usize) {
+    assert_eq!(COUNT.get(), 7);
+    assert_eq!(TOUCHED.get(), 0);
+    for _ in 0..100 {
+        COUNT.set(COUNT.get() + arg);
+        TOUCHED.set(TOUCHED.get() + 1);
+        yield_();
+    }
+    assert_eq!(TOUCHED.get(), 100);
+    // errno is thread-local too
+    assert!(close(usize::MAX) < 0);
+    assert_eq!(errno(), Some(Errno::EBADF));
+    thread_exit(COUNT.get() as i32);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(COUNT.get(), 7);
+    COUNT.set(1);
+    assert!(dup3(0, 0, OpenFlags::empty()) < 0);
+    assert_eq!(errno(), Some(Errno::EINVAL));
+    let mut tids = [0usize; 4];
+    for (i, tid) in tids.iter_mut().enumerate() {
+        let ret = thread_create(worker as usize, i + 1);
+        assert!(ret > 0);
+        *tid = ret as usize;
+    }
+    // every thread counted in its own copy
+    for (i, &tid) in tids.iter().enumerate() {
+        assert_eq!(waittid(tid), (7 + 100 * (i + 1)) as isize);
+    }
+    assert_eq!(COUNT.get(), 1);
+    assert_eq!(TOUCHED.get(), 0);
+    assert_eq!(errno(), Some(Errno::EINVAL));
+
+    // a forked child keeps the values of its parent
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(COUNT.get(), 1);
+        return 0;
+    }
+    let mut exit_code = 0;
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+    println!("tlstest passed!");
+    0
+}
@@ -7,56 +7,374 @@ extern crate alloc;
 #[macro_use]
 extern crate user_lib;
 
-const LF: u8 = 0x0au8;
-const CR: u8 = 0x0du8;
-const DL: u8 = 0x7fu8;
-const BS: u8 = 0x08u8;
-
 use alloc::string::String;
-use user_lib::console::getchar;
-use user_lib::{spawn, waitpid};
+use alloc::vec::Vec;
+use user_lib::{
+    chdir, close, dup2, exec, exit, fork, getpid, kill, open, pipe, read, set_priority, setpgid,
+    sigaction, stop_signal, tcgetattr, tcsetattr, tcsetpgrp, waitpid_with, OpenFlags, SignalAction,
+    SignalFlags, Termios, WaitFlags, SIGCONT, SIGINT, SIGQUIT, SIGTSTP, SIGTTIN, SIGTTOU, SIG_DFL,
+    SIG_IGN,
+};
 
-#[no_mangle]
-pub fn main() -> i32 {
-    println!("Rust user shell");
-    let mut line: String = String::new();
-    print!(">> ");
-    loop {
-        let c = getchar();
-        match c {
-            LF | CR => {
+/// Niceness of `nice` without `-n`
+const DEFAULT_NICE: isize = 10;
+
+/// One stage of a pipeline, every string ends with `\0`
+struct Command {
+    args: Vec<String>,
+    input: Option<String>,
+    output: Option<String>,
+    /// Niceness to run with
+    nice: Option<isize>,
+}
+
+impl Command {
+    /// Parse `[nice [-n N]] cmd args.. [< input] [> output]`, None on a
+    /// syntax error
+    fn parse(s: &str) -> Option<Self> {
+        let mut words = s
+            .split_whitespace()
+            .map(|word| {
+                let mut word = String::from(word);
+                word.push('\0');
+                word
+            })
+            .peekable();
+        let mut command = Self {
+            args: Vec::new(),
+            input: None,
+            output: None,
+            nice: None,
+        };
+        if words.next_if_eq("nice\0").is_some() {
+            let nice = match words.next_if_eq("-n\0") {
+                Some(_) => words.next()?.trim_end_matches('\0').parse().ok()?,
+                None => DEFAULT_NICE,
+            };
+            command.nice = Some(nice);
+        }
+        while let Some(word) = words.next() {
+            match word.as_str() {
+                "<\0" => command.input = Some(words.next()?),
+                ">\0" => command.output = Some(words.next()?),
+                _ => command.args.push(word),
+            }
+        }
+        if command.args.is_empty() {
+            None
+        } else {
+            Some(command)
+        }
+    }
+}
+
+/// Make `fd` refer to the file at `path` in a forked child
+fn redirect(path: &str, flags: OpenFlags, fd: usize) {
+    let file = open(path, flags);
+    if file < 0 {
+        println!("Shell: cannot open {}", path.trim_end_matches('\0'));
+        exit(-4);
+    }
+    dup2(file as usize, fd);
+    close(file as usize);
+}
+
+/// Signals the shell ignores but the jobs it runs must not
+const JOB_CONTROL_SIGNALS: [usize; 5] = [SIGINT, SIGQUIT, SIGTSTP, SIGTTIN, SIGTTOU];
+
+/// Set the action of the job control signals to `handler`
+fn set_job_control_signals(handler: usize) {
+    let action = SignalAction::new(handler, SignalFlags::empty());
+    for signum in JOB_CONTROL_SIGNALS {
+        sigaction(signum, Some(&action), None);
+    }
+}
+
+/// A pipeline running in its own process group
+struct Job {
+    id: usize,
+    pgid: usize,
+    /// Processes that have not exited yet
+    pids: Vec<isize>,
+    command: String,
+    stopped: bool,
+}
+
+impl Job {
+    /// Continue the job if it is stopped
+    fn resume(&mut self) {
+        if self.stopped {
+            kill(-(self.pgid as isize), SIGCONT);
+            self.stopped = false;
+        }
+    }
+
+    /// Wait until every process has exited or one is stopped, with the job
+    /// in the foreground of the terminal. The terminal gets `termios` back
+    /// afterwards, whatever mode the job left it in.
+    fn wait_foreground(&mut self, termios: &Termios) {
+        tcsetpgrp(0, self.pgid);
+        while let Some(&pid) = self.pids.first() {
+            let mut exit_code: i32 = 0;
+            let exit_pid = waitpid_with(pid, &mut exit_code, WaitFlags::UNTRACED);
+            assert_eq!(pid, exit_pid);
+            if stop_signal(exit_code).is_some() {
+                self.stopped = true;
                 println!("");
-                while !line.is_empty() {
-                    line.push('\0');
-                    let pid = spawn(line.as_str());
-                    if pid < 0 {
-                        println!("Shell: {} not found", line);
-                        line.clear();
-                        break;
+                println!("[{}] Stopped {}", self.id, self.command);
+                break;
+            }
+            println!("Shell: Process {} exited with code {}", pid, exit_code);
+            self.pids.remove(0);
+        }
+        tcsetpgrp(0, getpid() as usize);
+        tcsetattr(0, termios);
+    }
+
+    /// Reap the processes that have exited without waiting, true if all
+    /// of them have
+    fn reap(&mut self) -> bool {
+        let mut i = 0;
+        while i < self.pids.len() {
+            let mut exit_code: i32 = 0;
+            let options = WaitFlags::NOHANG | WaitFlags::UNTRACED;
+            match waitpid_with(self.pids[i], &mut exit_code, options) {
+                0 => i += 1,
+                _ if stop_signal(exit_code).is_some() => {
+                    if !self.stopped {
+                        self.stopped = true;
+                        println!("[{}] Stopped {}", self.id, self.command);
                     }
+                    i += 1;
+                }
+                _ => {
+                    self.pids.remove(i);
+                }
+            }
+        }
+        self.pids.is_empty()
+    }
+}
 
-                    println!("Shell: Process {} created", pid);
-                    let mut exit_code: i32 = 0;
-                    let exit_pid = waitpid(pid as usize, &mut exit_code);
-                    assert_eq!(pid, exit_pid);
-                    println!("Shell: Process {} exited with code {}", pid, exit_code);
+/// Fork a child per command with the stdout of each connected to the stdin
+/// of the next one, all in a new process group
+fn run_pipeline(commands: &[Command], line: &str) -> Job {
+    let mut pids = Vec::new();
+    // the first process leads the group
+    let mut pgid = 0;
+    // read end of the pipe from the previous command
+    let mut prev_read: Option<usize> = None;
+    for (i, command) in commands.iter().enumerate() {
+        let mut pipe_fd = [0usize; 2];
+        let piped = i + 1 < commands.len();
+        if piped {
+            assert_eq!(pipe(&mut pipe_fd), 0);
+        }
+        let pid = fork();
+        if pid == 0 {
+            setpgid(0, pgid);
+            set_job_control_signals(SIG_DFL);
+            if let Some(fd) = prev_read {
+                dup2(fd, 0);
+                close(fd);
+            }
+            if piped {
+                dup2(pipe_fd[1], 1);
+                close(pipe_fd[0]);
+                close(pipe_fd[1]);
+            }
+            if let Some(path) = &command.input {
+                redirect(path, OpenFlags::RDONLY, 0);
+            }
+            if let Some(path) = &command.output {
+                redirect(
+                    path,
+                    OpenFlags::CREATE | OpenFlags::WRONLY | OpenFlags::TRUNC,
+                    1,
+                );
+            }
+            if let Some(nice) = command.nice {
+                set_priority(0, nice);
+            }
+            let mut args_addr: Vec<*const u8> =
+                command.args.iter().map(|arg| arg.as_ptr()).collect();
+            args_addr.push(core::ptr::null::<u8>());
+            exec(
+                command.args[0].as_str(),
+                args_addr.as_slice(),
+                &[core::ptr::null::<u8>()],
+            );
+            println!(
+                "Shell: {} not found",
+                command.args[0].trim_end_matches('\0')
+            );
+            exit(-4);
+        }
+        // set it here as well, whichever of the two runs first
+        if pgid == 0 {
+            pgid = pid as usize;
+        }
+        setpgid(pid as usize, pgid);
+        if let Some(fd) = prev_read.take() {
+            close(fd);
+        }
+        if piped {
+            close(pipe_fd[1]);
+            prev_read = Some(pipe_fd[0]);
+        }
+        println!("Shell: Process {} created", pid);
+        pids.push(pid);
+    }
+    Job {
+        id: 0,
+        pgid,
+        pids,
+        command: String::from(line.trim()),
+        stopped: false,
+    }
+}
+
+/// The jobs that have not finished yet
+struct Jobs {
+    jobs: Vec<Job>,
+    /// Settings of the terminal for the shell
+    termios: Termios,
+}
+
+impl Jobs {
+    /// The job `arg` names, the latest one if there is no `arg`
+    fn take(&mut self, arg: Option<&String>) -> Option<Job> {
+        let index = match arg {
+            None => self.jobs.len().checked_sub(1)?,
+            Some(arg) => {
+                let arg = arg.trim_end_matches('\0').trim_start_matches('%');
+                let id = arg.parse::<usize>().ok()?;
+                self.jobs.iter().position(|job| job.id == id)?
+            }
+        };
+        Some(self.jobs.remove(index))
+    }
+
+    /// Forget the jobs that have finished
+    fn reap(&mut self) {
+        self.jobs.retain_mut(|job| {
+            let done = job.reap();
+            if done {
+                println!("[{}] Done {}", job.id, job.command);
+            }
+            !done
+        });
+    }
+
+    /// Run `commands`, in the background if `background`
+    fn run(&mut self, commands: &[Command], line: &str, background: bool) {
+        let mut job = run_pipeline(commands, line);
+        job.id = self.jobs.last().map_or(1, |last| last.id + 1);
+        if background {
+            println!("[{}] {}", job.id, job.pgid);
+        } else {
+            job.wait_foreground(&self.termios);
+        }
+        if !job.pids.is_empty() {
+            self.jobs.push(job);
+        }
+    }
 
-                    line.clear();
+    /// The builtins `jobs`, `fg` and `bg`, false if `args` is none of them
+    fn builtin(&mut self, args: &[String]) -> bool {
+        match args[0].as_str() {
+            "jobs\0" => {
+                for job in self.jobs.iter() {
+                    let state = if job.stopped { "Stopped" } else { "Running" };
+                    println!("[{}] {} {}", job.id, state, job.command);
                 }
-                print!(">> ");
-            }
-            BS | DL => {
-                if !line.is_empty() {
-                    print!("{}", BS as char);
-                    print!(" ");
-                    print!("{}", BS as char);
-                    line.pop();
+            }
+            "fg\0" | "bg\0" => {
+                let Some(mut job) = self.take(args.get(1)) else {
+                    println!("Shell: {}: no such job", args[0].trim_end_matches('\0'));
+                    return true;
+                };
+                println!("{}", job.command);
+                if args[0] == "fg\0" {
+                    // it must be in the foreground before it reads again
+                    tcsetpgrp(0, job.pgid);
+                    job.resume();
+                    job.wait_foreground(&self.termios);
+                } else {
+                    job.resume();
+                }
+                if !job.pids.is_empty() {
+                    let position = self.jobs.partition_point(|other| other.id < job.id);
+                    self.jobs.insert(position, job);
                 }
             }
-            _ => {
-                print!("{}", c as char);
-                line.push(c as char);
+            _ => return false,
+        }
+        true
+    }
+}
+
+/// Start a new prompt, the terminal has dropped the line being typed
+extern "C" fn interrupted(_signum: usize) {
+    print!("\n>> ");
+}
+
+/// Read a line into `line`, false at the end of input
+fn read_line(line: &mut String) -> bool {
+    let mut buf = [0u8; 128];
+    // a line the terminal reads is ended by a newline, unless it is longer
+    // than the buffer or ended by Ctrl-D
+    while !line.ends_with('\n') {
+        let len = read(0, &mut buf);
+        if len <= 0 {
+            return !line.is_empty();
+        }
+        line.push_str(core::str::from_utf8(&buf[..len as usize]).unwrap_or(""));
+    }
+    true
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    println!("Rust user shell");
+    // lead a group of its own which owns the terminal between jobs
+    setpgid(0, 0);
+    tcsetpgrp(0, getpid() as usize);
+    set_job_control_signals(SIG_IGN);
+    let action = SignalAction::new(interrupted as usize, SignalFlags::empty());
+    sigaction(SIGINT, Some(&action), None);
+    let mut jobs = Jobs {
+        jobs: Vec::new(),
+        termios: Termios::default(),
+    };
+    tcgetattr(0, &mut jobs.termios);
+    let mut line: String = String::new();
+    loop {
+        print!(">> ");
+        line.clear();
+        if !read_line(&mut line) {
+            // the end of input is not the end of the shell
+            println!("");
+            continue;
+        }
+        let background = line.trim_end().ends_with('&');
+        let command_line = line.trim_end().trim_end_matches('&');
+        if !command_line.trim().is_empty() {
+            let commands: Option<Vec<Command>> =
+                command_line.split('|').map(Command::parse).collect();
+            match commands {
+                None => println!("Shell: syntax error"),
+                Some(commands) if commands[0].args[0] == "cd\0" => {
+                    let args = &commands[0].args;
+                    let path = args.get(1).map_or("/\0", |path| path.as_str());
+                    if chdir(path) != 0 {
+                        println!("Shell: cd: {} not a directory", path.trim_end_matches('\0'));
+                    }
+                }
+                Some(commands) if jobs.builtin(&commands[0].args) => {}
+                Some(commands) => jobs.run(&commands, command_line, background),
             }
         }
+        jobs.reap();
     }
 }
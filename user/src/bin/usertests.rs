@@ -5,12 +5,29 @@
 extern crate user_lib;
 
 // not in SUCC_TESTS & FAIL_TESTS
-// count_lines, infloop, user_shell, usertests
+// count_lines, user_shell, usertests
 
 // item of TESTS : app_name(argv_0), argv_1, argv_2, argv_3, exit_code
 static SUCC_TESTS: &[(&str, &str, &str, &str, i32)] = &[
     ("filetest_simple\0", "\0", "\0", "\0", 0),
     ("cat_filea\0", "\0", "\0", "\0", 0),
+    ("dirtest\0", "\0", "\0", "\0", 0),
+    ("linktest\0", "\0", "\0", "\0", 0),
+    ("stattest\0", "\0", "\0", "\0", 0),
+    ("efaulttest\0", "\0", "\0", "\0", 0),
+    ("seektest\0", "\0", "\0", "\0", 0),
+    ("pipetest\0", "\0", "\0", "\0", 0),
+    ("duptest\0", "\0", "\0", "\0", 0),
+    ("sigtest\0", "\0", "\0", "\0", 0),
+    ("exittest\0", "\0", "\0", "\0", 0),
+    ("waittest\0", "\0", "\0", "\0", 0),
+    ("threadstress\0", "\0", "\0", "\0", 0),
+    ("tlstest\0", "\0", "\0", "\0", 0),
+    ("schedtest\0", "\0", "\0", "\0", 0),
+    ("jobtest\0", "\0", "\0", "\0", 0),
+    ("termiostest\0", "\0", "\0", "\0", 0),
+    ("cmdline_args\0", "1\0", "2\0", "3\0", 0),
+    ("exectest\0", "\0", "\0", "\0", 0),
     ("exit\0", "\0", "\0", "\0", 0),
     ("fantastic_text\0", "\0", "\0", "\0", 0),
     ("forktest_simple\0", "\0", "\0", "\0", 0),
@@ -20,18 +37,30 @@ static SUCC_TESTS: &[(&str, &str, &str, &str, i32)] = &[
     ("hello_world\0", "\0", "\0", "\0", 0),
     ("huge_write\0", "\0", "\0", "\0", 0),
     ("matrix\0", "\0", "\0", "\0", 0),
+    ("producer_consumer\0", "\0", "\0", "\0", 0),
+    ("barrier\0", "\0", "\0", "\0", 0),
+    ("futextest\0", "\0", "\0", "\0", 0),
+    ("mmaptest\0", "\0", "\0", "\0", 0),
+    ("sbrk_test\0", "\0", "\0", "\0", 0),
     ("sleep_simple\0", "\0", "\0", "\0", 0),
     ("sleep\0", "\0", "\0", "\0", 0),
     ("yield\0", "\0", "\0", "\0", 0),
 ];
 
-static FAIL_TESTS: &[(&str, &str, &str, &str, i32)] = &[("stack_overflow\0", "\0", "\0", "\0", -2)];
+static FAIL_TESTS: &[(&str, &str, &str, &str, i32)] = &[
+    ("stack_overflow\0", "\0", "\0", "\0", -(SIGSEGV as i32)),
+    ("infloop\0", "\0", "\0", "\0", -(SIGKILL as i32)),
+];
+
+/// A test still running after this long is killed
+const TIMEOUT_MS: usize = 10000;
 
-use user_lib::{spawn, waitpid};
+use user_lib::{exec, exit, fork, kill, sleep, waitpid, SIGKILL, SIGSEGV};
 
 fn run_tests(tests: &[(&str, &str, &str, &str, i32)]) -> i32 {
     let mut pass_num = 0;
-    let mut arr: [*const u8; 4] = [
+    let mut arr: [*const u8; 5] = [
+        core::ptr::null::<u8>(),
         core::ptr::null::<u8>(),
         core::ptr::null::<u8>(),
         core::ptr::null::<u8>(),
@@ -63,11 +92,27 @@ fn run_tests(tests: &[(&str, &str, &str, &str, i32)]) -> i32 {
             arr[3] = core::ptr::null::<u8>();
         }
 
-        let pid = spawn(test.0);
+        let pid = fork();
+        if pid == 0 {
+            exec(test.0, &arr[..], &[core::ptr::null::<u8>()]);
+            println!("Usertests: cannot execute {}", test.0);
+            exit(-4);
+        }
+
+        let watchdog = fork();
+        if watchdog == 0 {
+            sleep(TIMEOUT_MS);
+            println!("Usertests: {} timed out", test.0);
+            kill(pid, SIGKILL);
+            exit(0);
+        }
 
         let mut exit_code: i32 = Default::default();
         let wait_pid = waitpid(pid as usize, &mut exit_code);
         assert_eq!(pid, wait_pid);
+        kill(watchdog, SIGKILL);
+        let mut watchdog_exit_code: i32 = Default::default();
+        waitpid(watchdog as usize, &mut watchdog_exit_code);
         if exit_code == test.4 {
             // summary apps with  exit_code
             pass_num = pass_num + 1;
@@ -0,0 +1,79 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    exit, fork, getpid, kill, sigaction, sleep, wait, waitpid, waitpid_with, Errno, SignalAction,
+    SignalFlags, WaitFlags, SIGUSR1,
+};
+
+/// Where the kernel is mapped, above all of user space
+const KERNEL_ADDR: usize = 0xffff_ffc0_8020_0000;
+
+/// SIGUSR1s handled so far
+static HANDLED: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn handler(_signum: usize) {
+    HANDLED.fetch_add(1, Ordering::SeqCst);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // polling does not wait for a running child
+    let pid = fork();
+    if pid == 0 {
+        sleep(20);
+        exit(1);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid_with(pid, &mut exit_code, WaitFlags::NOHANG), 0);
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 1);
+
+    // a wait for one child goes on while another exits
+    let slow = fork();
+    if slow == 0 {
+        sleep(40);
+        exit(2);
+    }
+    let fast = fork();
+    if fast == 0 {
+        exit(3);
+    }
+    assert_eq!(waitpid(slow as usize, &mut exit_code), slow);
+    assert_eq!(exit_code, 2);
+    assert_eq!(waitpid(fast as usize, &mut exit_code), fast);
+    assert_eq!(exit_code, 3);
+
+    // a handler runs in between and the wait goes on
+    let action = SignalAction::new(handler as usize, SignalFlags::empty());
+    assert_eq!(sigaction(SIGUSR1, Some(&action), None), 0);
+    let parent = getpid();
+    let pid = fork();
+    if pid == 0 {
+        sleep(20);
+        kill(parent, SIGUSR1);
+        sleep(20);
+        exit(4);
+    }
+    assert_eq!(wait(&mut exit_code), pid);
+    assert_eq!(exit_code, 4);
+    assert_eq!(HANDLED.load(Ordering::SeqCst), 1);
+
+    // a status that can't be stored leaves the child to be waited for again
+    let kernel = unsafe { &mut *(KERNEL_ADDR as *mut i32) };
+    let pid = fork();
+    if pid == 0 {
+        sleep(20);
+        exit(5);
+    }
+    assert_eq!(waitpid(pid as usize, kernel), Errno::EFAULT.to_ret());
+    assert_eq!(waitpid(pid as usize, kernel), Errno::EFAULT.to_ret());
+    assert_eq!(waitpid(pid as usize, &mut exit_code), pid);
+    assert_eq!(exit_code, 5);
+    println!("waittest passed!");
+    0
+}
@@ -9,7 +9,15 @@ struct Stdout;
 
 impl Write for Stdout {
     fn write_str(&mut self, s: &str) -> fmt::Result {
-        write(STDOUT, s.as_bytes());
+        // a pipe may take only part of it
+        let mut buf = s.as_bytes();
+        while !buf.is_empty() {
+            let len = write(STDOUT, buf);
+            if len <= 0 {
+                return Err(fmt::Error);
+            }
+            buf = &buf[len as usize..];
+        }
         Ok(())
     }
 }
@@ -0,0 +1,101 @@
+//! Error numbers of failed syscalls, a syscall that fails returns its
+//! errno negated. Each thread also keeps the error of its last failed
+//! syscall, see [`errno`].
+
+use core::cell::Cell;
+
+/// Set by every syscall of the thread that fails
+#[thread_local]
+static ERRNO: Cell<Option<Errno>> = Cell::new(None);
+
+/// The error of the last syscall of this thread that failed, None if none
+/// has or its errno is not known here
+pub fn errno() -> Option<Errno> {
+    ERRNO.get()
+}
+
+/// Keep the error of a syscall that returned `ret`, if it failed
+pub(crate) fn set_errno(ret: isize) {
+    if let Some(errno) = Errno::from_ret(ret) {
+        ERRNO.set(Some(errno));
+    }
+}
+
+/// Why a syscall failed, the value is the Linux errno
+#[repr(isize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Errno {
+    EPERM = 1,
+    ENOENT = 2,
+    ESRCH = 3,
+    EINTR = 4,
+    E2BIG = 7,
+    ENOEXEC = 8,
+    EBADF = 9,
+    ECHILD = 10,
+    EAGAIN = 11,
+    ENOMEM = 12,
+    EACCES = 13,
+    EFAULT = 14,
+    EBUSY = 16,
+    EEXIST = 17,
+    ENODEV = 19,
+    ENOTDIR = 20,
+    EISDIR = 21,
+    EINVAL = 22,
+    ENOTTY = 25,
+    EFBIG = 27,
+    ENOSPC = 28,
+    ESPIPE = 29,
+    EPIPE = 32,
+    ERANGE = 34,
+    EDEADLK = 35,
+    ENOSYS = 38,
+    ENOTEMPTY = 39,
+}
+
+impl Errno {
+    const ALL: [Self; 27] = [
+        Self::EPERM,
+        Self::ENOENT,
+        Self::ESRCH,
+        Self::EINTR,
+        Self::E2BIG,
+        Self::ENOEXEC,
+        Self::EBADF,
+        Self::ECHILD,
+        Self::EAGAIN,
+        Self::ENOMEM,
+        Self::EACCES,
+        Self::EFAULT,
+        Self::EBUSY,
+        Self::EEXIST,
+        Self::ENODEV,
+        Self::ENOTDIR,
+        Self::EISDIR,
+        Self::EINVAL,
+        Self::ENOTTY,
+        Self::EFBIG,
+        Self::ENOSPC,
+        Self::ESPIPE,
+        Self::EPIPE,
+        Self::ERANGE,
+        Self::EDEADLK,
+        Self::ENOSYS,
+        Self::ENOTEMPTY,
+    ];
+
+    /// The error of a syscall that returned `ret`, None if it succeeded
+    /// or failed with an errno not known here
+    pub fn from_ret(ret: isize) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|errno| errno.to_ret() == ret)
+    }
+
+    /// What a syscall failing with this error returns
+    pub fn to_ret(self) -> isize {
+        -(self as isize)
+    }
+}
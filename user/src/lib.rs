@@ -1,17 +1,23 @@
 #![no_std]
 #![feature(linkage)]
 #![feature(alloc_error_handler)]
+#![feature(thread_local)]
 
 #[macro_use]
 pub mod console;
+pub mod errno;
 mod lang_items;
+pub mod sync;
 mod syscall;
 
 extern crate alloc;
 #[macro_use]
 extern crate bitflags;
 
-use buddy_system_allocator::LockedHeap;
+use alloc::vec::Vec;
+use buddy_system_allocator::{Heap, LockedHeapWithRescue};
+use core::sync::atomic::AtomicU32;
+pub use errno::{errno, Errno};
 use syscall::*;
 
 const USER_HEAP_SIZE: usize = 32768;
@@ -19,7 +25,18 @@ const USER_HEAP_SIZE: usize = 32768;
 static mut HEAP_SPACE: [u8; USER_HEAP_SIZE] = [0; USER_HEAP_SIZE];
 
 #[global_allocator]
-static HEAP: LockedHeap = LockedHeap::empty();
+static HEAP: LockedHeapWithRescue = LockedHeapWithRescue::new(heap_rescue);
+
+/// Called when the heap runs out, doubles it with memory from sbrk.
+fn heap_rescue(heap: &mut Heap) {
+    let size = heap.stats_total_bytes().max(USER_HEAP_SIZE);
+    let start = sbrk(size as isize);
+    if start >= 0 {
+        unsafe {
+            heap.add_to_heap(start as usize, start as usize + size);
+        }
+    }
+}
 
 #[alloc_error_handler]
 pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
@@ -28,20 +45,55 @@ pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
 
 #[no_mangle]
 #[link_section = ".text.entry"]
-pub extern "C" fn _start() -> ! {
+pub extern "C" fn _start(argc: usize, argv: usize) -> ! {
     unsafe {
         HEAP.lock()
             .init(&raw mut HEAP_SPACE as usize, USER_HEAP_SIZE);
     }
-    exit(main());
+    let mut v: Vec<&'static str> = Vec::new();
+    for i in 0..argc {
+        let str_start =
+            unsafe { ((argv + i * core::mem::size_of::<usize>()) as *const usize).read_volatile() };
+        let len = (0usize..)
+            .find(|i| unsafe { ((str_start + *i) as *const u8).read_volatile() == 0 })
+            .unwrap();
+        v.push(
+            core::str::from_utf8(unsafe {
+                core::slice::from_raw_parts(str_start as *const u8, len)
+            })
+            .unwrap(),
+        );
+    }
+    exit(main(argc, v.as_slice()));
 }
 
 #[linkage = "weak"]
 #[no_mangle]
-fn main() -> i32 {
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
     panic!("Cannot find main!");
 }
 
+/// `dirfd` of the `*at` syscalls for paths relative to the current directory
+pub const AT_FDCWD: isize = -100;
+
+pub const F_GETFD: usize = 1;
+pub const F_SETFD: usize = 2;
+/// Descriptor flag of `F_GETFD` and `F_SETFD`
+pub const FD_CLOEXEC: usize = 1;
+
+/// Gets the settings of a terminal
+pub const TCGETS: usize = 0x5401;
+/// Sets the settings of a terminal
+pub const TCSETS: usize = 0x5402;
+/// Gets the foreground process group of a terminal
+pub const TIOCGPGRP: usize = 0x540f;
+/// Sets the foreground process group of a terminal
+pub const TIOCSPGRP: usize = 0x5410;
+
+pub const SEEK_SET: usize = 0;
+pub const SEEK_CUR: usize = 1;
+pub const SEEK_END: usize = 2;
+
 bitflags! {
     pub struct OpenFlags: u32 {
         const RDONLY = 0;
@@ -49,21 +101,352 @@ bitflags! {
         const RDWR = 1 << 1;
         const CREATE = 1 << 9;
         const TRUNC = 1 << 10;
+        const APPEND = 1 << 11;
+        const CLOEXEC = 1 << 19;
+    }
+}
+
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+/// `clock_nanosleep` sleeps until an absolute time
+pub const TIMER_ABSTIME: usize = 1;
+
+/// Round robin, one tick each
+pub const SCHED_RR: usize = 0;
+/// CPU time in proportion to the weight the niceness gives
+pub const SCHED_STRIDE: usize = 1;
+/// Multi-level feedback queue
+pub const SCHED_MLFQ: usize = 2;
+
+/// A time in seconds and nanoseconds, clocks count from boot
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+impl TimeSpec {
+    pub fn from_ms(ms: usize) -> Self {
+        Self {
+            sec: ms / 1000,
+            nsec: ms % 1000 * 1_000_000,
+        }
+    }
+}
+
+/// Indices of the control characters in `Termios::cc`
+pub const VINTR: usize = 0;
+pub const VQUIT: usize = 1;
+pub const VERASE: usize = 2;
+pub const VKILL: usize = 3;
+pub const VEOF: usize = 4;
+pub const VTIME: usize = 5;
+pub const VMIN: usize = 6;
+pub const VSUSP: usize = 10;
+pub const VWERASE: usize = 14;
+
+bitflags! {
+    /// Input modes of a terminal
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct InputFlags: u32 {
+        const IGNCR = 0o200;
+        const ICRNL = 0o400;
+    }
+}
+
+bitflags! {
+    /// Local modes of a terminal
+    #[derive(Default)]
+    #[repr(transparent)]
+    pub struct LocalFlags: u32 {
+        const ISIG = 0o1;
+        const ICANON = 0o2;
+        const ECHO = 0o10;
+        const ECHOE = 0o20;
+        const ECHOK = 0o40;
+        const ECHONL = 0o100;
+        const ECHOCTL = 0o1000;
+        const IEXTEN = 0o100000;
+    }
+}
+
+/// Settings of a terminal, see `tcgetattr`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Termios {
+    pub iflag: InputFlags,
+    pub oflag: u32,
+    pub cflag: u32,
+    pub lflag: LocalFlags,
+    pub line: u8,
+    pub cc: [u8; 19],
+}
+
+impl Termios {
+    /// No line editing, echo or signal characters, and reads return as
+    /// soon as there is a byte
+    pub fn make_raw(&mut self) {
+        self.iflag -= InputFlags::ICRNL | InputFlags::IGNCR;
+        self.lflag -= LocalFlags::ISIG | LocalFlags::ICANON | LocalFlags::ECHO | LocalFlags::IEXTEN;
+        self.cc[VMIN] = 1;
+        self.cc[VTIME] = 0;
+    }
+}
+
+bitflags! {
+    /// Options of `waitpid_with`
+    pub struct WaitFlags: usize {
+        /// Returns 0 instead of waiting for a running child
+        const NOHANG = 1 << 0;
+        /// Returns a stopped child too, see `stop_signal`
+        const UNTRACED = 1 << 1;
+    }
+}
+
+/// The signal that stopped a child, if `exit_code` from `waitpid_with`
+/// reports a stop rather than an exit
+pub fn stop_signal(exit_code: i32) -> Option<usize> {
+    (exit_code > 0 && exit_code & 0xff == 0x7f).then(|| (exit_code >> 8) as usize)
+}
+
+bitflags! {
+    pub struct ProtFlags: usize {
+        const NONE = 0;
+        const READ = 1 << 0;
+        const WRITE = 1 << 1;
+        const EXEC = 1 << 2;
+    }
+}
+
+bitflags! {
+    pub struct MapFlags: usize {
+        const SHARED = 1 << 0;
+        const PRIVATE = 1 << 1;
+        const FIXED = 1 << 4;
+        const ANONYMOUS = 1 << 5;
+    }
+}
+
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGTRAP: usize = 5;
+pub const SIGABRT: usize = 6;
+pub const SIGBUS: usize = 7;
+pub const SIGFPE: usize = 8;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+pub const SIGTSTP: usize = 20;
+pub const SIGTTIN: usize = 21;
+pub const SIGTTOU: usize = 22;
+
+/// Handler of the default action
+pub const SIG_DFL: usize = 0;
+/// Handler that ignores the signal
+pub const SIG_IGN: usize = 1;
+
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+bitflags! {
+    /// A set of signals, bit n stands for signal n
+    #[derive(Default)]
+    pub struct SignalFlags: u64 {
+        const SIGHUP = 1 << SIGHUP;
+        const SIGINT = 1 << SIGINT;
+        const SIGQUIT = 1 << SIGQUIT;
+        const SIGILL = 1 << SIGILL;
+        const SIGTRAP = 1 << SIGTRAP;
+        const SIGABRT = 1 << SIGABRT;
+        const SIGBUS = 1 << SIGBUS;
+        const SIGFPE = 1 << SIGFPE;
+        const SIGKILL = 1 << SIGKILL;
+        const SIGUSR1 = 1 << SIGUSR1;
+        const SIGSEGV = 1 << SIGSEGV;
+        const SIGUSR2 = 1 << SIGUSR2;
+        const SIGPIPE = 1 << SIGPIPE;
+        const SIGALRM = 1 << SIGALRM;
+        const SIGTERM = 1 << SIGTERM;
+        const SIGCHLD = 1 << SIGCHLD;
+        const SIGCONT = 1 << SIGCONT;
+        const SIGSTOP = 1 << SIGSTOP;
+        const SIGTSTP = 1 << SIGTSTP;
+        const SIGTTIN = 1 << SIGTTIN;
+        const SIGTTOU = 1 << SIGTTOU;
+    }
+}
+
+/// What the process does on a signal
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SignalAction {
+    /// `SIG_DFL`, `SIG_IGN` or the address of an `extern "C" fn(usize)`,
+    /// which gets the signal number
+    pub handler: usize,
+    /// Signals blocked while the handler runs, besides the signal itself
+    pub mask: SignalFlags,
+    /// Filled in by `sigaction`
+    restorer: usize,
+}
+
+impl SignalAction {
+    pub fn new(handler: usize, mask: SignalFlags) -> Self {
+        Self {
+            handler,
+            mask,
+            restorer: 0,
+        }
+    }
+}
+
+impl Default for SignalAction {
+    fn default() -> Self {
+        Self::new(SIG_DFL, SignalFlags::empty())
+    }
+}
+
+/// Metadata of a file, times are in milliseconds since boot
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Stat {
+    pub ino: u64,
+    /// File type and permission bits
+    pub mode: u32,
+    pub nlink: u32,
+    pub size: u64,
+    pub atime: u64,
+    pub mtime: u64,
+    pub ctime: u64,
+}
+
+impl Stat {
+    pub const S_IFMT: u32 = 0o170000;
+    pub const S_IFDIR: u32 = 0o040000;
+    pub const S_IFREG: u32 = 0o100000;
+
+    pub fn is_dir(&self) -> bool {
+        self.mode & Self::S_IFMT == Self::S_IFDIR
     }
 }
 
 pub fn open(path: &str, flags: OpenFlags) -> isize {
     sys_open(path, flags.bits)
 }
+/// Only files in the filesystem have metadata.
+pub fn fstat(fd: usize, st: &mut Stat) -> isize {
+    sys_fstat(fd, st)
+}
+pub fn stat(path: &str, st: &mut Stat) -> isize {
+    sys_fstatat(AT_FDCWD, path, st)
+}
+pub fn mkdir(path: &str) -> isize {
+    sys_mkdir(path)
+}
+/// Only an empty directory can be removed.
+pub fn rmdir(path: &str) -> isize {
+    sys_rmdir(path)
+}
+/// Remove a link to a file, the file is released with its last link.
+pub fn unlink(path: &str) -> isize {
+    sys_unlinkat(AT_FDCWD, path, 0)
+}
+pub fn link(old_path: &str, new_path: &str) -> isize {
+    sys_linkat(AT_FDCWD, old_path, AT_FDCWD, new_path, 0)
+}
+pub fn rename(old_path: &str, new_path: &str) -> isize {
+    sys_renameat(AT_FDCWD, old_path, AT_FDCWD, new_path)
+}
+pub fn chdir(path: &str) -> isize {
+    sys_chdir(path)
+}
+/// Returns the length of the path including the terminating 0, fails with
+/// `Errno::ERANGE` if `buf` is too small.
+pub fn getcwd(buf: &mut [u8]) -> isize {
+    sys_getcwd(buf)
+}
+pub fn dup(fd: usize) -> isize {
+    sys_dup(fd)
+}
+/// Makes `newfd` refer to the file of `oldfd`, closing what it referred to.
+pub fn dup2(oldfd: usize, newfd: usize) -> isize {
+    if oldfd == newfd {
+        // nothing to duplicate, but `oldfd` has to be open
+        return if fcntl(oldfd, F_GETFD, 0) < 0 {
+            Errno::EBADF.to_ret()
+        } else {
+            newfd as isize
+        };
+    }
+    sys_dup3(oldfd, newfd, 0)
+}
+/// Like `dup2`, `flags` may be `OpenFlags::CLOEXEC`.
+pub fn dup3(oldfd: usize, newfd: usize, flags: OpenFlags) -> isize {
+    sys_dup3(oldfd, newfd, flags.bits)
+}
+pub fn fcntl(fd: usize, cmd: usize, arg: usize) -> isize {
+    sys_fcntl(fd, cmd, arg)
+}
+pub fn ioctl(fd: usize, cmd: usize, arg: usize) -> isize {
+    sys_ioctl(fd, cmd, arg)
+}
+/// Stores the settings of the terminal `fd` in `termios`.
+pub fn tcgetattr(fd: usize, termios: &mut Termios) -> isize {
+    ioctl(fd, TCGETS, termios as *mut _ as usize)
+}
+/// Changes the settings of the terminal `fd` to `termios`.
+pub fn tcsetattr(fd: usize, termios: &Termios) -> isize {
+    ioctl(fd, TCSETS, termios as *const _ as usize)
+}
+/// The foreground process group of the terminal `fd`
+pub fn tcgetpgrp(fd: usize) -> isize {
+    let mut pgid = 0i32;
+    match ioctl(fd, TIOCGPGRP, &mut pgid as *mut _ as usize) {
+        0 => pgid as isize,
+        err => err,
+    }
+}
+/// Brings the process group `pgid` to the foreground of the terminal `fd`.
+pub fn tcsetpgrp(fd: usize, pgid: usize) -> isize {
+    let pgid = pgid as i32;
+    ioctl(fd, TIOCSPGRP, &pgid as *const _ as usize)
+}
 pub fn close(fd: usize) -> isize {
     sys_close(fd)
 }
+/// Stores the read end in `pipe_fd[0]` and the write end in `pipe_fd[1]`.
+pub fn pipe(pipe_fd: &mut [usize]) -> isize {
+    sys_pipe(pipe_fd)
+}
 pub fn read(fd: usize, buf: &mut [u8]) -> isize {
     sys_read(fd, buf)
 }
 pub fn write(fd: usize, buf: &[u8]) -> isize {
     sys_write(fd, buf)
 }
+/// Returns the new offset.
+pub fn lseek(fd: usize, offset: isize, whence: usize) -> isize {
+    sys_lseek(fd, offset, whence)
+}
+/// Reads at `offset` without moving the offset of the file.
+pub fn pread(fd: usize, buf: &mut [u8], offset: usize) -> isize {
+    sys_pread64(fd, buf, offset)
+}
+/// Writes at `offset` without moving the offset of the file.
+pub fn pwrite(fd: usize, buf: &[u8], offset: usize) -> isize {
+    sys_pwrite64(fd, buf, offset)
+}
 pub fn exit(exit_code: i32) -> ! {
     sys_exit(exit_code);
 }
@@ -74,28 +457,154 @@ pub fn abort() -> ! {
 pub fn yield_() -> isize {
     sys_yield()
 }
+/// Sets the niceness of the process `pid`, 0 for the caller, from -20 for
+/// the most CPU time to 19 for the least. Only the stride scheduler heeds
+/// it. Fails with `Errno::EPERM` to lower the niceness of another process
+/// or below 0, which only initproc may.
+pub fn set_priority(pid: usize, nice: isize) -> isize {
+    sys_set_priority(pid, nice)
+}
+/// Switches the whole system to the scheduling `policy`, one of the
+/// `SCHED_*`. Returns the policy before, fails with `Errno::EPERM` unless
+/// the caller is initproc or the policy is already in force.
+pub fn sched_setscheduler(policy: usize) -> isize {
+    sys_sched_setscheduler(policy)
+}
+/// Sends signal `signum` to the process `pid`, or to the process group
+/// `-pid` if `pid` is negative. Signal 0 only checks that it exists.
+pub fn kill(pid: isize, signum: usize) -> isize {
+    sys_kill(pid, signum)
+}
+/// Stores the old action in `old_action` if it is given. The action of
+/// SIGKILL and SIGSTOP can't be changed.
+pub fn sigaction(
+    signum: usize,
+    action: Option<&SignalAction>,
+    old_action: Option<&mut SignalAction>,
+) -> isize {
+    let action = action.map(|action| SignalAction {
+        restorer: sigreturn_trampoline(),
+        ..*action
+    });
+    sys_sigaction(
+        signum,
+        action
+            .as_ref()
+            .map_or(core::ptr::null(), |action| action as *const _),
+        old_action.map_or(core::ptr::null_mut(), |action| action as *mut _),
+    )
+}
+/// `how` is one of `SIG_BLOCK`, `SIG_UNBLOCK` and `SIG_SETMASK`. Stores the
+/// old mask in `old_set` if it is given.
+pub fn sigprocmask(
+    how: usize,
+    set: Option<SignalFlags>,
+    old_set: Option<&mut SignalFlags>,
+) -> isize {
+    sys_sigprocmask(
+        how,
+        set.as_ref()
+            .map_or(core::ptr::null(), |set| set as *const _),
+        old_set.map_or(core::ptr::null_mut(), |set| set as *mut _),
+    )
+}
 pub fn get_time() -> isize {
     sys_get_time()
 }
 pub fn getpid() -> isize {
     sys_getpid()
 }
+/// Moves the process `pid` into the group `pgid`, 0 stands for the caller
+/// and for `pid` respectively.
+pub fn setpgid(pid: usize, pgid: usize) -> isize {
+    sys_setpgid(pid, pgid)
+}
+pub fn getpgid(pid: usize) -> isize {
+    sys_getpgid(pid)
+}
+pub fn fork() -> isize {
+    sys_fork()
+}
+/// `args` and `envs` are arrays of C strings, each ended by a null pointer.
+pub fn exec(path: &str, args: &[*const u8], envs: &[*const u8]) -> isize {
+    sys_exec(path, args, envs)
+}
 pub fn spawn(path: &str) -> isize {
     sys_spawn(path)
 }
+/// `fd` is ignored for an anonymous mapping.
+pub fn mmap(
+    addr: usize,
+    len: usize,
+    prot: ProtFlags,
+    flags: MapFlags,
+    fd: usize,
+    offset: usize,
+) -> isize {
+    sys_mmap(addr, len, prot.bits, flags.bits, fd, offset)
+}
+pub fn munmap(addr: usize, len: usize) -> isize {
+    sys_munmap(addr, len)
+}
+pub fn mprotect(addr: usize, len: usize, prot: ProtFlags) -> isize {
+    sys_mprotect(addr, len, prot.bits)
+}
+pub fn msync(addr: usize, len: usize) -> isize {
+    sys_msync(addr, len, 0)
+}
+/// Returns the new program break, `brk(0)` asks for the current one.
+pub fn brk(addr: usize) -> isize {
+    sys_brk(addr)
+}
+/// Returns the old program break.
+pub fn sbrk(size: isize) -> isize {
+    sys_sbrk(size)
+}
+/// Blocks until a child exits and returns its pid, `Errno::ECHILD` if
+/// there is no child.
 pub fn wait(exit_code: &mut i32) -> isize {
-    sys_waitpid(-1, exit_code as *mut _)
+    sys_waitpid(-1, exit_code as *mut _, 0)
 }
 
 pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
-    sys_waitpid(pid as isize, exit_code as *mut _)
+    sys_waitpid(pid as isize, exit_code as *mut _, 0)
 }
+/// Like `waitpid`, but returns 0 for a running child with
+/// `WaitFlags::NOHANG`, and the pid of a stopped one with
+/// `WaitFlags::UNTRACED`.
+pub fn waitpid_with(pid: isize, exit_code: &mut i32, options: WaitFlags) -> isize {
+    sys_waitpid(pid, exit_code as *mut _, options.bits)
+}
+/// Sleeps for `period_ms` milliseconds without taking the CPU, signal
+/// handlers don't end it early.
 pub fn sleep(period_ms: usize) {
-    let start = sys_get_time();
-    while sys_get_time() < start + period_ms as isize {
-        sys_yield();
+    let mut req = TimeSpec::from_ms(period_ms);
+    let mut rem = TimeSpec::default();
+    while sys_nanosleep(&req, &mut rem) == Errno::EINTR.to_ret() {
+        req = rem;
     }
 }
+/// Sleeps for `req`. A signal handler ends it with `Errno::EINTR`, the time
+/// left goes to `rem` if it is given.
+pub fn nanosleep(req: &TimeSpec, rem: Option<&mut TimeSpec>) -> isize {
+    sys_nanosleep(req, rem.map_or(core::ptr::null_mut(), |rem| rem as *mut _))
+}
+/// Sleeps for `req` on `clock`, or until `clock` reads `req` if `flags` is
+/// `TIMER_ABSTIME`. Like `nanosleep` otherwise, an absolute sleep leaves
+/// `rem` alone.
+pub fn clock_nanosleep(
+    clock: usize,
+    flags: usize,
+    req: &TimeSpec,
+    rem: Option<&mut TimeSpec>,
+) -> isize {
+    sys_clock_nanosleep(
+        clock,
+        flags,
+        req,
+        rem.map_or(core::ptr::null_mut(), |rem| rem as *mut _),
+    )
+}
 
 pub fn shutdown() {
     sys_shutdown();
@@ -111,12 +620,70 @@ pub fn mutex_unlock(mutex_id: usize) {
     sys_mutex_unlock(mutex_id);
 }
 
+pub const FUTEX_WAIT: usize = 0;
+pub const FUTEX_WAKE: usize = 1;
+pub const FUTEX_PRIVATE_FLAG: usize = 128;
+
+/// Waits on `futex` while it holds `expected`, until `futex_wake`. Returns
+/// 0 once woken up, fails with `Errno::EAGAIN` right away if the value
+/// differs and with `Errno::EINTR` if a signal interrupts the wait.
+pub fn futex_wait(futex: &AtomicU32, expected: u32) -> isize {
+    sys_futex(
+        futex.as_ptr(),
+        FUTEX_WAIT | FUTEX_PRIVATE_FLAG,
+        expected as usize,
+    )
+}
+/// Wakes at most `count` waiters of `futex`, returns how many it woke.
+pub fn futex_wake(futex: &AtomicU32, count: usize) -> isize {
+    sys_futex(futex.as_ptr(), FUTEX_WAKE | FUTEX_PRIVATE_FLAG, count)
+}
+
+/// Creates a semaphore holding `count` resources.
+pub fn semaphore_create(count: usize) -> isize {
+    sys_semaphore_create(count)
+}
+pub fn semaphore_up(sem_id: usize) {
+    sys_semaphore_up(sem_id);
+}
+/// Takes a resource, waiting until there is one.
+pub fn semaphore_down(sem_id: usize) {
+    sys_semaphore_down(sem_id);
+}
+
+pub fn condvar_create() -> isize {
+    sys_condvar_create()
+}
+pub fn condvar_signal(condvar_id: usize) {
+    sys_condvar_signal(condvar_id);
+}
+pub fn condvar_broadcast(condvar_id: usize) {
+    sys_condvar_broadcast(condvar_id);
+}
+/// Releases `mutex_id`, which the caller holds, waits for a signal and
+/// takes the mutex again. It may return without a signal, so wait in a
+/// loop checking the condition.
+pub fn condvar_wait(condvar_id: usize, mutex_id: usize) {
+    sys_condvar_wait(condvar_id, mutex_id);
+}
+
 pub fn thread_create(entry: usize, arg: usize) -> isize {
     sys_thread_create(entry, arg)
 }
 pub fn gettid() -> isize {
     sys_gettid()
 }
+/// Returns the exit code of the thread `tid` once it exits, it is
+/// released then.
 pub fn waittid(tid: usize) -> isize {
     sys_waittid(tid)
 }
+/// Ends the calling thread, `exit` ends the whole process.
+pub fn thread_exit(exit_code: i32) -> ! {
+    sys_thread_exit(exit_code)
+}
+/// Lets the thread `tid` be released as soon as it exits, it can't be
+/// waited for then.
+pub fn thread_detach(tid: usize) -> isize {
+    sys_thread_detach(tid)
+}